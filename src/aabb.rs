@@ -0,0 +1,96 @@
+use crate::{Point3, Ray};
+
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Aabb {
+    minimum: Point3,
+    maximum: Point3,
+}
+
+impl Aabb {
+    pub fn new(minimum: Point3, maximum: Point3) -> Self {
+        Aabb { minimum, maximum }
+    }
+
+    pub fn min(&self) -> Point3 {
+        self.minimum
+    }
+
+    pub fn max(&self) -> Point3 {
+        self.maximum
+    }
+
+    pub fn centroid(&self) -> Point3 {
+        0.5 * (self.minimum + self.maximum)
+    }
+
+    pub fn hit(&self, ray: &Ray, mut t_min: f64, mut t_max: f64) -> bool {
+        for axis in 0..3 {
+            let inverse_direction = 1.0 / ray.direction()[axis];
+            let mut t0 = (self.minimum[axis] - ray.origin()[axis]) * inverse_direction;
+            let mut t1 = (self.maximum[axis] - ray.origin()[axis]) * inverse_direction;
+            if inverse_direction < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            t_min = if t0 > t_min { t0 } else { t_min };
+            t_max = if t1 < t_max { t1 } else { t_max };
+            if t_max <= t_min {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    // Return the smallest box containing both boxes.
+    pub fn surrounding_box(box0: &Aabb, box1: &Aabb) -> Aabb {
+        let small = Point3::new(
+            box0.minimum.x().min(box1.minimum.x()),
+            box0.minimum.y().min(box1.minimum.y()),
+            box0.minimum.z().min(box1.minimum.z()),
+        );
+        let big = Point3::new(
+            box0.maximum.x().max(box1.maximum.x()),
+            box0.maximum.y().max(box1.maximum.y()),
+            box0.maximum.z().max(box1.maximum.z()),
+        );
+
+        Aabb::new(small, big)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Vec3;
+
+    #[test]
+    fn test_hit() {
+        let aabb = Aabb::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+        let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+
+        assert!(aabb.hit(&ray, 0.001, f64::INFINITY));
+        assert!(!aabb.hit(&ray, 0.001, 3.0)); // box is further than t_max
+    }
+
+    #[test]
+    fn test_miss() {
+        let aabb = Aabb::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+        let parallel = Ray::new(Point3::new(2.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let away = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, 1.0), 0.0);
+
+        assert!(!aabb.hit(&parallel, 0.001, f64::INFINITY));
+        assert!(!aabb.hit(&away, 0.001, f64::INFINITY));
+    }
+
+    #[test]
+    fn test_surrounding_box() {
+        let box0 = Aabb::new(Point3::new(-1.0, 0.0, 0.0), Point3::new(0.0, 1.0, 1.0));
+        let box1 = Aabb::new(Point3::new(0.0, -2.0, 0.0), Point3::new(3.0, 0.0, 0.5));
+
+        let expected = Aabb::new(Point3::new(-1.0, -2.0, 0.0), Point3::new(3.0, 1.0, 1.0));
+
+        assert_eq!(expected, Aabb::surrounding_box(&box0, &box1));
+        assert_eq!(Point3::new(1.0, -0.5, 0.5), expected.centroid());
+    }
+}
@@ -0,0 +1,428 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::sampler::Sampler;
+use crate::{dot, Point3, Ray, Vec3};
+
+// Rectangles are infinitely thin, their boxes are padded to keep a non-zero width on every axis.
+const PADDING: f64 = 0.0001;
+
+// Intersection with the rectangle [a0, a1] x [b0, b1] lying in the plane `axis == k`.
+// `a` and `b` are the indices of the two other axes.
+fn hit_rect<'a>(
+    ray: &Ray,
+    t_min: f64,
+    t_max: f64,
+    (a, b, axis): (i32, i32, i32),
+    (a0, a1, b0, b1, k): (f64, f64, f64, f64, f64),
+    outward_normal: Vec3,
+    material: &'a dyn Material,
+) -> Option<HitRecord<'a>> {
+    let t = (k - ray.origin()[axis]) / ray.direction()[axis];
+    if !(t_min..=t_max).contains(&t) {
+        return None;
+    }
+
+    let point = ray.at(t);
+    if point[a] < a0 || point[a] > a1 || point[b] < b0 || point[b] > b1 {
+        return None;
+    }
+
+    let mut hit = HitRecord {
+        point,
+        normal: Vec3::default(),
+        t,
+        u: (point[a] - a0) / (a1 - a0),
+        v: (point[b] - b0) / (b1 - b0),
+        front_face: false,
+        material,
+    };
+    hit.set_face_normal(ray, &outward_normal);
+
+    Some(hit)
+}
+
+// Density over solid angle of the directions from `origin` towards a uniformly chosen point of
+// `rect`, of area `area`.
+fn rect_pdf_value(rect: &dyn Hittable, area: f64, origin: &Point3, direction: &Vec3) -> f64 {
+    let Some(hit) = rect.hit(&Ray::new(*origin, *direction, 0.0), 0.001, f64::INFINITY) else {
+        return 0.0;
+    };
+
+    let distance_squared = hit.t * hit.t * direction.length_squared();
+    let cosine = (dot(direction, &hit.normal) / direction.length()).abs();
+    if cosine < 1e-12 {
+        return 0.0;
+    }
+    distance_squared / (cosine * area)
+}
+
+// Direction from `origin` to a uniformly chosen point of the rectangle, laid out as in `hit_rect`.
+fn rect_random(
+    origin: &Point3,
+    (a, b, axis): (i32, i32, i32),
+    (a0, a1, b0, b1, k): (f64, f64, f64, f64, f64),
+    sampler: &mut dyn Sampler,
+) -> Vec3 {
+    let (u, v) = sampler.next_2d();
+    let mut point = [0.0; 3];
+    point[a as usize] = a0 + u * (a1 - a0);
+    point[b as usize] = b0 + v * (b1 - b0);
+    point[axis as usize] = k;
+    Point3::new(point[0], point[1], point[2]) - *origin
+}
+
+// Uniformly chosen point of the rectangle, laid out as in `hit_rect`, with its density over area.
+fn sample_rect<'a>(
+    (a, b, axis): (i32, i32, i32),
+    (a0, a1, b0, b1, k): (f64, f64, f64, f64, f64),
+    outward_normal: Vec3,
+    material: &'a dyn Material,
+    sampler: &mut dyn Sampler,
+) -> (HitRecord<'a>, f64) {
+    let (u, v) = sampler.next_2d();
+    let mut point = [0.0; 3];
+    point[a as usize] = a0 + u * (a1 - a0);
+    point[b as usize] = b0 + v * (b1 - b0);
+    point[axis as usize] = k;
+    let hit = HitRecord {
+        point: Point3::new(point[0], point[1], point[2]),
+        normal: outward_normal,
+        t: 0.0,
+        u,
+        v,
+        front_face: true,
+        material,
+    };
+    (hit, 1.0 / ((a1 - a0) * (b1 - b0)))
+}
+
+fn rect_surface_pdf(
+    point: &Point3,
+    (a, b, axis): (i32, i32, i32),
+    (a0, a1, b0, b1, k): (f64, f64, f64, f64, f64),
+) -> f64 {
+    if (point[axis] - k).abs() > 1e-6 * (1.0 + k.abs())
+        || point[a] < a0
+        || point[a] > a1
+        || point[b] < b0
+        || point[b] > b1
+    {
+        return 0.0;
+    }
+    1.0 / ((a1 - a0) * (b1 - b0))
+}
+
+pub struct XYRect<Mat: Material> {
+    x0: f64,
+    x1: f64,
+    y0: f64,
+    y1: f64,
+    k: f64,
+    material: Mat,
+}
+
+impl<Mat: Material> XYRect<Mat> {
+    pub fn new(x0: f64, x1: f64, y0: f64, y1: f64, k: f64, material: Mat) -> Self {
+        XYRect {
+            x0,
+            x1,
+            y0,
+            y1,
+            k,
+            material,
+        }
+    }
+}
+
+impl<Mat: Material> Hittable for XYRect<Mat> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        hit_rect(
+            ray,
+            t_min,
+            t_max,
+            (0, 1, 2),
+            (self.x0, self.x1, self.y0, self.y1, self.k),
+            Vec3::new(0.0, 0.0, 1.0),
+            &self.material,
+        )
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<Aabb> {
+        Some(Aabb::new(
+            Point3::new(self.x0, self.y0, self.k - PADDING),
+            Point3::new(self.x1, self.y1, self.k + PADDING),
+        ))
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        let area = (self.x1 - self.x0) * (self.y1 - self.y0);
+        rect_pdf_value(self, area, origin, direction)
+    }
+
+    fn random(&self, origin: &Point3, sampler: &mut dyn Sampler) -> Vec3 {
+        rect_random(
+            origin,
+            (0, 1, 2),
+            (self.x0, self.x1, self.y0, self.y1, self.k),
+            sampler,
+        )
+    }
+
+    fn sample_surface(&self, sampler: &mut dyn Sampler) -> Option<(HitRecord<'_>, f64)> {
+        Some(sample_rect(
+            (0, 1, 2),
+            (self.x0, self.x1, self.y0, self.y1, self.k),
+            Vec3::new(0.0, 0.0, 1.0),
+            &self.material,
+            sampler,
+        ))
+    }
+
+    fn surface_pdf(&self, point: &Point3) -> f64 {
+        rect_surface_pdf(
+            point,
+            (0, 1, 2),
+            (self.x0, self.x1, self.y0, self.y1, self.k),
+        )
+    }
+}
+
+pub struct XZRect<Mat: Material> {
+    x0: f64,
+    x1: f64,
+    z0: f64,
+    z1: f64,
+    k: f64,
+    material: Mat,
+}
+
+impl<Mat: Material> XZRect<Mat> {
+    pub fn new(x0: f64, x1: f64, z0: f64, z1: f64, k: f64, material: Mat) -> Self {
+        XZRect {
+            x0,
+            x1,
+            z0,
+            z1,
+            k,
+            material,
+        }
+    }
+}
+
+impl<Mat: Material> Hittable for XZRect<Mat> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        hit_rect(
+            ray,
+            t_min,
+            t_max,
+            (0, 2, 1),
+            (self.x0, self.x1, self.z0, self.z1, self.k),
+            Vec3::new(0.0, 1.0, 0.0),
+            &self.material,
+        )
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<Aabb> {
+        Some(Aabb::new(
+            Point3::new(self.x0, self.k - PADDING, self.z0),
+            Point3::new(self.x1, self.k + PADDING, self.z1),
+        ))
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        let area = (self.x1 - self.x0) * (self.z1 - self.z0);
+        rect_pdf_value(self, area, origin, direction)
+    }
+
+    fn random(&self, origin: &Point3, sampler: &mut dyn Sampler) -> Vec3 {
+        rect_random(
+            origin,
+            (0, 2, 1),
+            (self.x0, self.x1, self.z0, self.z1, self.k),
+            sampler,
+        )
+    }
+
+    fn sample_surface(&self, sampler: &mut dyn Sampler) -> Option<(HitRecord<'_>, f64)> {
+        Some(sample_rect(
+            (0, 2, 1),
+            (self.x0, self.x1, self.z0, self.z1, self.k),
+            Vec3::new(0.0, 1.0, 0.0),
+            &self.material,
+            sampler,
+        ))
+    }
+
+    fn surface_pdf(&self, point: &Point3) -> f64 {
+        rect_surface_pdf(
+            point,
+            (0, 2, 1),
+            (self.x0, self.x1, self.z0, self.z1, self.k),
+        )
+    }
+}
+
+pub struct YZRect<Mat: Material> {
+    y0: f64,
+    y1: f64,
+    z0: f64,
+    z1: f64,
+    k: f64,
+    material: Mat,
+}
+
+impl<Mat: Material> YZRect<Mat> {
+    pub fn new(y0: f64, y1: f64, z0: f64, z1: f64, k: f64, material: Mat) -> Self {
+        YZRect {
+            y0,
+            y1,
+            z0,
+            z1,
+            k,
+            material,
+        }
+    }
+}
+
+impl<Mat: Material> Hittable for YZRect<Mat> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        hit_rect(
+            ray,
+            t_min,
+            t_max,
+            (1, 2, 0),
+            (self.y0, self.y1, self.z0, self.z1, self.k),
+            Vec3::new(1.0, 0.0, 0.0),
+            &self.material,
+        )
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<Aabb> {
+        Some(Aabb::new(
+            Point3::new(self.k - PADDING, self.y0, self.z0),
+            Point3::new(self.k + PADDING, self.y1, self.z1),
+        ))
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        let area = (self.y1 - self.y0) * (self.z1 - self.z0);
+        rect_pdf_value(self, area, origin, direction)
+    }
+
+    fn random(&self, origin: &Point3, sampler: &mut dyn Sampler) -> Vec3 {
+        rect_random(
+            origin,
+            (1, 2, 0),
+            (self.y0, self.y1, self.z0, self.z1, self.k),
+            sampler,
+        )
+    }
+
+    fn sample_surface(&self, sampler: &mut dyn Sampler) -> Option<(HitRecord<'_>, f64)> {
+        Some(sample_rect(
+            (1, 2, 0),
+            (self.y0, self.y1, self.z0, self.z1, self.k),
+            Vec3::new(1.0, 0.0, 0.0),
+            &self.material,
+            sampler,
+        ))
+    }
+
+    fn surface_pdf(&self, point: &Point3) -> f64 {
+        rect_surface_pdf(
+            point,
+            (1, 2, 0),
+            (self.y0, self.y1, self.z0, self.z1, self.k),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::pdf_integral;
+    use crate::sampler::IndependentSampler;
+    use crate::{Color, Lambertian};
+
+    fn white() -> Lambertian {
+        Lambertian::new(&Color::new(1.0, 1.0, 1.0))
+    }
+
+    #[test]
+    fn test_xy_rect() {
+        let rect = XYRect::new(-1.0, 1.0, -1.0, 1.0, -2.0, white());
+
+        let hit = rect
+            .hit(
+                &Ray::new(Point3::new(0.5, 0.5, 0.0), Vec3::new(0.0, 0.0, -1.0), 0.0),
+                0.001,
+                f64::INFINITY,
+            )
+            .unwrap();
+        assert_eq!(2.0, hit.t);
+        assert_eq!(Point3::new(0.5, 0.5, -2.0), hit.point);
+        assert_eq!(Vec3::new(0.0, 0.0, 1.0), hit.normal);
+        assert!(hit.front_face);
+
+        let outside = Ray::new(Point3::new(1.5, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        assert!(rect.hit(&outside, 0.001, f64::INFINITY).is_none());
+        let parallel = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), 0.0);
+        assert!(rect.hit(&parallel, 0.001, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_xz_rect_back_face() {
+        let rect = XZRect::new(0.0, 1.0, 0.0, 1.0, 1.0, white());
+
+        let hit = rect
+            .hit(
+                &Ray::new(Point3::new(0.5, 0.0, 0.5), Vec3::new(0.0, 1.0, 0.0), 0.0),
+                0.001,
+                f64::INFINITY,
+            )
+            .unwrap();
+        assert_eq!(1.0, hit.t);
+        assert_eq!(Vec3::new(0.0, -1.0, 0.0), hit.normal);
+        assert!(!hit.front_face);
+    }
+
+    #[test]
+    fn test_yz_rect() {
+        let rect = YZRect::new(0.0, 2.0, 0.0, 3.0, 5.0, white());
+
+        let hit = rect.hit(
+            &Ray::new(Point3::new(0.0, 1.0, 1.0), Vec3::new(1.0, 0.0, 0.0), 0.0),
+            0.001,
+            4.0,
+        );
+        assert!(hit.is_none()); // further than t_max
+
+        let expected = Aabb::new(
+            Point3::new(5.0 - PADDING, 0.0, 0.0),
+            Point3::new(5.0 + PADDING, 2.0, 3.0),
+        );
+        assert_eq!(Some(expected), rect.bounding_box(0.0, 1.0));
+    }
+
+    #[test]
+    fn test_pdf() {
+        let origin = Point3::new(0.2, 0.3, 0.1);
+        let rects: [Box<dyn Hittable>; 3] = [
+            Box::new(XYRect::new(-1.0, 1.0, -1.0, 2.0, -1.0, white())),
+            Box::new(XZRect::new(-1.0, 1.0, -1.0, 2.0, 1.0, white())),
+            Box::new(YZRect::new(-1.0, 1.0, -1.0, 2.0, -1.0, white())),
+        ];
+
+        let mut sampler = IndependentSampler::new(2);
+        for rect in rects.iter() {
+            assert!((pdf_integral(rect.as_ref(), &origin) - 1.0).abs() < 0.02);
+
+            for index in 0..100 {
+                sampler.start_sample(0, index);
+                let direction = rect.random(&origin, &mut sampler);
+                assert!(rect.pdf_value(&origin, &direction) > 0.0);
+            }
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::str::FromStr;
+
+use crate::{Color, Ray};
+
+// Color seen by the rays escaping the scene.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Background {
+    // Vertical gradient from white to light blue.
+    Sky,
+    Solid(Color),
+}
+
+impl Background {
+    pub fn color(&self, ray: &Ray) -> Color {
+        match self {
+            Background::Sky => {
+                let white = Color::new(1.0, 1.0, 1.0);
+                let light_blue = Color::new(0.5, 0.7, 1.0);
+
+                let unit_direction = ray.direction().unit_vector();
+                let t = 0.5 * (unit_direction.y() + 1.0);
+
+                linear_blend(t, white, light_blue)
+            }
+            Background::Solid(color) => *color,
+        }
+    }
+}
+
+impl FromStr for Background {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "sky" => return Ok(Background::Sky),
+            "black" => return Ok(Background::Solid(Color::default())),
+            _ => {}
+        }
+
+        let channels = value
+            .split(',')
+            .map(|channel| channel.trim().parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>();
+        match channels.as_deref() {
+            Ok([r, g, b]) => Ok(Background::Solid(Color::new(*r, *g, *b))),
+            _ => Err(format!(
+                "invalid background '{value}', expected sky, black or R,G,B"
+            )),
+        }
+    }
+}
+
+fn linear_blend(t: f64, start: Color, end: Color) -> Color {
+    (1.0 - t) * start + t * end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Point3, Vec3};
+
+    #[test]
+    fn linear_blend_test() {
+        let black = Color::new(0.0, 0.0, 0.0);
+        let white = Color::new(1.0, 1.0, 1.0);
+        let expected_grey = Color::new(0.5, 0.5, 0.5);
+
+        let grey_0_0 = linear_blend(0.0, white, black);
+        let grey_0_5 = linear_blend(0.5, white, black);
+        let grey_1_0 = linear_blend(1.0, white, black);
+
+        assert_eq!(white, grey_0_0);
+        assert_eq!(expected_grey, grey_0_5);
+        assert_eq!(black, grey_1_0);
+    }
+
+    #[test]
+    fn test_color() {
+        let up = Ray::new(Point3::default(), Vec3::new(0.0, 1.0, 0.0), 0.0);
+        let down = Ray::new(Point3::default(), Vec3::new(0.0, -1.0, 0.0), 0.0);
+
+        assert_eq!(Color::new(0.5, 0.7, 1.0), Background::Sky.color(&up));
+        assert_eq!(Color::new(1.0, 1.0, 1.0), Background::Sky.color(&down));
+        assert_eq!(
+            Color::new(0.1, 0.2, 0.3),
+            Background::Solid(Color::new(0.1, 0.2, 0.3)).color(&up)
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Ok(Background::Sky), "sky".parse());
+        assert_eq!(Ok(Background::Solid(Color::default())), "black".parse());
+        assert_eq!(
+            Ok(Background::Solid(Color::new(0.1, 0.2, 0.3))),
+            "0.1,0.2,0.3".parse()
+        );
+        assert!("blue".parse::<Background>().is_err());
+    }
+}
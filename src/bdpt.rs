@@ -0,0 +1,602 @@
+use crate::background::Background;
+use crate::camera::Camera;
+use crate::hittable::{HitRecord, Hittable};
+use crate::hittable_list::HittableList;
+use crate::integrator::Integrator;
+use crate::material::Scatter;
+use crate::pdf::{EmissionPdf, Pdf};
+use crate::render::{PathDepth, Splats};
+use crate::sampler::Sampler;
+use crate::{dot, Color, Point3, Ray, Vec3};
+
+// Bidirectional path tracer. A path is traced from the camera and another from a light, and every
+// vertex of one is connected to every vertex of the other, each way of building the same path
+// being weighted with multiple importance sampling. The light paths connected straight to the lens
+// are splatted to the pixel they land on. Only `depth.max` limits the paths, the per lobe limits
+// and the roulette of the path tracer are not used.
+pub struct BdptIntegrator<'a> {
+    pub depth: PathDepth,
+    pub camera: &'a Camera,
+    pub splats: &'a Splats,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum VertexKind {
+    Camera,
+    Light,
+    Surface,
+}
+
+// Vertex of a subpath. `pdf_forward` is the density over area with which the subpath chose the
+// vertex, `pdf_reverse` the density with which the other subpath would have chosen it.
+#[derive(Clone, Copy)]
+struct Vertex<'a> {
+    kind: VertexKind,
+    point: Point3,
+    // Front facing normal of a surface, outward normal of a light.
+    normal: Vec3,
+    // Surface hit or the point sampled on a light, none for the camera.
+    hit: Option<HitRecord<'a>>,
+    // Ray arriving at a surface.
+    incoming: Ray,
+    // Light or importance carried by the subpath up to the vertex, divided by its density.
+    beta: Color,
+    // Reflectance of a matte surface, the only surfaces paths are connected at.
+    attenuation: Option<Color>,
+    // Mirror or glass, choosing the one direction out.
+    delta: bool,
+    pdf_forward: f64,
+    pdf_reverse: f64,
+}
+
+impl<'a> Vertex<'a> {
+    fn camera(point: Point3, time: f64) -> Self {
+        Vertex {
+            kind: VertexKind::Camera,
+            point,
+            normal: Vec3::default(),
+            hit: None,
+            incoming: Ray::new(point, Vec3::default(), time),
+            beta: Color::new(1.0, 1.0, 1.0),
+            attenuation: None,
+            delta: false,
+            pdf_forward: 0.0,
+            pdf_reverse: 0.0,
+        }
+    }
+
+    fn light(hit: HitRecord<'a>, time: f64, area_pdf: f64) -> Self {
+        Vertex {
+            kind: VertexKind::Light,
+            point: hit.point,
+            normal: hit.normal,
+            hit: Some(hit),
+            incoming: Ray::new(hit.point, hit.normal, time),
+            beta: Color::new(1.0, 1.0, 1.0) / area_pdf,
+            attenuation: None,
+            delta: false,
+            pdf_forward: area_pdf,
+            pdf_reverse: 0.0,
+        }
+    }
+
+    fn surface(hit: HitRecord<'a>, incoming: Ray, beta: Color) -> Self {
+        Vertex {
+            kind: VertexKind::Surface,
+            point: hit.point,
+            normal: hit.normal,
+            hit: Some(hit),
+            incoming,
+            beta,
+            attenuation: None,
+            delta: false,
+            pdf_forward: 0.0,
+            pdf_reverse: 0.0,
+        }
+    }
+
+    fn is_connectible(&self) -> bool {
+        match self.kind {
+            VertexKind::Camera | VertexKind::Light => true,
+            VertexKind::Surface => self.attenuation.is_some(),
+        }
+    }
+
+    fn emitted(&self) -> Color {
+        match self.hit {
+            Some(hit) => hit.material.emitted(hit.u, hit.v, &hit.point),
+            None => Color::default(),
+        }
+    }
+
+    // Light sent from the vertex towards `target` over the density of its arrival, the surface
+    // reflectance times the cosine, or the emitted light times the cosine at a light.
+    fn reflected(&self, target: &Point3) -> Color {
+        let direction = *target - self.point;
+        match (self.kind, self.hit, self.attenuation) {
+            (VertexKind::Light, _, _) => self.emitted() * self.cosine(&direction),
+            (VertexKind::Surface, Some(hit), Some(attenuation)) => {
+                let scattered = Ray::new(self.point, direction, self.incoming.time());
+                attenuation
+                    * hit
+                        .material
+                        .scattering_pdf(&self.incoming, &hit, &scattered)
+            }
+            _ => Color::default(),
+        }
+    }
+
+    // Density over area at `next` of a density over solid angle at the vertex.
+    fn area_density(&self, pdf: f64, next: &Vertex) -> f64 {
+        let direction = next.point - self.point;
+        let distance_squared = direction.length_squared();
+        if distance_squared == 0.0 {
+            return 0.0;
+        }
+        let pdf = pdf / distance_squared;
+        match next.kind {
+            VertexKind::Camera => pdf,
+            VertexKind::Light | VertexKind::Surface => pdf * next.cosine(&direction),
+        }
+    }
+
+    // Absolute cosine between the normal and `direction`, one inside a medium where there is no
+    // surface to cross.
+    fn cosine(&self, direction: &Vec3) -> f64 {
+        if self.hit.is_some_and(|hit| hit.is_in_medium()) {
+            return 1.0;
+        }
+        dot(&self.normal, &direction.unit_vector()).abs()
+    }
+}
+
+fn emission_pdf(normal: &Vec3, direction: &Vec3) -> f64 {
+    EmissionPdf::new(normal).value(direction)
+}
+
+fn power(value: f64) -> f64 {
+    value * value
+}
+
+// Whether nothing lies on the segment between two points.
+fn is_visible(world: &dyn Hittable, from: &Point3, to: &Point3, time: f64) -> bool {
+    let direction = *to - *from;
+    let distance = direction.length();
+    let ray = Ray::new(*from, direction / distance, time);
+    world.hit(&ray, 0.001, distance - 0.001).is_none()
+}
+
+impl<'a> BdptIntegrator<'a> {
+    // Pixel, counting rows from the top, of the ray from `lens_point` through `point`.
+    fn pixel(&self, lens_point: &Point3, point: &Point3) -> Option<(u32, u32)> {
+        let (u, v) = self.camera.project(lens_point, point)?;
+        let (width, height) = (self.splats.width(), self.splats.height());
+        let x = u * (width - 1) as f64;
+        let y = v * (height - 1) as f64;
+        if !(x >= 0.0 && y >= 0.0 && x < width as f64 && y < height as f64) {
+            return None;
+        }
+        Some((x as u32, height - 1 - y as u32))
+    }
+
+    // Density over solid angle of the camera choosing `direction` from `lens_point`. The rays of
+    // the pixels cross the plane in focus uniformly, pixel coordinates going from 0 to
+    // width / (width - 1) as in `TileRenderer::render_tile`.
+    fn camera_pdf(&self, lens_point: &Point3, direction: &Vec3) -> f64 {
+        let cosine = -dot(&direction.unit_vector(), &self.camera.w);
+        if cosine <= 0.0
+            || self
+                .pixel(lens_point, &(*lens_point + *direction))
+                .is_none()
+        {
+            return 0.0;
+        }
+        let (width, height) = (self.splats.width() as f64, self.splats.height() as f64);
+        let area = self.camera.horizontal.length()
+            * self.camera.vertical.length()
+            * (width / (width - 1.0))
+            * (height / (height - 1.0));
+        power(self.camera.focus_distance()) / (area * cosine.powi(3))
+    }
+
+    // Density over area of `vertex` choosing `next`, having been reached from `previous`.
+    fn pdf(&self, vertex: &Vertex, previous: Option<&Vertex>, next: &Vertex) -> f64 {
+        let direction = next.point - vertex.point;
+        let pdf = match vertex.kind {
+            VertexKind::Camera => self.camera_pdf(&vertex.point, &direction),
+            VertexKind::Light => emission_pdf(&vertex.normal, &direction),
+            VertexKind::Surface => {
+                let (Some(hit), Some(previous), Some(_)) =
+                    (vertex.hit, previous, vertex.attenuation)
+                else {
+                    return 0.0;
+                };
+                let time = vertex.incoming.time();
+                let incoming = Ray::new(previous.point, vertex.point - previous.point, time);
+                let scattered = Ray::new(vertex.point, direction, time);
+                hit.material.scattering_pdf(&incoming, &hit, &scattered)
+            }
+        };
+        vertex.area_density(pdf, next)
+    }
+
+    // Extend `path` from its last vertex along `ray`, chosen with density `pdf` over solid angle
+    // and carrying `beta`, until it is absorbed or has `max_vertices`. Returns the ray leaving the
+    // scene, with what it carries, if the path ends that way.
+    fn random_walk<'w>(
+        &self,
+        world: &'w dyn Hittable,
+        (mut ray, mut beta, mut pdf): (Ray, Color, f64),
+        path: &mut Vec<Vertex<'w>>,
+        max_vertices: usize,
+        sampler: &mut dyn Sampler,
+    ) -> Option<(Ray, Color)> {
+        while path.len() < max_vertices {
+            let Some(hit) = world.hit(&ray, 0.001, f64::INFINITY) else {
+                return Some((ray, beta));
+            };
+            let previous = path.len() - 1;
+            let mut vertex = Vertex::surface(hit, ray, beta);
+            vertex.pdf_forward = path[previous].area_density(pdf, &vertex);
+
+            // The lights do not scatter and end the path.
+            let Some(record) = hit.material.scatter(&ray, &hit, sampler) else {
+                path.push(vertex);
+                break;
+            };
+            match record.scatter {
+                Scatter::Specular(scattered) => {
+                    vertex.delta = true;
+                    path.push(vertex);
+                    path[previous].pdf_reverse = 0.0;
+                    beta = beta * record.attenuation;
+                    pdf = 0.0;
+                    ray = scattered;
+                }
+                Scatter::Diffuse(scatter_pdf) => {
+                    vertex.attenuation = Some(record.attenuation);
+                    path.push(vertex);
+                    if path.len() == max_vertices {
+                        break;
+                    }
+
+                    let scattered = Ray::new(hit.point, scatter_pdf.generate(sampler), ray.time());
+                    let value = scatter_pdf.value(&scattered.direction());
+                    let scattering_pdf = hit.material.scattering_pdf(&ray, &hit, &scattered);
+                    if value <= 0.0 || scattering_pdf <= 0.0 {
+                        break;
+                    }
+                    beta = beta * record.attenuation * (scattering_pdf / value);
+                    pdf = scattering_pdf;
+
+                    // The same bounce taken the other way, from the next vertex.
+                    let reversed = Ray::new(
+                        hit.point + scattered.direction(),
+                        -scattered.direction(),
+                        ray.time(),
+                    );
+                    let back = Ray::new(hit.point, -ray.direction(), ray.time());
+                    let pdf_reverse = hit.material.scattering_pdf(&reversed, &hit, &back);
+                    path[previous].pdf_reverse = vertex.area_density(pdf_reverse, &path[previous]);
+                    ray = scattered;
+                }
+            }
+        }
+        None
+    }
+
+    // Path from a point of the lights, of at most `max_vertices`.
+    fn light_path<'w>(
+        &self,
+        world: &'w dyn Hittable,
+        lights: &'w HittableList,
+        time: f64,
+        max_vertices: usize,
+        sampler: &mut dyn Sampler,
+    ) -> Vec<Vertex<'w>> {
+        let mut path = Vec::new();
+        let Some((hit, area_pdf)) = lights.sample_surface(sampler) else {
+            return path;
+        };
+        if area_pdf <= 0.0 || max_vertices == 0 {
+            return path;
+        }
+        let light = Vertex::light(hit, time, area_pdf);
+        path.push(light);
+
+        let emission = EmissionPdf::new(&light.normal);
+        let direction = emission.generate(sampler);
+        let pdf = emission.value(&direction);
+        if pdf <= 0.0 {
+            return path;
+        }
+        let beta = light.beta * light.reflected(&(light.point + direction)) / pdf;
+        let ray = Ray::new(light.point, direction, time);
+        self.random_walk(world, (ray, beta, pdf), &mut path, max_vertices, sampler);
+        path
+    }
+
+    // Weight of the path made of the first `s` vertices of the light path and the first `t` of
+    // the camera path, against every other way of sampling it (Veach's balance of the densities,
+    // squared as in the power heuristic). `sampled` replaces the camera vertex for `t == 1`.
+    fn mis_weight(
+        &self,
+        light_path: &[Vertex],
+        camera_path: &[Vertex],
+        (s, t): (usize, usize),
+        sampled: Option<&Vertex>,
+        lights: &HittableList,
+    ) -> f64 {
+        if s + t == 2 {
+            return 1.0;
+        }
+        let mut light: Vec<Vertex> = light_path[..s].to_vec();
+        let mut camera: Vec<Vertex> = camera_path[..t].to_vec();
+        if let Some(sampled) = sampled {
+            camera[0] = *sampled;
+        }
+
+        // The densities of the connected vertices and the ones before them change with the
+        // connection.
+        let pt = camera[t - 1];
+        let pt_minus = t.checked_sub(2).map(|index| camera[index]);
+        let qs = s.checked_sub(1).map(|index| light[index]);
+        let qs_minus = s.checked_sub(2).map(|index| light[index]);
+
+        camera[t - 1].pdf_reverse = match &qs {
+            Some(qs) => self.pdf(qs, qs_minus.as_ref(), &pt),
+            None => lights.surface_pdf(&pt.point),
+        };
+        if let Some(pt_minus) = &pt_minus {
+            camera[t - 2].pdf_reverse = match &qs {
+                Some(qs) => self.pdf(&pt, Some(qs), pt_minus),
+                None => pt.area_density(
+                    emission_pdf(&pt.normal, &(pt_minus.point - pt.point)),
+                    pt_minus,
+                ),
+            };
+        }
+        if let Some(qs) = &qs {
+            light[s - 1].pdf_reverse = self.pdf(&pt, pt_minus.as_ref(), qs);
+        }
+        if let (Some(qs), Some(qs_minus)) = (&qs, &qs_minus) {
+            light[s - 2].pdf_reverse = self.pdf(qs, Some(&pt), qs_minus);
+        }
+        camera[t - 1].delta = false;
+        if s > 0 {
+            light[s - 1].delta = false;
+        }
+
+        // A delta density cannot be matched by the other strategies, it counts as one.
+        let remap = |pdf: f64| if pdf != 0.0 { pdf } else { 1.0 };
+        let mut sum = 0.0;
+        let mut ratio = 1.0;
+        for index in (1..t).rev() {
+            ratio *= remap(camera[index].pdf_reverse) / remap(camera[index].pdf_forward);
+            if !camera[index].delta && !camera[index - 1].delta {
+                sum += power(ratio);
+            }
+        }
+        ratio = 1.0;
+        for index in (0..s).rev() {
+            ratio *= remap(light[index].pdf_reverse) / remap(light[index].pdf_forward);
+            if !light[index].delta && (index == 0 || !light[index - 1].delta) {
+                sum += power(ratio);
+            }
+        }
+        1.0 / (1.0 + sum)
+    }
+
+    // Light paths reaching the lens, splatted to the pixel they land on.
+    fn splat_light_paths(
+        &self,
+        light_path: &[Vertex],
+        camera_path: &[Vertex],
+        world: &dyn Hittable,
+        lights: &HittableList,
+        sampler: &mut dyn Sampler,
+    ) {
+        let time = camera_path[0].incoming.time();
+        let lens = Vertex::camera(self.camera.lens_point(sampler), time);
+        let max_vertices = self.depth.max as usize + 1;
+        // The lights seen straight from the camera are left to the camera paths.
+        for s in 2..=light_path.len().min(max_vertices - 1) {
+            let qs = &light_path[s - 1];
+            if !qs.is_connectible() {
+                continue;
+            }
+            let Some((column, row)) = self.pixel(&lens.point, &qs.point) else {
+                continue;
+            };
+            let direction = lens.point - qs.point;
+            let importance = self.camera_pdf(&lens.point, &-direction) / direction.length_squared();
+            let contribution = qs.beta * qs.reflected(&lens.point) * importance;
+            if contribution == Color::default() || !is_visible(world, &qs.point, &lens.point, time)
+            {
+                continue;
+            }
+            let weight = self.mis_weight(light_path, camera_path, (s, 1), Some(&lens), lights);
+            self.splats.add(column, row, weight * contribution);
+        }
+    }
+}
+
+impl Integrator for BdptIntegrator<'_> {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hittable,
+        lights: &HittableList,
+        background: &Background,
+        sampler: &mut dyn Sampler,
+    ) -> Color {
+        // Paths have at most `depth.max` segments, as with the path tracer.
+        let max_vertices = self.depth.max as usize + 1;
+        let mut color = Color::default();
+
+        let mut camera_path = vec![Vertex::camera(ray.origin(), ray.time())];
+        let pdf = self.camera_pdf(&ray.origin(), &ray.direction());
+        let beta = Color::new(1.0, 1.0, 1.0);
+        if let Some((escaped, beta)) = self.random_walk(
+            world,
+            (*ray, beta, pdf),
+            &mut camera_path,
+            max_vertices,
+            sampler,
+        ) {
+            color += beta * background.color(&escaped);
+        }
+        let light_path = self.light_path(world, lights, ray.time(), max_vertices - 1, sampler);
+
+        for t in 2..=camera_path.len() {
+            let pt = &camera_path[t - 1];
+            // Light found by the camera path alone.
+            let emitted = pt.emitted();
+            if emitted != Color::default() {
+                // Lights missing from `lights` cannot be found another way.
+                let weight = if lights.surface_pdf(&pt.point) > 0.0 {
+                    self.mis_weight(&light_path, &camera_path, (0, t), None, lights)
+                } else {
+                    1.0
+                };
+                color += pt.beta * emitted * weight;
+            }
+
+            if !pt.is_connectible() {
+                continue;
+            }
+            for s in 1..=light_path.len().min(max_vertices - t) {
+                let qs = &light_path[s - 1];
+                if !qs.is_connectible() {
+                    continue;
+                }
+                let distance_squared = (pt.point - qs.point).length_squared();
+                if distance_squared == 0.0 {
+                    continue;
+                }
+                let contribution =
+                    qs.beta * qs.reflected(&pt.point) * pt.reflected(&qs.point) * pt.beta
+                        / distance_squared;
+                if contribution == Color::default()
+                    || !is_visible(world, &pt.point, &qs.point, ray.time())
+                {
+                    continue;
+                }
+                color +=
+                    contribution * self.mis_weight(&light_path, &camera_path, (s, t), None, lights);
+            }
+        }
+
+        self.splat_light_paths(&light_path, &camera_path, world, lights, sampler);
+        color
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aarect::{XYRect, XZRect, YZRect};
+    use crate::camera::CameraSettings;
+    use crate::flip_face::FlipFace;
+    use crate::integrator::IntegratorKind;
+    use crate::render::tests::{mean_luminance, test_options};
+    use crate::render::{render, ProgressiveRender};
+    use crate::sphere::Sphere;
+    use crate::{Dielectric, DiffuseLight, Lambertian};
+
+    // Closed room lit by a small panel under its ceiling, with a matte and a glass sphere.
+    fn room() -> (HittableList, HittableList) {
+        let white = || Lambertian::new(&Color::new(0.7, 0.7, 0.7));
+        let lamp = || DiffuseLight::new(&Color::new(8.0, 8.0, 8.0));
+        let light = || FlipFace::new(XZRect::new(-0.3, 0.3, -0.3, 0.3, 0.99, lamp()));
+
+        let mut world = HittableList::new();
+        world.add(Box::new(XZRect::new(-1.0, 1.0, -1.0, 1.0, -1.0, white())));
+        world.add(Box::new(XZRect::new(-1.0, 1.0, -1.0, 1.0, 1.0, white())));
+        world.add(Box::new(XYRect::new(-1.0, 1.0, -1.0, 1.0, -1.0, white())));
+        world.add(Box::new(XYRect::new(-1.0, 1.0, -1.0, 1.0, 1.0, white())));
+        world.add(Box::new(YZRect::new(
+            -1.0,
+            1.0,
+            -1.0,
+            1.0,
+            -1.0,
+            Lambertian::new(&Color::new(0.7, 0.1, 0.1)),
+        )));
+        world.add(Box::new(YZRect::new(-1.0, 1.0, -1.0, 1.0, 1.0, white())));
+        world.add(Box::new(Sphere::new(
+            Point3::new(-0.4, -0.6, -0.3),
+            0.4,
+            white(),
+        )));
+        world.add(Box::new(Sphere::new(
+            Point3::new(0.4, -0.6, 0.1),
+            0.4,
+            Dielectric::new(1.5),
+        )));
+        world.add(Box::new(light()));
+
+        let mut lights = HittableList::new();
+        lights.add(Box::new(light()));
+        (world, lights)
+    }
+
+    fn camera() -> Camera {
+        CameraSettings {
+            look_from: Point3::new(0.0, 0.0, 0.9),
+            look_at: Point3::new(0.0, 0.0, -1.0),
+            view_up: Vec3::new(0.0, 1.0, 0.0),
+            vertical_field_of_view: 90.0,
+            aperture: 0.0,
+            focus_distance: 1.0,
+            shutter_open_time: 0.0,
+            shutter_close_time: 1.0,
+        }
+        .build(1.0)
+    }
+
+    #[test]
+    fn test_path() {
+        let (world, lights) = room();
+        let camera = camera();
+        let background = Background::Solid(Color::default());
+        let mean = |integrator| {
+            let options = test_options(integrator, 256, 6);
+            mean_luminance(&world, &lights, &camera, &background, &options)
+        };
+
+        let (path, bdpt) = (mean(IntegratorKind::Path), mean(IntegratorKind::Bdpt));
+        assert!(path > 0.1);
+        assert!((bdpt / path - 1.0).abs() < 0.03, "{bdpt} against {path}");
+    }
+
+    #[test]
+    fn test_passes() {
+        let (world, lights) = room();
+        let camera = camera();
+        let background = Background::Solid(Color::default());
+        let options = test_options(IntegratorKind::Bdpt, 6, 6);
+
+        let mut progressive =
+            ProgressiveRender::new(&world, &lights, &camera, &background, &options);
+        progressive.add_samples(2);
+        assert!(!progressive.splats().sums().is_empty());
+
+        // The light splatted so far is saved and restored with the tiles.
+        let mut resumed = ProgressiveRender::with_tiles(
+            &world,
+            &lights,
+            &camera,
+            &background,
+            &options,
+            progressive.tiles().to_vec(),
+            progressive.splats().sums(),
+        )
+        .unwrap();
+        assert_eq!(progressive.framebuffer(), resumed.framebuffer());
+        resumed.add_samples(4);
+
+        let full = render(&world, &lights, &camera, &background, &options);
+        assert_eq!(full, resumed.framebuffer());
+    }
+}
@@ -0,0 +1,132 @@
+use crate::aabb::Aabb;
+use crate::aarect::{XYRect, XZRect, YZRect};
+use crate::flip_face::FlipFace;
+use crate::hittable::{HitRecord, Hittable};
+use crate::hittable_list::HittableList;
+use crate::material::Material;
+use crate::{Point3, Ray};
+
+// Axis-aligned box made of six rectangles sharing the same material. The faces on the minimum
+// side are flipped so every normal points out of the box.
+pub struct BoxShape {
+    minimum: Point3,
+    maximum: Point3,
+    sides: HittableList,
+}
+
+impl BoxShape {
+    pub fn new<Mat: Material + Clone + 'static>(
+        minimum: Point3,
+        maximum: Point3,
+        material: Mat,
+    ) -> Self {
+        let (p0, p1) = (minimum, maximum);
+        let mut sides = HittableList::new();
+
+        sides.add(Box::new(XYRect::new(
+            p0.x(),
+            p1.x(),
+            p0.y(),
+            p1.y(),
+            p1.z(),
+            material.clone(),
+        )));
+        sides.add(Box::new(FlipFace::new(XYRect::new(
+            p0.x(),
+            p1.x(),
+            p0.y(),
+            p1.y(),
+            p0.z(),
+            material.clone(),
+        ))));
+
+        sides.add(Box::new(XZRect::new(
+            p0.x(),
+            p1.x(),
+            p0.z(),
+            p1.z(),
+            p1.y(),
+            material.clone(),
+        )));
+        sides.add(Box::new(FlipFace::new(XZRect::new(
+            p0.x(),
+            p1.x(),
+            p0.z(),
+            p1.z(),
+            p0.y(),
+            material.clone(),
+        ))));
+
+        sides.add(Box::new(YZRect::new(
+            p0.y(),
+            p1.y(),
+            p0.z(),
+            p1.z(),
+            p1.x(),
+            material.clone(),
+        )));
+        sides.add(Box::new(FlipFace::new(YZRect::new(
+            p0.y(),
+            p1.y(),
+            p0.z(),
+            p1.z(),
+            p0.x(),
+            material,
+        ))));
+
+        BoxShape {
+            minimum,
+            maximum,
+            sides,
+        }
+    }
+}
+
+impl Hittable for BoxShape {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        self.sides.hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<Aabb> {
+        Some(Aabb::new(self.minimum, self.maximum))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Color, Lambertian, Vec3};
+
+    #[test]
+    fn test_hit() {
+        let cube = BoxShape::new(
+            Point3::new(-1.0, -1.0, -1.0),
+            Point3::new(1.0, 1.0, 1.0),
+            Lambertian::new(&Color::new(1.0, 1.0, 1.0)),
+        );
+
+        let ray = Ray::new(Point3::new(5.0, 0.5, 0.0), Vec3::new(-1.0, 0.0, 0.0), 0.0);
+        let hit = cube.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(4.0, hit.t);
+        assert_eq!(Vec3::new(1.0, 0.0, 0.0), hit.normal);
+
+        // From the inside, every side is hit on its back face.
+        for direction in [
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(-1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, -1.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(0.0, 0.0, -1.0),
+        ] {
+            let ray = Ray::new(Point3::default(), direction, 0.0);
+            let hit = cube.hit(&ray, 0.001, f64::INFINITY).unwrap();
+            assert_eq!(1.0, hit.t);
+            assert!(!hit.front_face);
+            assert_eq!(-direction, hit.normal);
+        }
+
+        let ray = Ray::new(Point3::new(5.0, 1.5, 0.0), Vec3::new(-1.0, 0.0, 0.0), 0.0);
+        assert!(cube.hit(&ray, 0.001, f64::INFINITY).is_none());
+    }
+}
@@ -0,0 +1,186 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::hittable_list::HittableList;
+use crate::Ray;
+
+pub struct BvhNode {
+    left: Box<dyn Hittable>,
+    right: Option<Box<dyn Hittable>>,
+    bounding_box: Aabb,
+}
+
+impl BvhNode {
+    pub fn new(list: HittableList, time0: f64, time1: f64) -> Self {
+        BvhNode::from_objects(list.into_objects(), time0, time1)
+    }
+
+    pub fn from_objects(objects: Vec<Box<dyn Hittable>>, time0: f64, time1: f64) -> Self {
+        assert!(
+            !objects.is_empty(),
+            "Cannot build a BvhNode from an empty list"
+        );
+
+        let objects = objects
+            .into_iter()
+            .map(|object| {
+                let object_box = object
+                    .bounding_box(time0, time1)
+                    .expect("No bounding box in BvhNode constructor");
+                (object_box, object)
+            })
+            .collect();
+
+        BvhNode::build(objects)
+    }
+
+    fn build(mut objects: Vec<(Aabb, Box<dyn Hittable>)>) -> Self {
+        let bounding_box = objects
+            .iter()
+            .map(|(object_box, _)| *object_box)
+            .reduce(|lhs, rhs| Aabb::surrounding_box(&lhs, &rhs))
+            .expect("Cannot build a BvhNode from an empty list");
+
+        if objects.len() == 1 {
+            let (_, left) = objects.pop().unwrap();
+            return BvhNode {
+                left,
+                right: None,
+                bounding_box,
+            };
+        }
+
+        if objects.len() == 2 {
+            let (_, right) = objects.pop().unwrap();
+            let (_, left) = objects.pop().unwrap();
+            return BvhNode {
+                left,
+                right: Some(right),
+                bounding_box,
+            };
+        }
+
+        // Split at the median centroid along the axis where the centroids are the most spread.
+        let axis = BvhNode::split_axis(&objects);
+        let middle = objects.len() / 2;
+        objects.select_nth_unstable_by(middle, |(lhs, _), (rhs, _)| {
+            lhs.centroid()[axis].total_cmp(&rhs.centroid()[axis])
+        });
+        let right_objects = objects.split_off(middle);
+
+        BvhNode {
+            left: Box::new(BvhNode::build(objects)),
+            right: Some(Box::new(BvhNode::build(right_objects))),
+            bounding_box,
+        }
+    }
+
+    fn split_axis(objects: &[(Aabb, Box<dyn Hittable>)]) -> i32 {
+        let centroids = objects
+            .iter()
+            .map(|(object_box, _)| Aabb::new(object_box.centroid(), object_box.centroid()))
+            .reduce(|lhs, rhs| Aabb::surrounding_box(&lhs, &rhs))
+            .unwrap_or_default();
+        let extent = centroids.max() - centroids.min();
+
+        if extent.x() > extent.y() && extent.x() > extent.z() {
+            0
+        } else if extent.y() > extent.z() {
+            1
+        } else {
+            2
+        }
+    }
+}
+
+impl Hittable for BvhNode {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        if !self.bounding_box.hit(ray, t_min, t_max) {
+            return None;
+        }
+
+        let hit_left = self.left.hit(ray, t_min, t_max);
+        let closest_so_far = hit_left.as_ref().map_or(t_max, |hit| hit.t);
+        let hit_right = self
+            .right
+            .as_ref()
+            .and_then(|right| right.hit(ray, t_min, closest_so_far));
+
+        hit_right.or(hit_left)
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<Aabb> {
+        Some(self.bounding_box)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sphere::Sphere;
+    use crate::utils::random_f64;
+    use crate::{Color, Lambertian, Point3, Vec3};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn random_spheres(count: usize, rng: &mut StdRng) -> HittableList {
+        let mut list = HittableList::new();
+        for _ in 0..count {
+            list.add(Box::new(Sphere::new(
+                Vec3::random_range(rng, -10.0, 10.0),
+                0.1 + random_f64(rng),
+                Lambertian::new(&Color::new(1.0, 1.0, 1.0)),
+            )));
+        }
+        list
+    }
+
+    #[test]
+    fn test_single_object() {
+        let mut list = HittableList::new();
+        list.add(Box::new(Sphere::new(
+            Point3::default(),
+            1.0,
+            Lambertian::new(&Color::new(1.0, 1.0, 1.0)),
+        )));
+        let bvh = BvhNode::new(list, 0.0, 1.0);
+
+        let ray = Ray::new(Point3::new(0.0, 0.0, 2.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        assert_eq!(
+            Some(1.0),
+            bvh.hit(&ray, 0.001, f64::INFINITY).map(|hit| hit.t)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot build a BvhNode from an empty list")]
+    fn test_empty() {
+        BvhNode::new(HittableList::new(), 0.0, 1.0);
+    }
+
+    #[test]
+    fn test_same_hits_as_list() {
+        let rng = &mut StdRng::seed_from_u64(3);
+        let spheres = random_spheres(200, rng);
+        let rays: Vec<Ray> = (0..500)
+            .map(|_| {
+                Ray::new(
+                    Vec3::random_range(rng, -15.0, 15.0),
+                    Vec3::random_in_unit_sphere(rng),
+                    0.0,
+                )
+            })
+            .collect();
+        let expected: Vec<Option<f64>> = rays
+            .iter()
+            .map(|ray| spheres.hit(ray, 0.001, f64::INFINITY).map(|hit| hit.t))
+            .collect();
+
+        let bvh = BvhNode::new(spheres, 0.0, 1.0);
+        let actual: Vec<Option<f64>> = rays
+            .iter()
+            .map(|ray| bvh.hit(ray, 0.001, f64::INFINITY).map(|hit| hit.t))
+            .collect();
+
+        assert_eq!(expected, actual);
+    }
+}
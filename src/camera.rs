@@ -1,5 +1,5 @@
-use crate::utils::random_f64_range;
-use crate::vec3::cross;
+use crate::sampler::{sample_unit_disk, Sampler};
+use crate::vec3::{cross, dot};
 use crate::{Point3, Ray, Vec3};
 
 #[derive(Clone, Copy, Default)]
@@ -16,7 +16,37 @@ pub struct Camera {
     pub shutter_close_time: f64,
 }
 
+// Parameters describing where the camera is and how it sees, independently of the image size.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraSettings {
+    pub look_from: Point3,
+    pub look_at: Point3,
+    pub view_up: Vec3,
+    pub vertical_field_of_view: f64,
+    pub aperture: f64,
+    pub focus_distance: f64,
+    pub shutter_open_time: f64,
+    pub shutter_close_time: f64,
+}
+
+impl CameraSettings {
+    pub fn build(&self, aspect_ratio: f64) -> Camera {
+        Camera::new(
+            &self.look_from,
+            &self.look_at,
+            &self.view_up,
+            self.vertical_field_of_view,
+            aspect_ratio,
+            self.aperture,
+            self.focus_distance,
+            self.shutter_open_time,
+            self.shutter_close_time,
+        )
+    }
+}
+
 impl Camera {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         look_from: &Point3,
         look_at: &Point3,
@@ -56,35 +86,82 @@ impl Camera {
         result
     }
 
-    pub fn get_ray(&self, u: f64, v: f64) -> Ray {
-        let random_vector = self.lens_radius * Vec3::random_in_unit_sphere();
-        let offset = self.u * random_vector.x() + self.v * random_vector.y();
+    // The lens and time dimensions are drawn even for a pinhole camera without motion blur, so the
+    // bounces use the same dimensions in every scene.
+    pub fn get_ray(&self, u: f64, v: f64, sampler: &mut dyn Sampler) -> Ray {
+        let offset = self.lens_offset(sampler);
+        let time = self.shutter_open_time
+            + (self.shutter_close_time - self.shutter_open_time) * sampler.next_1d();
         Ray::new(
             self.origin + offset,
             self.lower_left_corner + u * self.horizontal + v * self.vertical - self.origin - offset,
-            random_f64_range(self.shutter_open_time, self.shutter_close_time),
+            time,
         )
     }
+
+    // Uniformly chosen point of the lens, the origin for a pinhole camera.
+    pub fn lens_point(&self, sampler: &mut dyn Sampler) -> Point3 {
+        self.origin + self.lens_offset(sampler)
+    }
+
+    fn lens_offset(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        let random_vector = self.lens_radius * sample_unit_disk(sampler.next_2d());
+        self.u * random_vector.x() + self.v * random_vector.y()
+    }
+
+    // Distance from the lens to the plane in focus, where `horizontal` and `vertical` span the
+    // image.
+    pub fn focus_distance(&self) -> f64 {
+        dot(&(self.origin - self.lower_left_corner), &self.w)
+    }
+
+    // Image coordinates `(u, v)` as given to `get_ray` of the ray from `lens_point` through
+    // `point`, if the point is in front of the camera.
+    pub fn project(&self, lens_point: &Point3, point: &Point3) -> Option<(f64, f64)> {
+        let direction = *point - *lens_point;
+        let forward = -dot(&direction, &self.w);
+        if forward <= 0.0 {
+            return None;
+        }
+        let on_plane =
+            *lens_point + (self.focus_distance() / forward) * direction - self.lower_left_corner;
+        Some((
+            dot(&on_plane, &self.horizontal) / self.horizontal.length_squared(),
+            dot(&on_plane, &self.vertical) / self.vertical.length_squared(),
+        ))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sampler::IndependentSampler;
 
-    #[test]
-    fn init() {
-        let aspect_ratio = 16.0 / 9.0;
-        let camera = Camera::new(
+    fn assert_near(expected: Vec3, actual: Vec3) {
+        assert!(
+            (expected - actual).length() < 1e-9,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    fn test_camera(aspect_ratio: f64) -> Camera {
+        Camera::new(
             &Point3::new(0.0, 0.0, 0.0),
-            &Point3::new(0.0, 0.0, 1.0),
+            &Point3::new(0.0, 0.0, -1.0),
             &Vec3::new(0.0, 1.0, 0.0),
             90.0,
             aspect_ratio,
-            2.0,
+            0.0,
             1.0,
             0.0,
             0.0,
-        );
+        )
+    }
+
+    #[test]
+    fn init() {
+        let aspect_ratio = 16.0 / 9.0;
+        let camera = test_camera(aspect_ratio);
 
         let expected_origin = Point3::default();
         let expected_llc = Point3::new(-aspect_ratio, -1.0, -1.0);
@@ -92,26 +169,33 @@ mod tests {
         let expected_vertical = Vec3::new(0.0, 2.0, 0.0);
 
         assert_eq!(expected_origin, camera.origin);
-        assert_eq!(expected_llc, camera.lower_left_corner);
-        assert_eq!(expected_horizontal, camera.horizontal);
-        assert_eq!(expected_vertical, camera.vertical);
+        assert_near(expected_llc, camera.lower_left_corner);
+        assert_near(expected_horizontal, camera.horizontal);
+        assert_near(expected_vertical, camera.vertical);
     }
 
     #[test]
     fn get_ray() {
         let aspect_ratio = 16.0 / 9.0;
-        let camera = Camera::new(
-            &Point3::new(0.0, 0.0, 0.0),
-            &Point3::new(0.0, 0.0, 1.0),
-            &Vec3::new(0.0, 1.0, 0.0),
-            90.0,
-            aspect_ratio,
-            2.0,
-            1.0,
-            0.0,
-            0.0,
+        let camera = test_camera(aspect_ratio);
+
+        let ray = camera.get_ray(0.0, 0.0, &mut IndependentSampler::new(0));
+        assert_eq!(Point3::default(), ray.origin());
+        assert_near(Vec3::new(-aspect_ratio, -1.0, -1.0), ray.direction());
+        assert_eq!(0.0, ray.time());
+    }
+
+    #[test]
+    fn project() {
+        let camera = test_camera(2.0);
+        assert_eq!(1.0, camera.focus_distance());
+
+        let ray = camera.get_ray(0.25, 0.75, &mut IndependentSampler::new(0));
+        let (u, v) = camera.project(&ray.origin(), &ray.at(3.0)).unwrap();
+        assert!((u - 0.25).abs() < 1e-12 && (v - 0.75).abs() < 1e-12);
+        assert_eq!(
+            None,
+            camera.project(&Point3::default(), &Point3::new(0.0, 0.0, 1.0))
         );
-        let expected_ray = Ray::new_old(Point3::default(), Vec3::new(-aspect_ratio, -1.0, -1.0));
-        assert_eq!(expected_ray, camera.get_ray(0.0, 0.0));
     }
 }
@@ -0,0 +1,461 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::background::Background;
+use crate::camera::CameraSettings;
+use crate::filter::PixelFilter;
+use crate::integrator::IntegratorKind;
+use crate::photon_map::PhotonSettings;
+use crate::render::{AdaptiveSampling, FilmPixel, PathDepth, PixelSamples, Tile, TILE_SIZE};
+use crate::sampler::SamplerKind;
+use crate::utils::mix;
+use crate::{Color, Vec3};
+
+const MAGIC: &[u8; 8] = b"RTCKPT09";
+
+// State of an interrupted render: the tiles with the sums of their samples, the light splatted by
+// `bdpt`, and what is needed to check that a resumed render computes the same image. Sums are
+// stored as 64-bit floats so a resumed render gives exactly the image of an uninterrupted one.
+// The photons are traced again when resuming, only their settings are saved.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    // Free form description of the scene, compared when resuming.
+    pub scene: String,
+    // `files_hash` of the scene file and the files it refers to, 0 for the built-in scenes.
+    pub scene_hash: u64,
+    pub camera: CameraSettings,
+    pub background: Background,
+    pub image_width: u32,
+    pub image_height: u32,
+    pub samples_per_pixel: u32,
+    pub adaptive: Option<AdaptiveSampling>,
+    pub depth: PathDepth,
+    pub seed: u64,
+    pub integrator: IntegratorKind,
+    pub photons: PhotonSettings,
+    pub sampler: SamplerKind,
+    pub filter: PixelFilter,
+    pub tiles: Vec<Tile>,
+    // Fixed point sums of `Splats`, empty when nothing was splatted.
+    pub splats: Vec<[i64; 3]>,
+}
+
+impl Checkpoint {
+    // Written next to `path` then renamed, so an interruption while saving keeps the last
+    // checkpoint intact.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".part");
+
+        let mut writer = BufWriter::new(File::create(&partial)?);
+        self.write(&mut writer)?;
+        writer.flush()?;
+        drop(writer);
+        fs::rename(&partial, path)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let data = fs::read(path).map_err(|error| error.to_string())?;
+        Checkpoint::read(&data)
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&(self.scene.len() as u32).to_le_bytes())?;
+        writer.write_all(self.scene.as_bytes())?;
+        writer.write_all(&self.scene_hash.to_le_bytes())?;
+        let camera = &self.camera;
+        for value in [camera.look_from, camera.look_at, camera.view_up]
+            .iter()
+            .flat_map(|vector| [vector.x(), vector.y(), vector.z()])
+            .chain([
+                camera.vertical_field_of_view,
+                camera.aperture,
+                camera.focus_distance,
+                camera.shutter_open_time,
+                camera.shutter_close_time,
+            ])
+        {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        match self.background {
+            Background::Sky => writer.write_all(&0u32.to_le_bytes())?,
+            Background::Solid(color) => {
+                writer.write_all(&1u32.to_le_bytes())?;
+                for channel in 0..3 {
+                    writer.write_all(&color[channel].to_le_bytes())?;
+                }
+            }
+        }
+        writer.write_all(&self.image_width.to_le_bytes())?;
+        writer.write_all(&self.image_height.to_le_bytes())?;
+        writer.write_all(&self.samples_per_pixel.to_le_bytes())?;
+        match self.adaptive {
+            None => writer.write_all(&0u32.to_le_bytes())?,
+            Some(adaptive) => {
+                writer.write_all(&1u32.to_le_bytes())?;
+                writer.write_all(&adaptive.min_samples.to_le_bytes())?;
+                writer.write_all(&adaptive.threshold.to_le_bytes())?;
+            }
+        }
+        for value in [
+            self.depth.max,
+            self.depth.diffuse,
+            self.depth.specular,
+            self.depth.transmission,
+            self.depth.roulette,
+        ] {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        writer.write_all(&self.seed.to_le_bytes())?;
+        writer.write_all(&(self.integrator.name().len() as u32).to_le_bytes())?;
+        writer.write_all(self.integrator.name().as_bytes())?;
+        writer.write_all(&self.photons.count.to_le_bytes())?;
+        writer.write_all(&self.photons.nearest.to_le_bytes())?;
+        writer.write_all(&(self.sampler.name().len() as u32).to_le_bytes())?;
+        writer.write_all(self.sampler.name().as_bytes())?;
+        writer.write_all(&(self.filter.kind.name().len() as u32).to_le_bytes())?;
+        writer.write_all(self.filter.kind.name().as_bytes())?;
+        writer.write_all(&self.filter.radius.to_le_bytes())?;
+        writer.write_all(&(self.tiles.len() as u32).to_le_bytes())?;
+
+        for tile in self.tiles.iter() {
+            for value in [
+                tile.x,
+                tile.y,
+                tile.width,
+                tile.height,
+                tile.margin,
+                tile.samples,
+            ] {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+            for pixel in tile.pixels.iter() {
+                for channel in 0..3 {
+                    writer.write_all(&pixel.sum[channel].to_le_bytes())?;
+                }
+                writer.write_all(&pixel.sum_squares.to_le_bytes())?;
+                writer.write_all(&pixel.count.to_le_bytes())?;
+            }
+            for pixel in tile.film.iter() {
+                for channel in 0..3 {
+                    writer.write_all(&pixel.weighted_sum[channel].to_le_bytes())?;
+                }
+                writer.write_all(&pixel.weight_sum.to_le_bytes())?;
+            }
+        }
+
+        writer.write_all(&(self.splats.len() as u32).to_le_bytes())?;
+        for value in self.splats.iter().flatten() {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn read(data: &[u8]) -> Result<Self, String> {
+        let mut reader = Reader { data, position: 0 };
+        if reader.bytes(MAGIC.len())? != MAGIC {
+            return Err("not a render checkpoint".to_string());
+        }
+
+        let scene_length = reader.u32()? as usize;
+        let scene = String::from_utf8(reader.bytes(scene_length)?.to_vec())
+            .map_err(|_| "invalid scene description in the checkpoint".to_string())?;
+        let scene_hash = reader.u64()?;
+        let camera = CameraSettings {
+            look_from: reader.vec3()?,
+            look_at: reader.vec3()?,
+            view_up: reader.vec3()?,
+            vertical_field_of_view: reader.f64()?,
+            aperture: reader.f64()?,
+            focus_distance: reader.f64()?,
+            shutter_open_time: reader.f64()?,
+            shutter_close_time: reader.f64()?,
+        };
+        let background = match reader.u32()? {
+            0 => Background::Sky,
+            1 => Background::Solid(reader.vec3()?),
+            _ => return Err("invalid background in the checkpoint".to_string()),
+        };
+        let image_width = reader.u32()?;
+        let image_height = reader.u32()?;
+        let samples_per_pixel = reader.u32()?;
+        let adaptive = match reader.u32()? {
+            0 => None,
+            1 => Some(AdaptiveSampling {
+                min_samples: reader.u32()?,
+                threshold: reader.f64()?,
+            }),
+            _ => return Err("invalid adaptive sampling in the checkpoint".to_string()),
+        };
+        let depth = PathDepth {
+            max: reader.u32()?,
+            diffuse: reader.u32()?,
+            specular: reader.u32()?,
+            transmission: reader.u32()?,
+            roulette: reader.u32()?,
+        };
+        let seed = reader.u64()?;
+        let integrator_length = reader.u32()? as usize;
+        let integrator = std::str::from_utf8(reader.bytes(integrator_length)?)
+            .map_err(|_| "invalid integrator in the checkpoint".to_string())?
+            .parse()?;
+        let photons = PhotonSettings {
+            count: reader.u32()?,
+            nearest: reader.u32()?,
+        };
+        let sampler_length = reader.u32()? as usize;
+        let sampler = std::str::from_utf8(reader.bytes(sampler_length)?)
+            .map_err(|_| "invalid sampler in the checkpoint".to_string())?
+            .parse()?;
+        let filter_length = reader.u32()? as usize;
+        let filter = PixelFilter {
+            kind: std::str::from_utf8(reader.bytes(filter_length)?)
+                .map_err(|_| "invalid filter in the checkpoint".to_string())?
+                .parse()?,
+            radius: reader.f64()?,
+        };
+
+        let tile_count = reader.u32()?;
+        let mut tiles = Vec::new();
+        for _ in 0..tile_count {
+            let (x, y, width, height, margin) = (
+                reader.u32()?,
+                reader.u32()?,
+                reader.u32()?,
+                reader.u32()?,
+                reader.u32()?,
+            );
+            if width > TILE_SIZE || height > TILE_SIZE || margin > TILE_SIZE {
+                return Err(format!("invalid {width}x{height} tile in the checkpoint"));
+            }
+            let mut tile = Tile::new(x, y, width, height, margin);
+            tile.samples = reader.u32()?;
+            for pixel in tile.pixels.iter_mut() {
+                *pixel = PixelSamples {
+                    sum: Color::new(reader.f64()?, reader.f64()?, reader.f64()?),
+                    sum_squares: reader.f64()?,
+                    count: reader.u32()?,
+                };
+            }
+            for pixel in tile.film.iter_mut() {
+                *pixel = FilmPixel {
+                    weighted_sum: Color::new(reader.f64()?, reader.f64()?, reader.f64()?),
+                    weight_sum: reader.f64()?,
+                };
+            }
+            tiles.push(tile);
+        }
+
+        let splat_count = reader.u32()? as usize;
+        if splat_count != 0 && splat_count != image_width as usize * image_height as usize {
+            return Err(format!(
+                "{splat_count} splatted pixels for a {image_width}x{image_height} image"
+            ));
+        }
+        let mut splats = Vec::with_capacity(splat_count);
+        for _ in 0..splat_count {
+            splats.push([reader.i64()?, reader.i64()?, reader.i64()?]);
+        }
+
+        if reader.position != data.len() {
+            return Err("unexpected data at the end of the checkpoint".to_string());
+        }
+
+        Ok(Checkpoint {
+            scene,
+            scene_hash,
+            camera,
+            background,
+            image_width,
+            image_height,
+            samples_per_pixel,
+            adaptive,
+            depth,
+            seed,
+            integrator,
+            photons,
+            sampler,
+            filter,
+            tiles,
+            splats,
+        })
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], String> {
+        let bytes = self
+            .data
+            .get(self.position..self.position.saturating_add(count))
+            .ok_or_else(|| "truncated checkpoint".to_string())?;
+        self.position += count;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    fn i64(&mut self) -> Result<i64, String> {
+        Ok(i64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    fn f64(&mut self) -> Result<f64, String> {
+        Ok(f64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    fn vec3(&mut self) -> Result<Vec3, String> {
+        Ok(Vec3::new(self.f64()?, self.f64()?, self.f64()?))
+    }
+}
+
+// Hash of the paths and contents of a scene file and the files it refers to, telling whether any
+// of them changed since the checkpoint was saved. Files that cannot be read hash as empty.
+pub fn files_hash<P: AsRef<Path>>(paths: &[P]) -> u64 {
+    paths.iter().fold(0, |hash, path| {
+        let path = path.as_ref();
+        let data = fs::read(path).unwrap_or_default();
+        let name = content_hash(path.as_os_str().as_encoded_bytes());
+        mix(mix(hash ^ name) ^ content_hash(&data))
+    })
+}
+
+// Hash of some data, like the contents of a file.
+pub fn content_hash(data: &[u8]) -> u64 {
+    data.chunks(8).fold(mix(data.len() as u64), |hash, chunk| {
+        let mut bytes = [0; 8];
+        bytes[..chunk.len()].copy_from_slice(chunk);
+        mix(hash ^ u64::from_le_bytes(bytes))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::FilterKind;
+    use crate::render::split_tiles;
+    use crate::Point3;
+
+    fn checkpoint() -> Checkpoint {
+        let mut tiles = split_tiles(40, 3, 2);
+        tiles[1].samples = 7;
+        tiles[1].pixels[5] = PixelSamples {
+            sum: Color::new(0.1, 2.5, 1e-300),
+            sum_squares: 3.25,
+            count: 6,
+        };
+        tiles[1].film[3] = FilmPixel {
+            weighted_sum: Color::new(-0.5, 1.0, 2.0),
+            weight_sum: 1.25,
+        };
+        let mut splats = vec![[0; 3]; 120];
+        splats[7] = [1, -2, i64::MAX];
+
+        Checkpoint {
+            scene: "scenes/cornell.scene".to_string(),
+            scene_hash: content_hash(b"camera look_from=0,0,1"),
+            camera: CameraSettings {
+                look_from: Point3::new(1.0, 2.0, 3.0),
+                look_at: Point3::new(0.0, -0.5, 0.0),
+                view_up: Vec3::new(0.0, 1.0, 0.0),
+                vertical_field_of_view: 35.5,
+                aperture: 0.1,
+                focus_distance: 4.0,
+                shutter_open_time: 0.0,
+                shutter_close_time: 0.5,
+            },
+            background: Background::Solid(Color::new(0.1, 0.2, 0.3)),
+            image_width: 40,
+            image_height: 3,
+            samples_per_pixel: 64,
+            adaptive: Some(AdaptiveSampling {
+                min_samples: 8,
+                threshold: 0.05,
+            }),
+            depth: PathDepth {
+                transmission: 12,
+                ..PathDepth::new(50)
+            },
+            seed: u64::MAX,
+            integrator: IntegratorKind::Photon,
+            photons: PhotonSettings {
+                count: 12345,
+                nearest: 7,
+            },
+            sampler: SamplerKind::Halton,
+            filter: PixelFilter::new(FilterKind::Mitchell),
+            tiles,
+            splats,
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut data = Vec::new();
+        checkpoint().write(&mut data).unwrap();
+        assert_eq!(Ok(checkpoint()), Checkpoint::read(&data));
+
+        let defaults = Checkpoint {
+            background: Background::Sky,
+            adaptive: None,
+            ..checkpoint()
+        };
+        data.clear();
+        defaults.write(&mut data).unwrap();
+        assert_eq!(Ok(defaults), Checkpoint::read(&data));
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let scene = b"sphere center=0,0,-1 radius=0.5 material=ground";
+        assert_eq!(content_hash(scene), content_hash(scene));
+        assert_ne!(content_hash(scene), content_hash(&scene[..scene.len() - 1]));
+        assert_ne!(content_hash(b"radius=0.5"), content_hash(b"radius=0.6"));
+        assert_ne!(content_hash(b""), content_hash(b"\0"));
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut data = Vec::new();
+        checkpoint().write(&mut data).unwrap();
+
+        assert_eq!(
+            Err("truncated checkpoint".to_string()),
+            Checkpoint::read(&data[..data.len() - 1])
+        );
+        data.push(0);
+        assert_eq!(
+            Err("unexpected data at the end of the checkpoint".to_string()),
+            Checkpoint::read(&data)
+        );
+        assert_eq!(
+            Err("not a render checkpoint".to_string()),
+            Checkpoint::read(b"P3\n1 1\n255\n0 0 0\n")
+        );
+        assert_eq!(
+            Err("not a render checkpoint".to_string()),
+            Checkpoint::read(b"RTCKPT08")
+        );
+
+        let mut wrong_splats = checkpoint();
+        wrong_splats.splats.truncate(5);
+        data.clear();
+        wrong_splats.write(&mut data).unwrap();
+        assert_eq!(
+            Err("5 splatted pixels for a 40x3 image".to_string()),
+            Checkpoint::read(&data)
+        );
+    }
+}
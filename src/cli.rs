@@ -0,0 +1,922 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use ray_tracing_in_one_weekend::background::Background;
+use ray_tracing_in_one_weekend::camera::CameraSettings;
+use ray_tracing_in_one_weekend::filter::{FilterKind, PixelFilter};
+use ray_tracing_in_one_weekend::image::ImageFormat;
+use ray_tracing_in_one_weekend::integrator::IntegratorKind;
+use ray_tracing_in_one_weekend::photon_map::PhotonSettings;
+use ray_tracing_in_one_weekend::render::{AdaptiveSampling, PathDepth};
+use ray_tracing_in_one_weekend::sampler::SamplerKind;
+use ray_tracing_in_one_weekend::scenes::SceneName;
+use ray_tracing_in_one_weekend::tone_mapping::{ToneMapperKind, TransferFunction};
+use ray_tracing_in_one_weekend::{cross, Color, Point3, Vec3};
+
+pub const USAGE: &str = "\
+Usage: ray-tracing-in-one-weekend [OPTIONS]
+
+Image:
+  --width <PIXELS>           Image width (default: 400)
+  --height <PIXELS>          Image height (default: width / aspect ratio)
+  --aspect-ratio <RATIO>     Width over height, as a number or W:H (default: 16:9)
+  --samples <COUNT>          Samples per pixel, the most with --adaptive (default: 100)
+  --integrator <NAME>        Rendering algorithm: path, direct, ao, normals, albedo, depth,
+                             whitted, bdpt, photon (default: path)
+  --photons <COUNT>          Photons traced from the lights for the caustics of the photon
+                             integrator (default: 200000)
+  --photon-nearest <COUNT>   Photons gathered to estimate the caustics at a point
+                             (default: 50)
+  --sampler <NAME>           Sample placement: independent, stratified, halton, sobol
+                             (default: sobol)
+  --filter <NAME>            Reconstruction filter: box, tent, gaussian, mitchell, lanczos
+                             (default: box)
+  --filter-radius <PIXELS>   Radius of the filter (default: 0.5 box, 1 tent, 1.5 gaussian,
+                             2 mitchell, 3 lanczos)
+  --adaptive <ERROR>         Stop sampling a pixel once its relative error is below ERROR
+                             with 95% confidence, e.g. 0.05
+  --min-samples <COUNT>      Samples per pixel before checking the error (default: 16)
+  --heatmap <FILE>           Also write an image of the samples used by each pixel
+  --depth <COUNT>            Maximum number of bounces per ray (default: 50)
+  --diffuse-depth <COUNT>    Maximum number of bounces off matte surfaces (default: --depth)
+  --specular-depth <COUNT>   Maximum number of reflections off mirrors and glass
+                             (default: --depth)
+  --transmission-depth <COUNT>
+                             Maximum number of refractions through glass (default: --depth)
+  --roulette-depth <COUNT>   Bounces before paths carrying little light may be ended
+                             randomly (default: 3)
+  --output <FILE>            Output file (default: result.ppm)
+  --tone-map <OPERATOR>      Tone mapping: linear, reinhard, aces (default: linear)
+  --exposure <STOPS>         Exposure adjustment in stops (default: 0)
+  --gamma <GAMMA>            Transfer function: srgb or a gamma value (default: srgb)
+  --format <FORMAT>          Output format: p3, p6, png, pfm, exr (default: from the output extension)
+
+Scene:
+  --scene <NAME>             Scene to render: random, simple, cornell (default: random)
+  --scene-file <FILE>        Scene description file to render instead of a built-in scene
+  --background <COLOR>       Color of the rays escaping the scene: sky, black or R,G,B
+                             (default: from the scene)
+  --seed <SEED>              Seed of the random numbers, the same seed gives the same image
+  --fog <DENSITY>            Fill the scene with fog, the share of light scattered per unit of
+                             distance, e.g. 0.05
+  --fog-albedo <R,G,B>       Share of the light the fog scatters rather than absorbs
+                             (default: 1,1,1)
+
+Camera (defaults depend on the scene):
+  --look-from <X,Y,Z>        Camera position
+  --look-at <X,Y,Z>          Point the camera looks at
+  --view-up <X,Y,Z>          Up direction of the camera
+  --vfov <DEGREES>           Vertical field of view
+  --aperture <SIZE>          Lens aperture, 0 disables depth of field
+  --focus-distance <DIST>    Distance to the plane in focus
+
+Progressive rendering (the image is refined one sample per pixel at a time):
+  --time-limit <SECONDS>     Stop after this time even if not all samples are rendered
+  --preview <SECONDS>        Write the image to the output file at this interval
+
+Checkpoints (the image is rendered tile by tile):
+  --checkpoint <FILE>        Save the rendered tiles to this file regularly and at the end
+  --resume                   Continue the render saved in the checkpoint file, finishing its
+                             tiles or adding samples up to --samples
+
+Execution:
+  --threads <COUNT>          Number of render threads (default: all cores)
+  -h, --help                 Print this help
+";
+
+// Camera values given on the command line, replacing the ones of the scene when present.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CameraOverrides {
+    pub look_from: Option<Point3>,
+    pub look_at: Option<Point3>,
+    pub view_up: Option<Vec3>,
+    pub vertical_field_of_view: Option<f64>,
+    pub aperture: Option<f64>,
+    pub focus_distance: Option<f64>,
+}
+
+impl CameraOverrides {
+    pub fn apply(&self, settings: &CameraSettings) -> Result<CameraSettings, String> {
+        let result = CameraSettings {
+            look_from: self.look_from.unwrap_or(settings.look_from),
+            look_at: self.look_at.unwrap_or(settings.look_at),
+            view_up: self.view_up.unwrap_or(settings.view_up),
+            vertical_field_of_view: self
+                .vertical_field_of_view
+                .unwrap_or(settings.vertical_field_of_view),
+            aperture: self.aperture.unwrap_or(settings.aperture),
+            focus_distance: self.focus_distance.unwrap_or(settings.focus_distance),
+            ..*settings
+        };
+
+        let view_direction = result.look_at - result.look_from;
+        if view_direction.near_zero() {
+            return Err("--look-from and --look-at must be different points".to_string());
+        }
+        if cross(&view_direction, &result.view_up).near_zero() {
+            return Err("--view-up must not be parallel to the viewing direction".to_string());
+        }
+
+        Ok(result)
+    }
+}
+
+// Fog of constant density filling the whole scene.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FogSettings {
+    pub density: f64,
+    pub albedo: Color,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderSettings {
+    pub image_width: u32,
+    pub image_height: u32,
+    pub samples_per_pixel: u32,
+    pub integrator: IntegratorKind,
+    pub photons: PhotonSettings,
+    pub sampler: SamplerKind,
+    pub filter: PixelFilter,
+    pub depth: PathDepth,
+    pub scene: SceneName,
+    pub scene_file: Option<String>,
+    pub background: Option<Background>,
+    pub fog: Option<FogSettings>,
+    pub seed: Option<u64>,
+    pub camera: CameraOverrides,
+    pub output: String,
+    pub format: ImageFormat,
+    pub tone_mapper: ToneMapperKind,
+    pub exposure: f64,
+    pub transfer_function: TransferFunction,
+    pub threads: Option<usize>,
+    pub time_limit: Option<Duration>,
+    pub preview_interval: Option<Duration>,
+    pub checkpoint: Option<String>,
+    pub resume: bool,
+    pub adaptive: Option<AdaptiveSampling>,
+    pub heatmap: Option<String>,
+}
+
+impl RenderSettings {
+    pub fn aspect_ratio(&self) -> f64 {
+        self.image_width as f64 / self.image_height as f64
+    }
+
+    pub fn is_progressive(&self) -> bool {
+        self.time_limit.is_some() || self.preview_interval.is_some()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Help,
+    Render(Box<RenderSettings>),
+}
+
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Command, String> {
+    let mut width: Option<u32> = None;
+    let mut height: Option<u32> = None;
+    let mut aspect_ratio: Option<f64> = None;
+    let mut samples_per_pixel = 100;
+    let mut integrator = IntegratorKind::default();
+    let mut photon_count = None;
+    let mut photon_nearest = None;
+    let mut sampler = SamplerKind::default();
+    let mut filter = FilterKind::Box;
+    let mut filter_radius = None;
+    let mut max_depth = 50;
+    let mut diffuse_depth = None;
+    let mut specular_depth = None;
+    let mut transmission_depth = None;
+    let mut roulette_depth = 3;
+    let mut scene = None;
+    let mut scene_file = None;
+    let mut background = None;
+    let mut fog_density = None;
+    let mut fog_albedo = None;
+    let mut seed = None;
+    let mut camera = CameraOverrides::default();
+    let mut output = "result.ppm".to_string();
+    let mut format = None;
+    let mut tone_mapper = ToneMapperKind::Linear;
+    let mut exposure = 0.0;
+    let mut transfer_function = TransferFunction::Srgb;
+    let mut threads = None;
+    let mut time_limit = None;
+    let mut preview_interval = None;
+    let mut checkpoint = None;
+    let mut resume = false;
+    let mut adaptive_threshold = None;
+    let mut min_samples = None;
+    let mut heatmap = None;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        // Accept both "--flag value" and "--flag=value".
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value)),
+            _ => (arg.clone(), None),
+        };
+
+        if flag == "-h" || flag == "--help" {
+            return Ok(Command::Help);
+        }
+
+        let mut value = || match &inline_value {
+            Some(value) => Ok(value.to_string()),
+            None => args
+                .next()
+                .ok_or_else(|| format!("missing value for {flag}")),
+        };
+
+        match flag.as_str() {
+            "--width" => width = Some(parse_positive(&flag, &value()?)?),
+            "--height" => height = Some(parse_positive(&flag, &value()?)?),
+            "--aspect-ratio" => aspect_ratio = Some(parse_aspect_ratio(&value()?)?),
+            "--samples" => samples_per_pixel = parse_positive(&flag, &value()?)?,
+            "--integrator" => integrator = value()?.parse()?,
+            "--photons" => photon_count = Some(parse_positive(&flag, &value()?)?),
+            "--photon-nearest" => photon_nearest = Some(parse_positive(&flag, &value()?)?),
+            "--sampler" => sampler = value()?.parse()?,
+            "--filter" => filter = value()?.parse()?,
+            "--filter-radius" => {
+                let radius: f64 = parse_number(&flag, &value()?)?;
+                // The tiles keep the samples splatted around them up to a tile away.
+                if radius <= 0.0 || radius > 16.0 {
+                    return Err(format!(
+                        "invalid value '{radius}' for --filter-radius: expected a radius between 0 and 16 pixels"
+                    ));
+                }
+                filter_radius = Some(radius);
+            }
+            "--depth" => max_depth = parse_positive(&flag, &value()?)?,
+            "--diffuse-depth" => diffuse_depth = Some(parse_number(&flag, &value()?)?),
+            "--specular-depth" => specular_depth = Some(parse_number(&flag, &value()?)?),
+            "--transmission-depth" => transmission_depth = Some(parse_number(&flag, &value()?)?),
+            "--roulette-depth" => roulette_depth = parse_number(&flag, &value()?)?,
+            "--output" => output = value()?,
+            "--format" => {
+                let name = value()?;
+                format = Some(ImageFormat::from_name(&name).ok_or_else(|| {
+                    format!("unknown image format '{name}', expected p3, p6, png, pfm or exr")
+                })?);
+            }
+            "--tone-map" => tone_mapper = value()?.parse()?,
+            "--exposure" => exposure = parse_number(&flag, &value()?)?,
+            "--gamma" => transfer_function = value()?.parse()?,
+            "--scene" => scene = Some(value()?.parse()?),
+            "--scene-file" => scene_file = Some(value()?),
+            "--background" => background = Some(value()?.parse()?),
+            "--seed" => seed = Some(parse_number(&flag, &value()?)?),
+            "--fog" => {
+                let density: f64 = parse_number(&flag, &value()?)?;
+                if density <= 0.0 {
+                    return Err(format!(
+                        "invalid value '{density}' for --fog: expected a positive number"
+                    ));
+                }
+                fog_density = Some(density);
+            }
+            "--fog-albedo" => {
+                let value = value()?;
+                let albedo = parse_vec3(&flag, &value)?;
+                if albedo.x() < 0.0 || albedo.y() < 0.0 || albedo.z() < 0.0 {
+                    return Err(format!(
+                        "invalid value '{value}' for --fog-albedo: expected non-negative numbers"
+                    ));
+                }
+                fog_albedo = Some(albedo);
+            }
+            "--look-from" => camera.look_from = Some(parse_vec3(&flag, &value()?)?),
+            "--look-at" => camera.look_at = Some(parse_vec3(&flag, &value()?)?),
+            "--view-up" => camera.view_up = Some(parse_vec3(&flag, &value()?)?),
+            "--vfov" => {
+                let vfov: f64 = parse_number(&flag, &value()?)?;
+                if vfov <= 0.0 || vfov >= 180.0 {
+                    return Err(format!(
+                        "invalid value '{vfov}' for --vfov: expected an angle between 0 and 180 degrees"
+                    ));
+                }
+                camera.vertical_field_of_view = Some(vfov);
+            }
+            "--aperture" => {
+                let aperture: f64 = parse_number(&flag, &value()?)?;
+                if aperture < 0.0 {
+                    return Err(format!(
+                        "invalid value '{aperture}' for --aperture: expected a non-negative number"
+                    ));
+                }
+                camera.aperture = Some(aperture);
+            }
+            "--focus-distance" => {
+                let focus_distance: f64 = parse_number(&flag, &value()?)?;
+                if focus_distance <= 0.0 {
+                    return Err(format!(
+                        "invalid value '{focus_distance}' for --focus-distance: expected a positive number"
+                    ));
+                }
+                camera.focus_distance = Some(focus_distance);
+            }
+            "--threads" => threads = Some(parse_positive(&flag, &value()?)?),
+            "--time-limit" => time_limit = Some(parse_seconds(&flag, &value()?)?),
+            "--preview" => preview_interval = Some(parse_seconds(&flag, &value()?)?),
+            "--checkpoint" => checkpoint = Some(value()?),
+            "--resume" => resume = true,
+            "--adaptive" => {
+                let threshold: f64 = parse_number(&flag, &value()?)?;
+                if threshold <= 0.0 {
+                    return Err(format!(
+                        "invalid value '{threshold}' for --adaptive: expected a positive number"
+                    ));
+                }
+                adaptive_threshold = Some(threshold);
+            }
+            "--min-samples" => min_samples = Some(parse_positive(&flag, &value()?)?),
+            "--heatmap" => heatmap = Some(value()?),
+            _ => return Err(format!("unknown argument '{arg}'")),
+        }
+    }
+
+    let format = match format.or_else(|| ImageFormat::from_path(&output)) {
+        Some(format) => format,
+        None => {
+            return Err(format!(
+                "unable to guess the image format of '{output}', use --format"
+            ))
+        }
+    };
+
+    let adaptive = match (adaptive_threshold, min_samples) {
+        (Some(threshold), min_samples) => Some(AdaptiveSampling {
+            min_samples: min_samples.unwrap_or(16),
+            threshold,
+        }),
+        (None, Some(_)) => return Err("--min-samples needs --adaptive".to_string()),
+        (None, None) => None,
+    };
+    // The light paths of bdpt land on any pixel, the error of a pixel cannot be known from its
+    // own samples.
+    if adaptive.is_some() && integrator == IntegratorKind::Bdpt {
+        return Err("--adaptive cannot be used with --integrator bdpt".to_string());
+    }
+    let defaults = PhotonSettings::default();
+    let photons = PhotonSettings {
+        count: photon_count.unwrap_or(defaults.count),
+        nearest: photon_nearest.unwrap_or(defaults.nearest),
+    };
+    if (photon_count.is_some() || photon_nearest.is_some()) && integrator != IntegratorKind::Photon
+    {
+        return Err("--photons and --photon-nearest need --integrator photon".to_string());
+    }
+    if let Some(path) = &heatmap {
+        if !matches!(
+            ImageFormat::from_path(path),
+            Some(ImageFormat::P6 | ImageFormat::Png)
+        ) {
+            return Err(format!(
+                "unable to write the heatmap to '{path}', expected a .ppm or .png file"
+            ));
+        }
+    }
+
+    let depth = PathDepth {
+        max: max_depth,
+        diffuse: diffuse_depth.unwrap_or(max_depth),
+        specular: specular_depth.unwrap_or(max_depth),
+        transmission: transmission_depth.unwrap_or(max_depth),
+        roulette: roulette_depth,
+    };
+
+    let filter = PixelFilter {
+        kind: filter,
+        radius: filter_radius.unwrap_or(filter.default_radius()),
+    };
+
+    if resume && checkpoint.is_none() {
+        return Err("--resume needs the --checkpoint file to resume from".to_string());
+    }
+
+    let fog = match (fog_density, fog_albedo) {
+        (Some(density), albedo) => Some(FogSettings {
+            density,
+            albedo: albedo.unwrap_or(Color::new(1.0, 1.0, 1.0)),
+        }),
+        (None, Some(_)) => return Err("--fog-albedo needs --fog".to_string()),
+        (None, None) => None,
+    };
+
+    if scene.is_some() && scene_file.is_some() {
+        return Err("--scene and --scene-file cannot be used together".to_string());
+    }
+
+    let (image_width, image_height) = match (width, height, aspect_ratio) {
+        (Some(_), Some(_), Some(_)) => {
+            return Err(
+                "--aspect-ratio cannot be combined with both --width and --height".to_string(),
+            )
+        }
+        (Some(width), Some(height), None) => (width, height),
+        (width, height, aspect_ratio) => {
+            let aspect_ratio = aspect_ratio.unwrap_or(16.0 / 9.0);
+            match height {
+                Some(height) => ((height as f64 * aspect_ratio) as u32, height),
+                None => {
+                    let width = width.unwrap_or(400);
+                    (width, (width as f64 / aspect_ratio) as u32)
+                }
+            }
+        }
+    };
+
+    // Pixel coordinates are divided by (size - 1) when casting rays.
+    if image_width < 2 || image_height < 2 {
+        return Err(format!(
+            "the image must be at least 2x2 pixels, got {image_width}x{image_height}"
+        ));
+    }
+
+    Ok(Command::Render(Box::new(RenderSettings {
+        image_width,
+        image_height,
+        samples_per_pixel,
+        integrator,
+        photons,
+        sampler,
+        filter,
+        depth,
+        scene: scene.unwrap_or(SceneName::Random),
+        scene_file,
+        background,
+        fog,
+        seed,
+        camera,
+        output,
+        format,
+        tone_mapper,
+        exposure,
+        transfer_function,
+        threads,
+        time_limit,
+        preview_interval,
+        checkpoint,
+        resume,
+        adaptive,
+        heatmap,
+    })))
+}
+
+// Numbers read from the command line, where infinities and NaN are not numbers.
+trait Number: FromStr {
+    fn is_finite(&self) -> bool {
+        true
+    }
+}
+
+impl Number for u32 {}
+
+impl Number for u64 {}
+
+impl Number for f64 {
+    fn is_finite(&self) -> bool {
+        f64::is_finite(*self)
+    }
+}
+
+fn parse_number<T: Number>(flag: &str, value: &str) -> Result<T, String> {
+    match value.trim().parse::<T>() {
+        Ok(number) if number.is_finite() => Ok(number),
+        _ => Err(format!(
+            "invalid value '{value}' for {flag}: expected a number"
+        )),
+    }
+}
+
+fn parse_positive<T: FromStr + Default + PartialOrd>(flag: &str, value: &str) -> Result<T, String> {
+    match value.trim().parse::<T>() {
+        Ok(number) if number > T::default() => Ok(number),
+        _ => Err(format!(
+            "invalid value '{value}' for {flag}: expected a positive integer"
+        )),
+    }
+}
+
+fn parse_seconds(flag: &str, value: &str) -> Result<Duration, String> {
+    match value.trim().parse::<f64>() {
+        Ok(seconds) if seconds > 0.0 && seconds.is_finite() => Ok(Duration::from_secs_f64(seconds)),
+        _ => Err(format!(
+            "invalid value '{value}' for {flag}: expected a positive number of seconds"
+        )),
+    }
+}
+
+fn parse_vec3(flag: &str, value: &str) -> Result<Vec3, String> {
+    let coordinates = value
+        .split(',')
+        .map(|coordinate| coordinate.trim().parse::<f64>())
+        .collect::<Result<Vec<f64>, _>>();
+
+    match coordinates.as_deref() {
+        Ok([x, y, z]) if [x, y, z].iter().all(|coordinate| coordinate.is_finite()) => {
+            Ok(Vec3::new(*x, *y, *z))
+        }
+        _ => Err(format!(
+            "invalid value '{value}' for {flag}: expected three comma separated numbers"
+        )),
+    }
+}
+
+fn parse_aspect_ratio(value: &str) -> Result<f64, String> {
+    let ratio = match value.split_once(':') {
+        Some((width, height)) => {
+            match (width.trim().parse::<f64>(), height.trim().parse::<f64>()) {
+                (Ok(width), Ok(height)) => Some(width / height),
+                _ => None,
+            }
+        }
+        None => value.trim().parse::<f64>().ok(),
+    };
+
+    match ratio {
+        Some(ratio) if ratio.is_finite() && ratio > 0.0 => Ok(ratio),
+        _ => Err(format!(
+            "invalid value '{value}' for --aspect-ratio: expected a positive number or W:H"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn parse_settings(args: &[&str]) -> RenderSettings {
+        match parse(args) {
+            Ok(Command::Render(settings)) => *settings,
+            other => panic!("expected render settings, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_defaults() {
+        let settings = parse_settings(&[]);
+
+        assert_eq!(400, settings.image_width);
+        assert_eq!(225, settings.image_height);
+        assert_eq!(100, settings.samples_per_pixel);
+        assert_eq!(IntegratorKind::Path, settings.integrator);
+        assert_eq!(PhotonSettings::default(), settings.photons);
+        assert_eq!(SamplerKind::Sobol, settings.sampler);
+        assert_eq!(PixelFilter::new(FilterKind::Box), settings.filter);
+        assert_eq!(PathDepth::new(50), settings.depth);
+        assert_eq!(SceneName::Random, settings.scene);
+        assert_eq!(None, settings.scene_file);
+        assert_eq!(None, settings.background);
+        assert_eq!(None, settings.fog);
+        assert_eq!("result.ppm", settings.output);
+        assert_eq!(ImageFormat::P6, settings.format);
+        assert_eq!(ToneMapperKind::Linear, settings.tone_mapper);
+        assert_eq!(0.0, settings.exposure);
+        assert_eq!(TransferFunction::Srgb, settings.transfer_function);
+        assert_eq!(CameraOverrides::default(), settings.camera);
+        assert_eq!(None, settings.threads);
+        assert_eq!(None, settings.seed);
+        assert!(!settings.is_progressive());
+        assert_eq!(None, settings.checkpoint);
+        assert!(!settings.resume);
+        assert_eq!(None, settings.adaptive);
+        assert_eq!(None, settings.heatmap);
+    }
+
+    #[test]
+    fn test_values() {
+        let settings = parse_settings(&[
+            "--width=800",
+            "--aspect-ratio",
+            "2:1",
+            "--samples",
+            "16",
+            "--sampler=halton",
+            "--integrator",
+            "ao",
+            "--filter",
+            "mitchell",
+            "--scene",
+            "simple",
+            "--look-from",
+            "1,2.5,-3",
+            "--threads",
+            "4",
+            "--seed",
+            "42",
+            "--output",
+            "out.png",
+            "--background",
+            "black",
+            "--tone-map",
+            "aces",
+            "--exposure=-1.5",
+            "--gamma",
+            "2",
+        ]);
+
+        assert_eq!(800, settings.image_width);
+        assert_eq!(400, settings.image_height);
+        assert_eq!(2.0, settings.aspect_ratio());
+        assert_eq!(16, settings.samples_per_pixel);
+        assert_eq!(IntegratorKind::AmbientOcclusion, settings.integrator);
+        assert_eq!(SamplerKind::Halton, settings.sampler);
+        assert_eq!(PixelFilter::new(FilterKind::Mitchell), settings.filter);
+        assert_eq!(SceneName::Simple, settings.scene);
+        assert_eq!(Some(Point3::new(1.0, 2.5, -3.0)), settings.camera.look_from);
+        assert_eq!(Some(4), settings.threads);
+        assert_eq!(Some(42), settings.seed);
+        assert_eq!("out.png", settings.output);
+        assert_eq!(ImageFormat::Png, settings.format);
+        assert_eq!(
+            Some(Background::Solid(Color::default())),
+            settings.background
+        );
+        assert_eq!(ToneMapperKind::Aces, settings.tone_mapper);
+        assert_eq!(-1.5, settings.exposure);
+        assert_eq!(TransferFunction::Gamma(2.0), settings.transfer_function);
+    }
+
+    #[test]
+    fn test_depth() {
+        let settings = parse_settings(&[
+            "--depth",
+            "20",
+            "--diffuse-depth=4",
+            "--transmission-depth",
+            "0",
+            "--roulette-depth",
+            "5",
+        ]);
+        assert_eq!(
+            PathDepth {
+                max: 20,
+                diffuse: 4,
+                specular: 20,
+                transmission: 0,
+                roulette: 5,
+            },
+            settings.depth
+        );
+        assert!(parse(&["--diffuse-depth", "-1"]).is_err());
+    }
+
+    #[test]
+    fn test_progressive() {
+        let settings = parse_settings(&["--time-limit", "90", "--preview=2.5"]);
+
+        assert!(settings.is_progressive());
+        assert_eq!(Some(Duration::from_secs(90)), settings.time_limit);
+        assert_eq!(Some(Duration::from_millis(2500)), settings.preview_interval);
+        assert_eq!(
+            Err(
+                "invalid value '0' for --preview: expected a positive number of seconds"
+                    .to_string()
+            ),
+            parse(&["--preview", "0"])
+        );
+    }
+
+    #[test]
+    fn test_checkpoint() {
+        let settings = parse_settings(&["--checkpoint", "render.ckpt", "--resume"]);
+
+        assert_eq!(Some("render.ckpt".to_string()), settings.checkpoint);
+        assert!(settings.resume);
+        assert_eq!(
+            Err("--resume needs the --checkpoint file to resume from".to_string()),
+            parse(&["--resume"])
+        );
+    }
+
+    #[test]
+    fn test_adaptive() {
+        let settings = parse_settings(&["--adaptive", "0.05", "--heatmap", "samples.png"]);
+        assert_eq!(
+            Some(AdaptiveSampling {
+                min_samples: 16,
+                threshold: 0.05
+            }),
+            settings.adaptive
+        );
+        assert_eq!(Some("samples.png".to_string()), settings.heatmap);
+
+        let settings = parse_settings(&["--adaptive=0.1", "--min-samples", "4"]);
+        assert_eq!(
+            Some(4),
+            settings.adaptive.map(|adaptive| adaptive.min_samples)
+        );
+
+        assert_eq!(
+            Err("--min-samples needs --adaptive".to_string()),
+            parse(&["--min-samples", "4"])
+        );
+        // A NaN threshold would never let a pixel converge.
+        assert_eq!(
+            Err("invalid value 'nan' for --adaptive: expected a number".to_string()),
+            parse(&["--adaptive", "nan"])
+        );
+        assert!(parse(&["--adaptive", "0"]).is_err());
+        assert_eq!(
+            Err("--adaptive cannot be used with --integrator bdpt".to_string()),
+            parse(&["--adaptive", "0.05", "--integrator", "bdpt"])
+        );
+        assert_eq!(
+            Err(
+                "unable to write the heatmap to 'samples.exr', expected a .ppm or .png file"
+                    .to_string()
+            ),
+            parse(&["--heatmap", "samples.exr"])
+        );
+    }
+
+    #[test]
+    fn test_photons() {
+        let settings = parse_settings(&[
+            "--integrator",
+            "photon",
+            "--photons",
+            "1000",
+            "--photon-nearest=20",
+        ]);
+        assert_eq!(IntegratorKind::Photon, settings.integrator);
+        assert_eq!(
+            PhotonSettings {
+                count: 1000,
+                nearest: 20
+            },
+            settings.photons
+        );
+
+        assert!(parse(&["--integrator", "photon", "--photons", "0"]).is_err());
+        assert_eq!(
+            Err("--photons and --photon-nearest need --integrator photon".to_string()),
+            parse(&["--photons", "1000"])
+        );
+    }
+
+    #[test]
+    fn test_fog() {
+        let settings = parse_settings(&["--fog", "0.05"]);
+        assert_eq!(
+            Some(FogSettings {
+                density: 0.05,
+                albedo: Color::new(1.0, 1.0, 1.0)
+            }),
+            settings.fog
+        );
+
+        let settings = parse_settings(&["--fog=0.1", "--fog-albedo", "0.9,0.8,0.7"]);
+        assert_eq!(
+            Some(Color::new(0.9, 0.8, 0.7)),
+            settings.fog.map(|fog| fog.albedo)
+        );
+
+        assert_eq!(
+            Err("invalid value '0' for --fog: expected a positive number".to_string()),
+            parse(&["--fog", "0"])
+        );
+        assert_eq!(
+            Err("invalid value 'nan' for --fog: expected a number".to_string()),
+            parse(&["--fog", "nan"])
+        );
+        assert!(parse(&["--fog", "inf"]).is_err());
+        assert_eq!(
+            Err(
+                "invalid value '1,-0.5,1' for --fog-albedo: expected non-negative numbers"
+                    .to_string()
+            ),
+            parse(&["--fog", "0.1", "--fog-albedo", "1,-0.5,1"])
+        );
+        assert!(parse(&["--fog", "0.1", "--fog-albedo", "1,nan,1"]).is_err());
+        assert_eq!(
+            Err("--fog-albedo needs --fog".to_string()),
+            parse(&["--fog-albedo", "1,1,1"])
+        );
+    }
+
+    #[test]
+    fn test_height_only() {
+        let settings = parse_settings(&["--height", "100", "--aspect-ratio", "1.5"]);
+
+        assert_eq!(150, settings.image_width);
+        assert_eq!(100, settings.image_height);
+    }
+
+    #[test]
+    fn test_help() {
+        assert_eq!(Ok(Command::Help), parse(&["--width", "10", "--help"]));
+        assert_eq!(Ok(Command::Help), parse(&["-h"]));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            Err("invalid value '0' for --samples: expected a positive integer".to_string()),
+            parse(&["--samples", "0"])
+        );
+        assert_eq!(
+            Err("missing value for --width".to_string()),
+            parse(&["--width"])
+        );
+        assert_eq!(
+            Err("unknown argument '--colour'".to_string()),
+            parse(&["--colour", "red"])
+        );
+        assert_eq!(
+            Err("unknown scene 'kitchen', expected random, simple or cornell".to_string()),
+            parse(&["--scene", "kitchen"])
+        );
+        assert_eq!(
+            Err(
+                "unknown sampler 'grid', expected independent, stratified, halton or sobol"
+                    .to_string()
+            ),
+            parse(&["--sampler", "grid"])
+        );
+        assert_eq!(
+            Err(
+                "invalid value '0' for --filter-radius: expected a radius between 0 and 16 pixels"
+                    .to_string()
+            ),
+            parse(&["--filter-radius", "0"])
+        );
+        assert_eq!(
+            Err(
+                "invalid value '1,2' for --look-at: expected three comma separated numbers"
+                    .to_string()
+            ),
+            parse(&["--look-at", "1,2"])
+        );
+        assert_eq!(
+            Err("the image must be at least 2x2 pixels, got 1x0".to_string()),
+            parse(&["--width", "1"])
+        );
+        assert!(parse(&["--width", "10", "--height", "10", "--aspect-ratio", "1"]).is_err());
+        assert_eq!(
+            Err("unable to guess the image format of 'out.jpg', use --format".to_string()),
+            parse(&["--output", "out.jpg"])
+        );
+        assert!(parse(&["--output", "out.txt", "--format", "p3"]).is_ok());
+        assert!(parse(&["--format", "jpg"]).is_err());
+        assert!(parse(&["--vfov", "180"]).is_err());
+        assert!(parse(&["--scene", "simple", "--scene-file", "a.scene"]).is_err());
+        assert!(parse(&["--aperture", "-1"]).is_err());
+
+        // Infinities and NaN are not numbers.
+        assert_eq!(
+            Err("invalid value 'nan' for --vfov: expected a number".to_string()),
+            parse(&["--vfov", "nan"])
+        );
+        assert_eq!(
+            Err("invalid value 'inf' for --exposure: expected a number".to_string()),
+            parse(&["--exposure", "inf"])
+        );
+        assert!(parse(&["--aperture", "NaN"]).is_err());
+        assert!(parse(&["--focus-distance", "1e999"]).is_err());
+        assert_eq!(
+            Err(
+                "invalid value 'nan,0,0' for --look-from: expected three comma separated numbers"
+                    .to_string()
+            ),
+            parse(&["--look-from", "nan,0,0"])
+        );
+        assert!(parse(&["--view-up", "0,-inf,0"]).is_err());
+    }
+
+    #[test]
+    fn test_camera_overrides() {
+        let settings = CameraSettings {
+            look_from: Point3::new(0.0, 0.0, 1.0),
+            look_at: Point3::new(0.0, 0.0, 0.0),
+            view_up: Vec3::new(0.0, 1.0, 0.0),
+            vertical_field_of_view: 90.0,
+            aperture: 0.0,
+            focus_distance: 1.0,
+            shutter_open_time: 0.0,
+            shutter_close_time: 1.0,
+        };
+        let overrides = CameraOverrides {
+            vertical_field_of_view: Some(45.0),
+            ..CameraOverrides::default()
+        };
+
+        let result = overrides.apply(&settings).unwrap();
+        assert_eq!(45.0, result.vertical_field_of_view);
+        assert_eq!(settings.look_from, result.look_from);
+
+        let overrides = CameraOverrides {
+            look_at: Some(Point3::new(0.0, 0.0, 1.0)),
+            ..CameraOverrides::default()
+        };
+        assert!(overrides.apply(&settings).is_err());
+    }
+}
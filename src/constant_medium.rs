@@ -0,0 +1,299 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::sphere::Sphere;
+use crate::utils::mix;
+use crate::{Point3, Ray, Vec3};
+
+// Smoke, fog or any volume of constant `density` filling a closed `boundary`, light scattering
+// inside it following `phase_function`, usually `Isotropic`. Only the first stretch of a ray inside
+// the boundary is filled, which is the whole of it for convex shapes.
+pub struct ConstantMedium<H: Hittable, Mat: Material> {
+    boundary: H,
+    density: f64,
+    phase_function: Mat,
+}
+
+impl<H: Hittable, Mat: Material> ConstantMedium<H, Mat> {
+    // The density must be a positive number.
+    pub fn new(boundary: H, density: f64, phase_function: Mat) -> Self {
+        assert!(
+            density > 0.0 && density.is_finite(),
+            "Cannot build a ConstantMedium of density {density}"
+        );
+        ConstantMedium {
+            boundary,
+            density,
+            phase_function,
+        }
+    }
+}
+
+impl<Mat: Material + Clone> ConstantMedium<Sphere<Mat>, Mat> {
+    // Fog filling the whole scene: a sphere around `bounds` and `camera_origin`, so the camera
+    // rays start inside it.
+    pub fn fog(bounds: &Aabb, camera_origin: &Point3, density: f64, phase_function: Mat) -> Self {
+        let center = bounds.centroid();
+        let radius = (bounds.max() - center)
+            .length()
+            .max((*camera_origin - center).length());
+        ConstantMedium::new(
+            Sphere::new(center, 1.01 * radius + 0.001, phase_function.clone()),
+            density,
+            phase_function,
+        )
+    }
+}
+
+impl<H: Hittable, Mat: Material> Hittable for ConstantMedium<H, Mat> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        // Where the ray enters and leaves the boundary, the entry being behind its origin when it
+        // starts inside.
+        let entry = self.boundary.hit(ray, f64::NEG_INFINITY, f64::INFINITY)?;
+        let exit = self.boundary.hit(ray, entry.t + 0.0001, f64::INFINITY)?;
+        let start = entry.t.max(t_min);
+        let end = exit.t.min(t_max);
+        if start >= end {
+            return None;
+        }
+
+        // Distance to the first particle met, exponentially distributed.
+        let length = ray.direction().length();
+        let distance = -free_path_random(ray, entry.t).ln() / self.density;
+        if distance > (end - start) * length {
+            return None;
+        }
+
+        let t = start + distance / length;
+        Some(HitRecord {
+            point: ray.at(t),
+            normal: Vec3::default(),
+            t,
+            u: 0.0,
+            v: 0.0,
+            front_face: true,
+            material: &self.phase_function,
+        })
+    }
+
+    fn bounding_box(&self, time0: f64, time1: f64) -> Option<Aabb> {
+        self.boundary.bounding_box(time0, time1)
+    }
+}
+
+// Random number in (0, 1] derived from the ray and where it enters the medium. `hit` takes no
+// sampler, and a number depending on the ray alone keeps renders the same whichever thread traces
+// them, a ray tested again finding the same particle. The distances are not stratified by the
+// sampler chosen.
+fn free_path_random(ray: &Ray, entry: f64) -> f64 {
+    let (origin, direction) = (ray.origin(), ray.direction());
+    let hash = [
+        origin.x(),
+        origin.y(),
+        origin.z(),
+        direction.x(),
+        direction.y(),
+        direction.z(),
+        ray.time(),
+        entry,
+    ]
+    .iter()
+    .fold(0, |hash, value| mix(hash ^ value.to_bits()));
+    ((hash >> 11) + 1) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aarect::XZRect;
+    use crate::background::Background;
+    use crate::box_shape::BoxShape;
+    use crate::camera::CameraSettings;
+    use crate::hittable_list::HittableList;
+    use crate::integrator::IntegratorKind;
+    use crate::isotropic::Isotropic;
+    use crate::render::tests::{mean_luminance, test_options};
+    use crate::{Color, DiffuseLight, Lambertian};
+
+    fn smoke() -> ConstantMedium<BoxShape, Isotropic> {
+        let white = Isotropic::new(&Color::new(1.0, 1.0, 1.0));
+        ConstantMedium::new(
+            BoxShape::new(
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 1.0, 1.0),
+                white,
+            ),
+            2.0,
+            white,
+        )
+    }
+
+    // Share of rays along `direction` from points spread over the plane z = `z` stopped in the
+    // medium, with the hits found.
+    fn stopped(medium: &dyn Hittable, z: f64, direction: Vec3) -> (f64, Vec<f64>) {
+        let count = 20_000;
+        let mut hits = Vec::new();
+        for index in 0..count {
+            let (x, y) = (0.25 + 0.5 * index as f64 / count as f64, 0.5);
+            let ray = Ray::new(Point3::new(x, y, z), direction, 0.0);
+            if let Some(hit) = medium.hit(&ray, 0.001, f64::INFINITY) {
+                assert!(hit.is_in_medium());
+                hits.push(hit.t);
+            }
+        }
+        (hits.len() as f64 / count as f64, hits)
+    }
+
+    #[test]
+    fn test_transmittance() {
+        // Light crossing the unit cube gets through with probability exp(-density).
+        let medium = smoke();
+        let (share, hits) = stopped(&medium, 3.0, Vec3::new(0.0, 0.0, -1.0));
+        assert!((share - (1.0 - (-2.0f64).exp())).abs() < 0.01, "{share}");
+        assert!(hits.iter().all(|t| (2.0..=3.0).contains(t)));
+
+        // The length of the direction does not change the distances.
+        let (share, hits) = stopped(&medium, 3.0, Vec3::new(0.0, 0.0, -4.0));
+        assert!((share - (1.0 - (-2.0f64).exp())).abs() < 0.01, "{share}");
+        assert!(hits.iter().all(|t| (0.5..=0.75).contains(t)));
+
+        // The same ray always meets the same particle.
+        let ray = Ray::new(Point3::new(0.5, 0.5, 3.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        assert_eq!(
+            medium.hit(&ray, 0.001, f64::INFINITY).map(|hit| hit.t),
+            medium.hit(&ray, 0.001, f64::INFINITY).map(|hit| hit.t)
+        );
+    }
+
+    #[test]
+    fn test_inside() {
+        // Rays starting halfway through the cube only cross the other half.
+        let medium = smoke();
+        let (share, hits) = stopped(&medium, 0.5, Vec3::new(0.0, 0.0, -1.0));
+        assert!((share - (1.0 - (-1.0f64).exp())).abs() < 0.01, "{share}");
+        assert!(hits.iter().all(|t| (0.001..=0.5).contains(t)));
+
+        // Rays moving away from the medium, or stopped before reaching it, find nothing.
+        assert_eq!(0.0, stopped(&medium, 1.5, Vec3::new(0.0, 0.0, 1.0)).0);
+        let ray = Ray::new(Point3::new(0.5, 0.5, 3.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        assert!(medium.hit(&ray, 0.001, 1.5).is_none());
+    }
+
+    #[test]
+    fn test_fog() {
+        let bounds = Aabb::new(Point3::new(-1.0, 0.0, -1.0), Point3::new(1.0, 2.0, 1.0));
+        let camera = Point3::new(0.0, 1.0, 10.0);
+        let fog = ConstantMedium::fog(
+            &bounds,
+            &camera,
+            0.5,
+            Isotropic::new(&Color::new(1.0, 1.0, 1.0)),
+        );
+        let fog_bounds = fog.bounding_box(0.0, 1.0).unwrap();
+        for corner in [bounds.min(), bounds.max(), camera] {
+            for axis in 0..3 {
+                assert!(fog_bounds.min()[axis] < corner[axis]);
+                assert!(fog_bounds.max()[axis] > corner[axis]);
+            }
+        }
+
+        // Rays from the camera are scattered on the way.
+        let (share, _) = stopped(&fog, 10.0, Vec3::new(0.0, 0.0, -1.0));
+        assert!(share > 0.99);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot build a ConstantMedium of density NaN")]
+    fn test_nan_density() {
+        let white = Isotropic::new(&Color::new(1.0, 1.0, 1.0));
+        ConstantMedium::new(
+            BoxShape::new(Point3::default(), Point3::new(1.0, 1.0, 1.0), white),
+            f64::NAN,
+            white,
+        );
+    }
+
+    // Mean luminance of a small render of `world` seen from above.
+    fn mean(
+        world: &HittableList,
+        lights: &HittableList,
+        background: Color,
+        integrator: IntegratorKind,
+    ) -> f64 {
+        let camera = CameraSettings {
+            look_from: Point3::new(0.0, 1.0, 4.0),
+            look_at: Point3::new(0.0, 0.5, 0.0),
+            view_up: Vec3::new(0.0, 1.0, 0.0),
+            vertical_field_of_view: 40.0,
+            aperture: 0.0,
+            focus_distance: 1.0,
+            shutter_open_time: 0.0,
+            shutter_close_time: 1.0,
+        }
+        .build(1.0);
+        let options = test_options(integrator, 256, 20);
+        mean_luminance(
+            world,
+            lights,
+            &camera,
+            &Background::Solid(background),
+            &options,
+        )
+    }
+
+    #[test]
+    fn test_furnace() {
+        // Smoke scattering all the light in an even white light stays as bright as it.
+        let mut world = HittableList::new();
+        world.add(Box::new(smoke()));
+        let white = Color::new(1.0, 1.0, 1.0);
+        for integrator in [IntegratorKind::Path, IntegratorKind::Bdpt] {
+            let mean = mean(&world, &HittableList::new(), white, integrator);
+            assert!((mean - 1.0).abs() < 0.01, "{mean}");
+        }
+
+        // Camera rays starting inside the fog are scattered too.
+        let mut world = HittableList::new();
+        let bounds = Aabb::new(Point3::default(), Point3::new(1.0, 1.0, 1.0));
+        let fog = ConstantMedium::fog(&bounds, &Point3::default(), 0.5, Isotropic::new(&white));
+        world.add(Box::new(fog));
+        let mean = mean(&world, &HittableList::new(), white, IntegratorKind::Path);
+        assert!((mean - 1.0).abs() < 0.01, "{mean}");
+    }
+
+    #[test]
+    fn test_lights() {
+        // A lamp above smoke on a floor, found by sampling it, by chance, and from light paths.
+        let lamp = || {
+            XZRect::new(
+                -0.5,
+                0.5,
+                -0.5,
+                0.5,
+                2.0,
+                DiffuseLight::new(&Color::new(4.0, 4.0, 4.0)),
+            )
+        };
+        let mut world = HittableList::new();
+        world.add(Box::new(XZRect::new(
+            -3.0,
+            3.0,
+            -3.0,
+            3.0,
+            0.0,
+            Lambertian::new(&Color::new(0.5, 0.5, 0.5)),
+        )));
+        world.add(Box::new(smoke()));
+        world.add(Box::new(lamp()));
+        let mut lights = HittableList::new();
+        lights.add(Box::new(lamp()));
+        let black = Color::default();
+
+        let path = mean(&world, &lights, black, IntegratorKind::Path);
+        let unsampled = mean(&world, &HittableList::new(), black, IntegratorKind::Path);
+        let bdpt = mean(&world, &lights, black, IntegratorKind::Bdpt);
+        assert!(path > 0.05);
+        assert!((unsampled / path - 1.0).abs() < 0.05, "{unsampled} {path}");
+        assert!((bdpt / path - 1.0).abs() < 0.03, "{bdpt} {path}");
+    }
+}
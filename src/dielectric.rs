@@ -1,6 +1,7 @@
 use crate::hittable::HitRecord;
-use crate::material::Material;
-use crate::{dot, random_f64, Color, Ray};
+use crate::material::{Material, Scatter, ScatterRecord};
+use crate::sampler::Sampler;
+use crate::{dot, Color, Ray};
 
 #[derive(Debug, Copy, Clone)]
 pub struct Dielectric {
@@ -23,7 +24,12 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, ray: &Ray, hit_record: &HitRecord) -> Option<(Ray, Color)> {
+    fn scatter(
+        &self,
+        ray: &Ray,
+        hit_record: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
         let refraction_ratio = if hit_record.front_face {
             1.0 / self.index_of_refraction
         } else {
@@ -36,16 +42,16 @@ impl Material for Dielectric {
 
         let cannot_refract = refraction_ratio * sin_theta > 1.0;
         let direction = if cannot_refract
-            || Dielectric::reflectance(cos_theta, refraction_ratio) > random_f64()
+            || Dielectric::reflectance(cos_theta, refraction_ratio) > sampler.next_1d()
         {
             unit_direction.reflect(&hit_record.normal)
         } else {
             unit_direction.refract(&hit_record.normal, refraction_ratio)
         };
 
-        Some((
-            Ray::new(hit_record.point, direction, ray.time()),
-            Color::new(1.0, 1.0, 1.0),
-        ))
+        Some(ScatterRecord {
+            attenuation: Color::new(1.0, 1.0, 1.0),
+            scatter: Scatter::Specular(Ray::new(hit_record.point, direction, ray.time())),
+        })
     }
 }
@@ -0,0 +1,81 @@
+use crate::hittable::HitRecord;
+use crate::material::{Material, ScatterRecord};
+use crate::sampler::Sampler;
+use crate::texture::{SolidColor, Texture};
+use crate::{Color, Point3, Ray};
+
+#[derive(Debug, Copy, Clone)]
+pub struct DiffuseLight<T: Texture = SolidColor> {
+    emit: T,
+}
+
+impl DiffuseLight {
+    pub fn new(color: &Color) -> DiffuseLight {
+        DiffuseLight {
+            emit: SolidColor::new(*color),
+        }
+    }
+}
+
+impl<T: Texture> DiffuseLight<T> {
+    pub fn with_texture(emit: T) -> Self {
+        DiffuseLight { emit }
+    }
+}
+
+impl<T: Texture> Material for DiffuseLight<T> {
+    fn scatter(
+        &self,
+        _ray: &Ray,
+        _hit_record: &HitRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        None
+    }
+
+    fn emitted(&self, u: f64, v: f64, point: &Point3) -> Color {
+        self.emit.value(u, v, point)
+    }
+}
+
+impl<T: Texture + PartialEq> PartialEq for DiffuseLight<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.emit == other.emit
+    }
+}
+
+impl<T: Texture + PartialEq> Eq for DiffuseLight<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::IndependentSampler;
+    use crate::Vec3;
+
+    #[test]
+    fn test_emitted() {
+        let color = Color::new(4.0, 4.0, 2.0);
+        let light = DiffuseLight::new(&color);
+
+        assert_eq!(color, light.emitted(0.5, 0.5, &Point3::default()));
+    }
+
+    #[test]
+    fn test_no_scatter() {
+        let light = DiffuseLight::new(&Color::new(1.0, 1.0, 1.0));
+        let hit_record = HitRecord {
+            point: Point3::default(),
+            normal: Vec3::new(0.0, 1.0, 0.0),
+            t: 1.0,
+            u: 0.0,
+            v: 0.0,
+            front_face: true,
+            material: &light,
+        };
+        let ray = Ray::new(Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+
+        assert!(light
+            .scatter(&ray, &hit_record, &mut IndependentSampler::new(0))
+            .is_none());
+    }
+}
@@ -0,0 +1,276 @@
+use std::f64::consts::PI;
+use std::str::FromStr;
+
+// Weight of a sample for the pixels around it, every pixel being the weighted average of the
+// samples closer to its center than the radius.
+pub trait Filter: Sync {
+    // Weight of a sample `x` pixels right and `y` pixels up from the center of a pixel, zero
+    // past the radius.
+    fn evaluate(&self, x: f64, y: f64) -> f64;
+}
+
+// Every sample within the square counts the same, a radius of 0.5 gives each pixel the average of
+// its own samples.
+pub struct BoxFilter {
+    pub radius: f64,
+}
+
+impl Filter for BoxFilter {
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        if x.abs() <= self.radius && y.abs() <= self.radius {
+            1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+pub struct TentFilter {
+    pub radius: f64,
+}
+
+impl Filter for TentFilter {
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        let tent = |value: f64| (self.radius - value.abs()).max(0.0);
+        tent(x) * tent(y)
+    }
+}
+
+// Gaussian shifted down to reach zero at the radius.
+pub struct GaussianFilter {
+    pub radius: f64,
+    pub sigma: f64,
+}
+
+impl GaussianFilter {
+    fn gaussian(&self, value: f64) -> f64 {
+        (-value * value / (2.0 * self.sigma * self.sigma)).exp()
+    }
+}
+
+impl Filter for GaussianFilter {
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        let edge = self.gaussian(self.radius);
+        let weight = |value: f64| (self.gaussian(value) - edge).max(0.0);
+        weight(x) * weight(y)
+    }
+}
+
+// Cubic with negative lobes sharpening edges, B = C = 1/3 trading blur against ringing.
+pub struct MitchellFilter {
+    pub radius: f64,
+    pub b: f64,
+    pub c: f64,
+}
+
+impl MitchellFilter {
+    fn mitchell(&self, value: f64) -> f64 {
+        let (b, c) = (self.b, self.c);
+        let x = (2.0 * value / self.radius).abs();
+        let result = if x >= 2.0 {
+            0.0
+        } else if x >= 1.0 {
+            (-b - 6.0 * c) * x.powi(3)
+                + (6.0 * b + 30.0 * c) * x.powi(2)
+                + (-12.0 * b - 48.0 * c) * x
+                + (8.0 * b + 24.0 * c)
+        } else {
+            (12.0 - 9.0 * b - 6.0 * c) * x.powi(3)
+                + (-18.0 + 12.0 * b + 6.0 * c) * x.powi(2)
+                + (6.0 - 2.0 * b)
+        };
+        result / 6.0
+    }
+}
+
+impl Filter for MitchellFilter {
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        self.mitchell(x) * self.mitchell(y)
+    }
+}
+
+// Sinc windowed by a wider sinc, the sharpest of the filters and the one ringing the most.
+pub struct LanczosFilter {
+    pub radius: f64,
+}
+
+impl LanczosFilter {
+    fn lanczos(&self, value: f64) -> f64 {
+        if value.abs() >= self.radius {
+            return 0.0;
+        }
+        sinc(value) * sinc(value / self.radius)
+    }
+}
+
+impl Filter for LanczosFilter {
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        self.lanczos(x) * self.lanczos(y)
+    }
+}
+
+fn sinc(value: f64) -> f64 {
+    if value.abs() < 1e-5 {
+        return 1.0;
+    }
+    (PI * value).sin() / (PI * value)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FilterKind {
+    Box,
+    Tent,
+    Gaussian,
+    Mitchell,
+    Lanczos,
+}
+
+impl FilterKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FilterKind::Box => "box",
+            FilterKind::Tent => "tent",
+            FilterKind::Gaussian => "gaussian",
+            FilterKind::Mitchell => "mitchell",
+            FilterKind::Lanczos => "lanczos",
+        }
+    }
+
+    pub fn default_radius(&self) -> f64 {
+        match self {
+            FilterKind::Box => 0.5,
+            FilterKind::Tent => 1.0,
+            FilterKind::Gaussian => 1.5,
+            FilterKind::Mitchell => 2.0,
+            FilterKind::Lanczos => 3.0,
+        }
+    }
+}
+
+impl FromStr for FilterKind {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "box" => Ok(FilterKind::Box),
+            "tent" => Ok(FilterKind::Tent),
+            "gaussian" => Ok(FilterKind::Gaussian),
+            "mitchell" => Ok(FilterKind::Mitchell),
+            "lanczos" => Ok(FilterKind::Lanczos),
+            _ => Err(format!(
+                "unknown filter '{name}', expected box, tent, gaussian, mitchell or lanczos"
+            )),
+        }
+    }
+}
+
+// Filter of a render, kept as its kind and radius so render options stay copyable.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PixelFilter {
+    pub kind: FilterKind,
+    pub radius: f64,
+}
+
+impl PixelFilter {
+    pub fn new(kind: FilterKind) -> Self {
+        PixelFilter {
+            kind,
+            radius: kind.default_radius(),
+        }
+    }
+
+    pub fn filter(&self) -> Box<dyn Filter> {
+        let radius = self.radius;
+        match self.kind {
+            FilterKind::Box => Box::new(BoxFilter { radius }),
+            FilterKind::Tent => Box::new(TentFilter { radius }),
+            FilterKind::Gaussian => Box::new(GaussianFilter {
+                radius,
+                sigma: radius / 3.0,
+            }),
+            FilterKind::Mitchell => Box::new(MitchellFilter {
+                radius,
+                b: 1.0 / 3.0,
+                c: 1.0 / 3.0,
+            }),
+            FilterKind::Lanczos => Box::new(LanczosFilter { radius }),
+        }
+    }
+
+    // Pixels around its own that a sample inside a pixel can reach.
+    pub fn margin(&self) -> u32 {
+        (self.radius - 0.5).ceil().max(0.0) as u32
+    }
+}
+
+impl Default for PixelFilter {
+    fn default() -> Self {
+        PixelFilter::new(FilterKind::Box)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KINDS: [FilterKind; 5] = [
+        FilterKind::Box,
+        FilterKind::Tent,
+        FilterKind::Gaussian,
+        FilterKind::Mitchell,
+        FilterKind::Lanczos,
+    ];
+
+    #[test]
+    fn test_names() {
+        for kind in KINDS {
+            assert_eq!(Ok(kind), kind.name().parse());
+        }
+        assert!("sinc".parse::<FilterKind>().is_err());
+    }
+
+    #[test]
+    fn test_support() {
+        for kind in KINDS {
+            let pixel_filter = PixelFilter::new(kind);
+            let filter = pixel_filter.filter();
+            let radius = pixel_filter.radius;
+
+            assert!(filter.evaluate(0.0, 0.0) > 0.0, "{kind:?}");
+            assert_eq!(0.0, filter.evaluate(radius + 0.01, 0.0), "{kind:?}");
+            assert_eq!(0.0, filter.evaluate(0.0, -radius - 0.01), "{kind:?}");
+            assert_eq!(filter.evaluate(0.3, -0.7), filter.evaluate(-0.3, 0.7));
+            // The center weighs the most.
+            assert!(
+                filter.evaluate(0.0, 0.0) >= filter.evaluate(0.4, 0.2),
+                "{kind:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_lobes() {
+        let mitchell = PixelFilter::new(FilterKind::Mitchell).filter();
+        assert!(mitchell.evaluate(1.5, 0.0) < 0.0);
+        let lanczos = PixelFilter::new(FilterKind::Lanczos).filter();
+        assert!(lanczos.evaluate(1.5, 0.0) < 0.0);
+        let gaussian = PixelFilter::new(FilterKind::Gaussian).filter();
+        assert!(gaussian.evaluate(1.4, 1.4) > 0.0);
+    }
+
+    #[test]
+    fn test_margin() {
+        let margin = |radius| {
+            PixelFilter {
+                kind: FilterKind::Tent,
+                radius,
+            }
+            .margin()
+        };
+        assert_eq!(0, margin(0.5));
+        assert_eq!(1, margin(1.0));
+        assert_eq!(1, margin(1.5));
+        assert_eq!(2, margin(1.6));
+        assert_eq!(3, PixelFilter::new(FilterKind::Lanczos).margin());
+    }
+}
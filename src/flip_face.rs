@@ -0,0 +1,69 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::sampler::Sampler;
+use crate::{Point3, Ray, Vec3};
+
+// Swap the front and back faces of an object, as if its outward normal pointed the other way.
+pub struct FlipFace<H: Hittable> {
+    object: H,
+}
+
+impl<H: Hittable> FlipFace<H> {
+    pub fn new(object: H) -> Self {
+        FlipFace { object }
+    }
+}
+
+impl<H: Hittable> Hittable for FlipFace<H> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let mut hit = self.object.hit(ray, t_min, t_max)?;
+        hit.front_face = !hit.front_face;
+        Some(hit)
+    }
+
+    fn bounding_box(&self, time0: f64, time1: f64) -> Option<Aabb> {
+        self.object.bounding_box(time0, time1)
+    }
+
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        self.object.pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: &Point3, sampler: &mut dyn Sampler) -> Vec3 {
+        self.object.random(origin, sampler)
+    }
+
+    fn sample_surface(&self, sampler: &mut dyn Sampler) -> Option<(HitRecord<'_>, f64)> {
+        let (mut hit, pdf) = self.object.sample_surface(sampler)?;
+        hit.normal = -hit.normal;
+        Some((hit, pdf))
+    }
+
+    fn surface_pdf(&self, point: &Point3) -> f64 {
+        self.object.surface_pdf(point)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aarect::XZRect;
+    use crate::{Color, Lambertian, Point3, Vec3};
+
+    #[test]
+    fn test_flip() {
+        let rect = FlipFace::new(XZRect::new(
+            0.0,
+            1.0,
+            0.0,
+            1.0,
+            1.0,
+            Lambertian::new(&Color::new(1.0, 1.0, 1.0)),
+        ));
+
+        let ray = Ray::new(Point3::new(0.5, 0.0, 0.5), Vec3::new(0.0, 1.0, 0.0), 0.0);
+        let hit = rect.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!(hit.front_face);
+        assert_eq!(Vec3::new(0.0, -1.0, 0.0), hit.normal);
+    }
+}
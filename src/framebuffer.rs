@@ -0,0 +1,105 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::hdr::{write_exr, write_pfm};
+use crate::image::{Image, ImageFormat};
+use crate::tone_mapping::DisplayTransform;
+use crate::Color;
+
+// Linear radiance of each pixel, rows stored from top to bottom.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameBuffer {
+    width: u32,
+    height: u32,
+    pixels: Vec<Color>,
+}
+
+impl FrameBuffer {
+    pub fn new(width: u32, height: u32, pixels: Vec<Color>) -> Self {
+        assert_eq!(
+            width as usize * height as usize,
+            pixels.len(),
+            "pixel buffer does not match the image size"
+        );
+        FrameBuffer {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    // Linear radiance of the pixels, rows from top to bottom.
+    pub fn pixels(&self) -> &[Color] {
+        &self.pixels
+    }
+
+    pub fn to_image(&self, display: &DisplayTransform) -> Image {
+        let pixels = self
+            .pixels
+            .iter()
+            .flat_map(|pixel| display.to_rgb(*pixel))
+            .collect();
+        Image::new(self.width, self.height, pixels)
+    }
+
+    pub fn save<P: AsRef<Path>>(
+        &self,
+        path: P,
+        format: ImageFormat,
+        display: &DisplayTransform,
+    ) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer, format, display)?;
+        writer.flush()
+    }
+
+    // High dynamic range formats keep the linear radiance, the display transform is left to the reader.
+    pub fn write<W: Write>(
+        &self,
+        writer: &mut W,
+        format: ImageFormat,
+        display: &DisplayTransform,
+    ) -> io::Result<()> {
+        match format {
+            ImageFormat::Pfm => write_pfm(writer, self.width, self.height, &self.pixels),
+            ImageFormat::Exr => write_exr(writer, self.width, self.height, &self.pixels),
+            _ => self.to_image(display).write(writer, format),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tone_mapping::{LinearClamp, TransferFunction};
+
+    #[test]
+    fn test_to_image() {
+        let framebuffer = FrameBuffer::new(
+            2,
+            1,
+            vec![Color::new(4.0, 0.5, 0.0), Color::new(0.25, 1.0, -1.0)],
+        );
+
+        let display =
+            DisplayTransform::new(0.0, Box::new(LinearClamp), TransferFunction::Gamma(2.0));
+
+        let mut output = Vec::new();
+        framebuffer
+            .write(&mut output, ImageFormat::P6, &display)
+            .unwrap();
+
+        let mut expected = b"P6\n2 1\n255\n".to_vec();
+        expected.extend_from_slice(&[255, 181, 0, 128, 255, 0]);
+        assert_eq!(expected, output);
+    }
+}
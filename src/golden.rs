@@ -0,0 +1,237 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::image::{Image, ImageFormat};
+
+// Regression tests comparing renders against reference images checked in the repository. Set
+// UPDATE_GOLDEN=1 to write the current renders as the new references instead.
+
+// Difference between two images, in 8-bit steps over every channel.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ImageDifference {
+    pub rmse: f64,
+    // Peak signal to noise ratio in decibels, infinite for identical images.
+    pub psnr: f64,
+    pub max_error: u8,
+}
+
+// Largest differences accepted, renders are not bit-identical across platforms since the float
+// functions of the standard library may round differently.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Tolerance {
+    pub max_rmse: f64,
+    pub max_error: u8,
+}
+
+impl ImageDifference {
+    pub fn within(&self, tolerance: &Tolerance) -> bool {
+        self.rmse <= tolerance.max_rmse && self.max_error <= tolerance.max_error
+    }
+}
+
+pub fn compare_images(expected: &Image, actual: &Image) -> Result<ImageDifference, String> {
+    if (expected.width(), expected.height()) != (actual.width(), actual.height()) {
+        return Err(format!(
+            "expected a {}x{} image, got {}x{}",
+            expected.width(),
+            expected.height(),
+            actual.width(),
+            actual.height()
+        ));
+    }
+
+    let mut squared_sum = 0.0;
+    let mut max_error = 0;
+    for (lhs, rhs) in expected.pixels().iter().zip(actual.pixels()) {
+        let error = lhs.abs_diff(*rhs);
+        squared_sum += (error as f64).powi(2);
+        max_error = max_error.max(error);
+    }
+
+    let rmse = (squared_sum / expected.pixels().len().max(1) as f64).sqrt();
+    Ok(ImageDifference {
+        rmse,
+        psnr: 20.0 * (255.0 / rmse).log10(),
+        max_error,
+    })
+}
+
+// Absolute difference of each channel, amplified so that small errors are visible.
+pub fn difference_image(expected: &Image, actual: &Image) -> Image {
+    let pixels = expected
+        .pixels()
+        .iter()
+        .zip(actual.pixels())
+        .map(|(lhs, rhs)| lhs.abs_diff(*rhs).saturating_mul(8))
+        .collect();
+    Image::new(expected.width(), expected.height(), pixels)
+}
+
+// Compare `actual` with the reference image at `reference`, relative to the crate directory. On
+// failure the render and the difference are written to target/golden for inspection.
+pub fn check_golden(
+    reference: &str,
+    actual: &Image,
+    tolerance: &Tolerance,
+) -> Result<ImageDifference, String> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(reference);
+    let format = ImageFormat::from_path(&path)
+        .ok_or_else(|| format!("{reference}: unknown image format"))?;
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        save(&path, actual, format)?;
+    }
+
+    let expected = Image::load(&path).map_err(|error| {
+        format!("{reference}: {error}, run the tests with UPDATE_GOLDEN=1 to create it")
+    })?;
+    let difference =
+        compare_images(&expected, actual).map_err(|error| format!("{reference}: {error}"))?;
+    if difference.within(tolerance) {
+        return Ok(difference);
+    }
+
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("target/golden");
+    let actual_path = directory.join(format!("{stem}.actual.png"));
+    let difference_path = directory.join(format!("{stem}.diff.png"));
+    fs::create_dir_all(&directory).map_err(|error| error.to_string())?;
+    save(&actual_path, actual, ImageFormat::Png)?;
+    save(
+        &difference_path,
+        &difference_image(&expected, actual),
+        ImageFormat::Png,
+    )?;
+
+    Err(format!(
+        "{reference}: RMSE {:.3} (max {}), PSNR {:.2} dB, max error {} (max {}), see {} and {}",
+        difference.rmse,
+        tolerance.max_rmse,
+        difference.psnr,
+        difference.max_error,
+        tolerance.max_error,
+        actual_path.display(),
+        difference_path.display()
+    ))
+}
+
+fn save(path: &Path, image: &Image, format: ImageFormat) -> Result<(), String> {
+    let write = || -> std::io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        image.write(&mut writer, format)?;
+        writer.flush()
+    };
+    write().map_err(|error| format!("unable to write {}: {error}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bvh::BvhNode;
+    use crate::framebuffer::FrameBuffer;
+    use crate::render::{render, PathDepth, RenderOptions};
+    use crate::scene_file::load_scene;
+    use crate::tone_mapping::{DisplayTransform, LinearClamp, TransferFunction};
+    use crate::Color;
+
+    fn linear() -> DisplayTransform {
+        DisplayTransform::new(0.0, Box::new(LinearClamp), TransferFunction::Gamma(1.0))
+    }
+
+    fn render_scene(path: &str, width: u32, height: u32, samples_per_pixel: u32) -> Image {
+        let scene = load_scene(Path::new(env!("CARGO_MANIFEST_DIR")).join(path)).unwrap();
+        let world = BvhNode::new(scene.world, 0.0, 1.0);
+        let camera = scene.camera.build(width as f64 / height as f64);
+        let options = RenderOptions {
+            image_width: width,
+            image_height: height,
+            samples_per_pixel,
+            depth: PathDepth::new(10),
+            seed: 1,
+            ..Default::default()
+        };
+
+        render(&world, &scene.lights, &camera, &scene.background, &options)
+            .to_image(&DisplayTransform::default())
+    }
+
+    #[test]
+    fn test_compare() {
+        let black = Image::new(2, 1, vec![0; 6]);
+        let grey = Image::new(2, 1, vec![0, 0, 0, 0, 0, 6]);
+
+        let same = compare_images(&black, &black).unwrap();
+        assert_eq!(0.0, same.rmse);
+        assert_eq!(f64::INFINITY, same.psnr);
+
+        let difference = compare_images(&black, &grey).unwrap();
+        assert_eq!(6.0_f64.sqrt(), difference.rmse);
+        assert_eq!(6, difference.max_error);
+        assert!((difference.psnr - 40.35).abs() < 0.01);
+        assert!(!difference.within(&Tolerance {
+            max_rmse: 3.0,
+            max_error: 5
+        }));
+        assert_eq!(
+            vec![0, 0, 0, 0, 0, 48],
+            difference_image(&black, &grey).pixels()
+        );
+
+        assert!(compare_images(&black, &Image::new(1, 2, vec![0; 6])).is_err());
+    }
+
+    #[test]
+    fn test_pixel() {
+        let expected =
+            fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("expected_pixel.txt"))
+                .unwrap();
+        let [r, g, b] = linear().to_rgb(Color::new(0.0, 0.5, 0.9));
+
+        assert_eq!(expected.trim(), format!("{r} {g} {b}"));
+    }
+
+    #[test]
+    fn test_gradient() {
+        let (width, height) = (256, 256);
+        let pixels = (0..height)
+            .rev()
+            .flat_map(|j| {
+                (0..width).map(move |i| {
+                    Color::new(
+                        i as f64 / (width - 1) as f64,
+                        j as f64 / (height - 1) as f64,
+                        0.25,
+                    )
+                })
+            })
+            .collect();
+        let image = FrameBuffer::new(width, height, pixels).to_image(&linear());
+
+        // The reference scales by 255.999 and stores 63 for the blue 0.25, where we store 64. It
+        // comes from the book so it is never updated.
+        let expected =
+            Image::load(Path::new(env!("CARGO_MANIFEST_DIR")).join("expected_gradient.ppm"))
+                .unwrap();
+        let difference = compare_images(&expected, &image).unwrap();
+        assert_eq!(1, difference.max_error);
+        assert!(difference.rmse < 1.0);
+    }
+
+    #[test]
+    fn test_scenes() {
+        // Renders are the same on every run, the tolerance only leaves room for floating point
+        // differences between platforms. Any change to the sampling shows up here, so update the
+        // golden images in its own commit saying why they changed.
+        let tolerance = Tolerance {
+            max_rmse: 1.0,
+            max_error: 5,
+        };
+
+        let simple = render_scene("scenes/simple.scene", 48, 27, 8);
+        check_golden("tests/golden/simple.png", &simple, &tolerance).unwrap();
+
+        let cornell = render_scene("scenes/cornell.scene", 32, 32, 64);
+        check_golden("tests/golden/cornell.png", &cornell, &tolerance).unwrap();
+    }
+}
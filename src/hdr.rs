@@ -0,0 +1,146 @@
+use std::io::{self, Write};
+
+use crate::Color;
+
+// Writers for floating point images, rows are given from top to bottom.
+
+pub fn write_pfm<W: Write>(
+    writer: &mut W,
+    width: u32,
+    height: u32,
+    pixels: &[Color],
+) -> io::Result<()> {
+    // A negative scale marks little endian data. PFM stores rows from bottom to top.
+    write!(writer, "PF\n{width} {height}\n-1.0\n")?;
+    for row in pixels.chunks(width as usize).rev() {
+        for pixel in row {
+            for channel in 0..3 {
+                writer.write_all(&(pixel[channel] as f32).to_le_bytes())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Single part scanline OpenEXR file with uncompressed 32-bit float B, G and R channels.
+pub fn write_exr<W: Write>(
+    writer: &mut W,
+    width: u32,
+    height: u32,
+    pixels: &[Color],
+) -> io::Result<()> {
+    let mut header = Vec::new();
+    header.extend_from_slice(&[0x76, 0x2f, 0x31, 0x01]);
+    header.extend_from_slice(&2u32.to_le_bytes());
+
+    // Channels are listed in alphabetical order, each one as FLOAT without subsampling.
+    let mut channels = Vec::new();
+    for name in [b'B', b'G', b'R'] {
+        channels.extend_from_slice(&[name, 0]);
+        channels.extend_from_slice(&2i32.to_le_bytes());
+        channels.extend_from_slice(&[0, 0, 0, 0]);
+        channels.extend_from_slice(&1i32.to_le_bytes());
+        channels.extend_from_slice(&1i32.to_le_bytes());
+    }
+    channels.push(0);
+
+    let mut window = Vec::new();
+    for value in [0, 0, width as i32 - 1, height as i32 - 1] {
+        window.extend_from_slice(&value.to_le_bytes());
+    }
+
+    write_attribute(&mut header, "channels", "chlist", &channels);
+    write_attribute(&mut header, "compression", "compression", &[0]);
+    write_attribute(&mut header, "dataWindow", "box2i", &window);
+    write_attribute(&mut header, "displayWindow", "box2i", &window);
+    write_attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+    write_attribute(
+        &mut header,
+        "pixelAspectRatio",
+        "float",
+        &1.0f32.to_le_bytes(),
+    );
+    write_attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+    write_attribute(
+        &mut header,
+        "screenWindowWidth",
+        "float",
+        &1.0f32.to_le_bytes(),
+    );
+    header.push(0);
+
+    // Offset table: the absolute position of each scanline, each one being y, size and the data.
+    let line_size = 3 * 4 * width as u64;
+    let first_line = header.len() as u64 + 8 * height as u64;
+    writer.write_all(&header)?;
+    for y in 0..height as u64 {
+        writer.write_all(&(first_line + y * (8 + line_size)).to_le_bytes())?;
+    }
+
+    for (y, row) in pixels.chunks(width as usize).enumerate() {
+        writer.write_all(&(y as i32).to_le_bytes())?;
+        writer.write_all(&(line_size as i32).to_le_bytes())?;
+        for channel in [2, 1, 0] {
+            for pixel in row {
+                writer.write_all(&(pixel[channel] as f32).to_le_bytes())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(kind.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixels() -> Vec<Color> {
+        vec![Color::new(1.0, 2.0, 3.0), Color::new(0.5, 0.25, 4.0)]
+    }
+
+    fn floats(bytes: &[u8]) -> Vec<f32> {
+        bytes
+            .chunks(4)
+            .map(|chunk| f32::from_le_bytes(chunk.try_into().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_pfm() {
+        let mut output = Vec::new();
+        write_pfm(&mut output, 1, 2, &pixels()).unwrap();
+
+        let header = b"PF\n1 2\n-1.0\n";
+        assert_eq!(header, &output[..header.len()]);
+        // Bottom row first
+        assert_eq!(
+            vec![0.5, 0.25, 4.0, 1.0, 2.0, 3.0],
+            floats(&output[header.len()..])
+        );
+    }
+
+    #[test]
+    fn test_exr() {
+        let mut output = Vec::new();
+        write_exr(&mut output, 2, 1, &pixels()).unwrap();
+
+        assert_eq!([0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0], output[..8]);
+
+        let scanline_size = 8 + 2 * 3 * 4;
+        let first_line = output.len() - scanline_size;
+        let offset = u64::from_le_bytes(output[first_line - 8..first_line].try_into().unwrap());
+        assert_eq!(first_line as u64, offset);
+        assert_eq!(
+            vec![3.0, 4.0, 2.0, 0.25, 1.0, 0.5],
+            floats(&output[first_line + 8..])
+        );
+    }
+}
@@ -1,10 +1,16 @@
+use crate::aabb::Aabb;
 use crate::material::Material;
+use crate::sampler::Sampler;
 use crate::{dot, Point3, Ray, Vec3};
 
+#[derive(Clone, Copy)]
 pub struct HitRecord<'a> {
     pub point: Point3,
     pub normal: Vec3,
     pub t: f64,
+    // Surface coordinates of the hit point, used for texture lookups.
+    pub u: f64,
+    pub v: f64,
     pub front_face: bool,
     pub material: &'a dyn Material,
 }
@@ -18,8 +24,57 @@ impl<'a> HitRecord<'a> {
             self.normal = -(*outward_normal);
         };
     }
+
+    // Points inside a participating medium, hit on its phase function. They have no surface, their
+    // normal is left at zero.
+    pub fn is_in_medium(&self) -> bool {
+        self.material.is_medium()
+    }
 }
 
 pub trait Hittable: Sync {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord>;
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>>;
+
+    // Return the box bounding the object over the [time0, time1] interval, if it has one.
+    fn bounding_box(&self, time0: f64, time1: f64) -> Option<Aabb>;
+
+    // Density over solid angle of `random` choosing `direction` from `origin`, zero for objects
+    // that cannot be sampled as lights.
+    fn pdf_value(&self, _origin: &Point3, _direction: &Vec3) -> f64 {
+        0.0
+    }
+
+    // Direction from `origin` towards a random point of the object.
+    fn random(&self, _origin: &Point3, _sampler: &mut dyn Sampler) -> Vec3 {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
+
+    // Random point of the surface with its outward normal, and its density over area, to start
+    // paths from the lights.
+    fn sample_surface(&self, _sampler: &mut dyn Sampler) -> Option<(HitRecord<'_>, f64)> {
+        None
+    }
+
+    // Density over area of `sample_surface` choosing `point`, zero away from the surface.
+    fn surface_pdf(&self, _point: &Point3) -> f64 {
+        0.0
+    }
+}
+
+// Integral of the density of `object` over every direction from `origin`, one for the objects
+// that can be sampled.
+#[cfg(test)]
+pub fn pdf_integral(object: &dyn Hittable, origin: &Point3) -> f64 {
+    use crate::sampler::sample_unit_vector;
+    use crate::utils::random_f64;
+    use rand::SeedableRng;
+
+    let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+    let count = 200_000;
+    let mut sum = 0.0;
+    for _ in 0..count {
+        let direction = sample_unit_vector((random_f64(&mut rng), random_f64(&mut rng)));
+        sum += object.pdf_value(origin, &direction);
+    }
+    4.0 * std::f64::consts::PI * sum / count as f64
 }
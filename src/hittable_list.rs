@@ -1,5 +1,7 @@
+use crate::aabb::Aabb;
 use crate::hittable::{HitRecord, Hittable};
-use crate::Ray;
+use crate::sampler::Sampler;
+use crate::{Point3, Ray, Vec3};
 
 #[derive(Default)]
 pub struct HittableList {
@@ -19,11 +21,19 @@ impl HittableList {
     pub fn clear(&mut self) {
         self.objects.clear();
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    pub fn into_objects(self) -> Vec<Box<dyn Hittable>> {
+        self.objects
+    }
 }
 
 impl Hittable for HittableList {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
-        let mut hit_anything: Option<HitRecord> = None;
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let mut hit_anything: Option<HitRecord<'_>> = None;
         let mut closest_so_far = t_max;
 
         for object in self.objects.iter() {
@@ -35,11 +45,64 @@ impl Hittable for HittableList {
 
         hit_anything
     }
+
+    fn bounding_box(&self, time0: f64, time1: f64) -> Option<Aabb> {
+        let mut result: Option<Aabb> = None;
+
+        for object in self.objects.iter() {
+            let object_box = object.bounding_box(time0, time1)?;
+            result = match result {
+                None => Some(object_box),
+                Some(current) => Some(Aabb::surrounding_box(&current, &object_box)),
+            };
+        }
+
+        result
+    }
+
+    // Every object is sampled as often, the density being the average of theirs.
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        let sum: f64 = self
+            .objects
+            .iter()
+            .map(|object| object.pdf_value(origin, direction))
+            .sum();
+        sum / self.objects.len().max(1) as f64
+    }
+
+    fn random(&self, origin: &Point3, sampler: &mut dyn Sampler) -> Vec3 {
+        if self.objects.is_empty() {
+            return Vec3::new(1.0, 0.0, 0.0);
+        }
+        let index = (sampler.next_1d() * self.objects.len() as f64) as usize;
+        self.objects[index.min(self.objects.len() - 1)].random(origin, sampler)
+    }
+
+    fn sample_surface(&self, sampler: &mut dyn Sampler) -> Option<(HitRecord<'_>, f64)> {
+        if self.objects.is_empty() {
+            return None;
+        }
+        let index = (sampler.next_1d() * self.objects.len() as f64) as usize;
+        let (hit, pdf) = self.objects[index.min(self.objects.len() - 1)].sample_surface(sampler)?;
+        Some((hit, pdf / self.objects.len() as f64))
+    }
+
+    fn surface_pdf(&self, point: &Point3) -> f64 {
+        let sum: f64 = self
+            .objects
+            .iter()
+            .map(|object| object.surface_pdf(point))
+            .sum();
+        sum / self.objects.len().max(1) as f64
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::aarect::XZRect;
+    use crate::hittable::pdf_integral;
+    use crate::sampler::IndependentSampler;
     use crate::sphere::Sphere;
     use crate::{Color, Lambertian, Point3};
 
@@ -55,7 +118,7 @@ mod tests {
         let mut hittables = HittableList::new();
         let sphere = Box::new(Sphere::new(
             Point3::new(0.0, 0.0, 0.0),
-            3.14,
+            std::f64::consts::PI,
             Lambertian::new(&Color::new(1.0, 0.0, 0.0)),
         ));
         hittables.add(sphere);
@@ -68,7 +131,7 @@ mod tests {
         let mut hittables = HittableList::new();
         let sphere = Box::new(Sphere::new(
             Point3::new(0.0, 0.0, 0.0),
-            3.14,
+            std::f64::consts::PI,
             Lambertian::new(&Color::new(1.0, 0.0, 0.0)),
         ));
         hittables.add(sphere);
@@ -76,4 +139,71 @@ mod tests {
 
         assert_eq!(0, hittables.objects.len());
     }
+
+    #[test]
+    fn test_bounding_box() {
+        let mut hittables = HittableList::new();
+        assert!(hittables.bounding_box(0.0, 1.0).is_none());
+
+        let material = Lambertian::new(&Color::new(1.0, 0.0, 0.0));
+        hittables.add(Box::new(Sphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            1.0,
+            material,
+        )));
+        hittables.add(Box::new(Sphere::new(
+            Point3::new(3.0, 0.0, 0.0),
+            0.5,
+            material,
+        )));
+
+        let expected = Aabb::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(3.5, 1.0, 1.0));
+        assert_eq!(Some(expected), hittables.bounding_box(0.0, 1.0));
+    }
+
+    #[test]
+    fn test_pdf() {
+        let material = Lambertian::new(&Color::new(1.0, 1.0, 1.0));
+        let mut lights = HittableList::new();
+        lights.add(Box::new(Sphere::new(
+            Point3::new(0.0, 0.0, -3.0),
+            1.0,
+            material,
+        )));
+        lights.add(Box::new(XZRect::new(-1.0, 1.0, -1.0, 1.0, 2.0, material)));
+
+        assert!((pdf_integral(&lights, &Point3::default()) - 1.0).abs() < 0.02);
+        assert_eq!(
+            0.0,
+            HittableList::new().pdf_value(&Point3::default(), &Vec3::new(0.0, 1.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn test_sample_surface() {
+        let material = Lambertian::new(&Color::new(1.0, 1.0, 1.0));
+        let mut lights = HittableList::new();
+        lights.add(Box::new(Sphere::new(
+            Point3::new(0.0, 0.0, -3.0),
+            0.5,
+            material,
+        )));
+        lights.add(Box::new(XZRect::new(-1.0, 1.0, -2.0, 0.0, 2.0, material)));
+        let sphere_pdf = 1.0 / (2.0 * std::f64::consts::PI);
+        let rect_pdf = 1.0 / 8.0;
+
+        // Points are on either object, with the density of the chosen one halved.
+        let mut sampler = IndependentSampler::new(1);
+        for index in 0..100 {
+            sampler.start_sample(0, index);
+            let (hit, pdf) = lights.sample_surface(&mut sampler).unwrap();
+            let on_sphere = ((hit.point - Point3::new(0.0, 0.0, -3.0)).length() - 0.5).abs() < 1e-9;
+            let expected = if on_sphere { sphere_pdf } else { rect_pdf };
+            assert!((pdf - expected).abs() < 1e-12);
+            assert!((lights.surface_pdf(&hit.point) - expected).abs() < 1e-12);
+            assert!((hit.normal.length() - 1.0).abs() < 1e-9);
+        }
+        assert_eq!(0.0, lights.surface_pdf(&Point3::new(0.0, 1.0, -1.0)));
+        assert!(HittableList::new().sample_surface(&mut sampler).is_none());
+    }
 }
@@ -0,0 +1,249 @@
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::png::{read_png, write_png};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ImageFormat {
+    // ASCII PPM
+    P3,
+    // Binary PPM
+    P6,
+    Png,
+    // Portable float map
+    Pfm,
+    // OpenEXR with 32-bit float channels
+    Exr,
+}
+
+impl ImageFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "p3" => Some(ImageFormat::P3),
+            "p6" | "ppm" => Some(ImageFormat::P6),
+            "png" => Some(ImageFormat::Png),
+            "pfm" => Some(ImageFormat::Pfm),
+            "exr" => Some(ImageFormat::Exr),
+            _ => None,
+        }
+    }
+
+    // Guess the format from the file extension, binary PPM is used for ".ppm" files.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?;
+        match extension.to_ascii_lowercase().as_str() {
+            "ppm" | "pnm" => Some(ImageFormat::P6),
+            "png" => Some(ImageFormat::Png),
+            "pfm" => Some(ImageFormat::Pfm),
+            "exr" => Some(ImageFormat::Exr),
+            _ => None,
+        }
+    }
+}
+
+// 8-bit RGB image, rows stored from top to bottom.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> Self {
+        assert_eq!(
+            3 * width as usize * height as usize,
+            pixels.len(),
+            "pixel buffer does not match the image size"
+        );
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    // Read a PPM (ASCII or binary) or PNG image, recognized from its content.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let data = std::fs::read(path).map_err(|error| error.to_string())?;
+        Image::decode(&data)
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, String> {
+        let (width, height, pixels) = match data.get(0..2) {
+            Some(b"P3") | Some(b"P6") => read_ppm(data)?,
+            _ if data.starts_with(b"\x89PNG") => read_png(data)?,
+            _ => return Err("unknown image format, expected PPM or PNG".to_string()),
+        };
+        Ok(Image::new(width, height, pixels))
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    // RGB bytes of the pixels, rows from top to bottom.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W, format: ImageFormat) -> io::Result<()> {
+        match format {
+            ImageFormat::P3 => self.write_p3(writer),
+            ImageFormat::P6 => self.write_p6(writer),
+            ImageFormat::Png => write_png(writer, self.width, self.height, &self.pixels),
+            ImageFormat::Pfm | ImageFormat::Exr => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "an 8-bit image cannot be written in a high dynamic range format",
+            )),
+        }
+    }
+
+    fn write_p3<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write!(writer, "P3\n{} {}\n255\n", self.width, self.height)?;
+        for rgb in self.pixels.chunks(3) {
+            writeln!(writer, "{} {} {}", rgb[0], rgb[1], rgb[2])?;
+        }
+        Ok(())
+    }
+
+    fn write_p6<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write!(writer, "P6\n{} {}\n255\n", self.width, self.height)?;
+        writer.write_all(&self.pixels)
+    }
+}
+
+fn read_ppm(data: &[u8]) -> Result<(u32, u32, Vec<u8>), String> {
+    let binary = data[1] == b'6';
+    let mut position = 2;
+
+    // Header fields are separated by whitespace, '#' starts a comment running to the end of line.
+    let next_number = |position: &mut usize| -> Result<u32, String> {
+        loop {
+            match data.get(*position) {
+                Some(byte) if byte.is_ascii_whitespace() => *position += 1,
+                Some(b'#') => {
+                    while data.get(*position).is_some_and(|&byte| byte != b'\n') {
+                        *position += 1;
+                    }
+                }
+                _ => break,
+            }
+        }
+        let start = *position;
+        while data.get(*position).is_some_and(u8::is_ascii_digit) {
+            *position += 1;
+        }
+        std::str::from_utf8(&data[start..*position])
+            .unwrap_or_default()
+            .parse()
+            .map_err(|_| "invalid PPM header".to_string())
+    };
+    let width = next_number(&mut position)?;
+    let height = next_number(&mut position)?;
+    let max_value = next_number(&mut position)?;
+    if max_value == 0 || max_value > 65535 {
+        return Err(format!("invalid PPM maximum value {max_value}"));
+    }
+
+    let count = 3 * width as usize * height as usize;
+    let samples: Vec<u32> = if binary {
+        // A single whitespace character separates the header from the samples.
+        let start = position + 1;
+        let size = if max_value > 255 { 2 } else { 1 };
+        let bytes = data
+            .get(start..start + size * count)
+            .ok_or_else(|| "not enough PPM pixel data".to_string())?;
+        bytes
+            .chunks(size)
+            .map(|sample| {
+                sample
+                    .iter()
+                    .fold(0, |value, &byte| value << 8 | byte as u32)
+            })
+            .collect()
+    } else {
+        (0..count)
+            .map(|_| next_number(&mut position))
+            .collect::<Result<_, _>>()
+            .map_err(|_| "not enough PPM pixel data".to_string())?
+    };
+
+    let pixels = samples
+        .into_iter()
+        .map(|sample| ((sample.min(max_value) * 255 + max_value / 2) / max_value) as u8)
+        .collect();
+
+    Ok((width, height, pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image() -> Image {
+        Image::new(2, 1, vec![255, 0, 0, 0, 128, 230])
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(Some(ImageFormat::P6), ImageFormat::from_path("result.ppm"));
+        assert_eq!(Some(ImageFormat::Png), ImageFormat::from_path("a/b.PNG"));
+        assert_eq!(Some(ImageFormat::Exr), ImageFormat::from_path("result.exr"));
+        assert_eq!(None, ImageFormat::from_path("result.jpg"));
+        assert_eq!(None, ImageFormat::from_path("result"));
+    }
+
+    #[test]
+    fn test_p3() {
+        let mut output = Vec::new();
+        image().write(&mut output, ImageFormat::P3).unwrap();
+
+        assert_eq!(b"P3\n2 1\n255\n255 0 0\n0 128 230\n".to_vec(), output);
+    }
+
+    #[test]
+    fn test_p6() {
+        let mut output = Vec::new();
+        image().write(&mut output, ImageFormat::P6).unwrap();
+
+        let mut expected = b"P6\n2 1\n255\n".to_vec();
+        expected.extend_from_slice(&[255, 0, 0, 0, 128, 230]);
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn test_decode() {
+        for format in [ImageFormat::P3, ImageFormat::P6, ImageFormat::Png] {
+            let mut output = Vec::new();
+            image().write(&mut output, format).unwrap();
+            assert_eq!(Ok(image()), Image::decode(&output));
+        }
+
+        let commented = b"P3 # comment\n2 1\n# another one\n15\n15 0 0  0 8 15";
+        assert_eq!(
+            Ok(Image::new(2, 1, vec![255, 0, 0, 0, 136, 255])),
+            Image::decode(commented)
+        );
+        let wide = b"P6\n1 1\n65535\n\xff\xff\x00\x00\x80\x00";
+        assert_eq!(Ok(Image::new(1, 1, vec![255, 0, 128])), Image::decode(wide));
+
+        assert!(Image::decode(b"P6\n2 1\n255\n\x00").is_err());
+        assert!(Image::decode(b"GIF89a").is_err());
+    }
+
+    #[test]
+    fn test_high_dynamic_range() {
+        assert!(image().write(&mut Vec::new(), ImageFormat::Exr).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "pixel buffer does not match the image size")]
+    fn test_invalid_size() {
+        Image::new(2, 2, vec![0; 6]);
+    }
+}
@@ -0,0 +1,690 @@
+use std::str::FromStr;
+
+use crate::background::Background;
+use crate::bdpt::BdptIntegrator;
+use crate::camera::Camera;
+use crate::hittable::{HitRecord, Hittable};
+use crate::hittable_list::HittableList;
+use crate::material::Scatter;
+use crate::pdf::{CosinePdf, HittablePdf, Pdf, SpherePdf};
+use crate::photon_map::PhotonMap;
+use crate::render::{PathDepth, Splats};
+use crate::sampler::Sampler;
+use crate::{dot, Color, Ray};
+
+// Rendering algorithm, computing the light arriving at the camera along a ray.
+pub trait Integrator: Sync {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hittable,
+        lights: &HittableList,
+        background: &Background,
+        sampler: &mut dyn Sampler,
+    ) -> Color;
+}
+
+// Unbiased path tracer. Lights are sampled at every diffuse bounce, and combined with the light the
+// bounces find by chance with multiple importance sampling. With `caustics`, the light reaching
+// matte surfaces through mirrors and glass is estimated from the photon map instead.
+pub struct PathIntegrator<'a> {
+    pub depth: PathDepth,
+    pub caustics: Option<&'a PhotonMap>,
+}
+
+impl Integrator for PathIntegrator<'_> {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hittable,
+        lights: &HittableList,
+        background: &Background,
+        sampler: &mut dyn Sampler,
+    ) -> Color {
+        let depth = &self.depth;
+        let mut color = Color::default();
+        // Share of the light found along `ray` reaching the camera.
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut ray = *ray;
+        // Density with which the last bounce chose `ray`, when it also sampled the lights.
+        let mut pdf_value: Option<f64> = None;
+        let (mut diffuse, mut specular, mut transmission) = (0, 0, 0);
+        // Whether the caustics were gathered at the last diffuse bounce, and whether `ray` went
+        // through mirrors or glass since, the light it finds being in the caustics.
+        let (mut gathered, mut caustic) = (false, false);
+
+        for bounce in 0..depth.max {
+            let Some(hit) = world.hit(&ray, 0.001, f64::INFINITY) else {
+                color += throughput * background.color(&ray);
+                break;
+            };
+
+            let mut emitted = hit.material.emitted(hit.u, hit.v, &hit.point);
+            if let Some(pdf_value) = pdf_value {
+                emitted *= emission_weight(&ray, emitted, pdf_value, lights);
+            }
+            if !(caustic && lights.surface_pdf(&hit.point) > 0.0) {
+                color += throughput * emitted;
+            }
+
+            // Lights are not sampled at the last bounce, whose scattered ray could not find them
+            // either.
+            if bounce + 1 == depth.max {
+                break;
+            }
+            let Some(record) = hit.material.scatter(&ray, &hit, sampler) else {
+                break;
+            };
+            let attenuation = record.attenuation;
+
+            match record.scatter {
+                Scatter::Specular(scattered) => {
+                    // Glass sends the rays through the surface, against the normal.
+                    let (count, limit) = if dot(&scattered.direction(), &hit.normal) < 0.0 {
+                        (&mut transmission, depth.transmission)
+                    } else {
+                        (&mut specular, depth.specular)
+                    };
+                    if *count == limit {
+                        break;
+                    }
+                    *count += 1;
+
+                    throughput = throughput * attenuation;
+                    pdf_value = None;
+                    caustic = gathered;
+                    ray = scattered;
+                }
+                Scatter::Diffuse(pdf) => {
+                    if diffuse == depth.diffuse {
+                        break;
+                    }
+                    diffuse += 1;
+
+                    // The photons are only stored on surfaces, not in media.
+                    gathered = false;
+                    caustic = false;
+                    if let Some(caustics) = self.caustics.filter(|_| !hit.is_in_medium()) {
+                        color += throughput * caustics.radiance(&ray, &hit, attenuation);
+                        gathered = true;
+                    }
+
+                    if !lights.is_empty() {
+                        let light_pdf = HittablePdf::new(lights, &hit.point);
+                        color += throughput
+                            * direct_light(
+                                &ray,
+                                &hit,
+                                attenuation,
+                                Some(pdf.as_ref()),
+                                &light_pdf,
+                                world,
+                                sampler,
+                            );
+                    }
+
+                    let scattered = Ray::new(hit.point, pdf.generate(sampler), ray.time());
+                    let value = pdf.value(&scattered.direction());
+                    if value <= 0.0 {
+                        break;
+                    }
+                    let scattering_pdf = hit.material.scattering_pdf(&ray, &hit, &scattered);
+                    throughput = throughput * attenuation * (scattering_pdf / value);
+                    pdf_value = (!lights.is_empty()).then_some(value);
+                    ray = scattered;
+                }
+            }
+
+            // Russian roulette: paths carrying little light are ended, the ones kept carrying
+            // more, so the mean stays the same.
+            if bounce >= depth.roulette {
+                let survival = throughput
+                    .x()
+                    .max(throughput.y())
+                    .max(throughput.z())
+                    .min(1.0);
+                if sampler.next_1d() >= survival {
+                    break;
+                }
+                throughput /= survival;
+            }
+        }
+        color
+    }
+}
+
+// Light reaching the first matte surface straight from the lights and the background, through
+// mirrors and glass. Much faster than paths, but without the light bouncing between surfaces.
+pub struct DirectLightingIntegrator {
+    pub depth: PathDepth,
+}
+
+impl Integrator for DirectLightingIntegrator {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hittable,
+        lights: &HittableList,
+        background: &Background,
+        sampler: &mut dyn Sampler,
+    ) -> Color {
+        let mut color = Color::default();
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut ray = *ray;
+
+        for bounce in 0..self.depth.max {
+            let Some(hit) = world.hit(&ray, 0.001, f64::INFINITY) else {
+                color += throughput * background.color(&ray);
+                break;
+            };
+            color += throughput * hit.material.emitted(hit.u, hit.v, &hit.point);
+
+            if bounce + 1 == self.depth.max {
+                break;
+            }
+            let Some(record) = hit.material.scatter(&ray, &hit, sampler) else {
+                break;
+            };
+            let attenuation = record.attenuation;
+            let pdf = match record.scatter {
+                Scatter::Specular(scattered) => {
+                    throughput = throughput * attenuation;
+                    ray = scattered;
+                    continue;
+                }
+                Scatter::Diffuse(pdf) => pdf,
+            };
+
+            if !lights.is_empty() {
+                let light_pdf = HittablePdf::new(lights, &hit.point);
+                color += throughput
+                    * direct_light(
+                        &ray,
+                        &hit,
+                        attenuation,
+                        Some(pdf.as_ref()),
+                        &light_pdf,
+                        world,
+                        sampler,
+                    );
+            }
+
+            // The direction of the material finds the background, and the lights as the other
+            // strategy.
+            let scattered = Ray::new(hit.point, pdf.generate(sampler), ray.time());
+            let value = pdf.value(&scattered.direction());
+            if value > 0.0 {
+                let found = match world.hit(&scattered, 0.001, f64::INFINITY) {
+                    Some(next) => {
+                        let emitted = next.material.emitted(next.u, next.v, &next.point);
+                        emitted * emission_weight(&scattered, emitted, value, lights)
+                    }
+                    None => background.color(&scattered),
+                };
+                let scattering_pdf = hit.material.scattering_pdf(&ray, &hit, &scattered);
+                color += throughput * attenuation * found * (scattering_pdf / value);
+            }
+            break;
+        }
+        color
+    }
+}
+
+// Share of the directions around the first surface not blocked within `distance`, white for open
+// surfaces and darker in creases and corners. Rays missing the scene are black.
+pub struct AmbientOcclusionIntegrator {
+    pub distance: f64,
+}
+
+impl Integrator for AmbientOcclusionIntegrator {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hittable,
+        _lights: &HittableList,
+        _background: &Background,
+        sampler: &mut dyn Sampler,
+    ) -> Color {
+        let Some(hit) = world.hit(ray, 0.001, f64::INFINITY) else {
+            return Color::default();
+        };
+
+        // Cosine weighted directions, so each unblocked one counts the same. Points in a medium
+        // are open every way.
+        let direction = if hit.is_in_medium() {
+            SpherePdf.generate(sampler)
+        } else {
+            CosinePdf::new(&hit.normal).generate(sampler)
+        };
+        let occlusion_ray = Ray::new(hit.point, direction, ray.time());
+        if world.hit(&occlusion_ray, 0.001, self.distance).is_some() {
+            Color::default()
+        } else {
+            Color::new(1.0, 1.0, 1.0)
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DebugChannel {
+    // Normal facing the camera, each component mapped from [-1, 1] to [0, 1].
+    Normals,
+    // Color of the surfaces, white for glass and the lights.
+    Albedo,
+    // Distance from the camera, in scene units.
+    Depth,
+}
+
+// Property of the first surface seen, to check a scene or feed a denoiser.
+pub struct DebugIntegrator {
+    pub channel: DebugChannel,
+}
+
+impl Integrator for DebugIntegrator {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hittable,
+        _lights: &HittableList,
+        background: &Background,
+        sampler: &mut dyn Sampler,
+    ) -> Color {
+        let Some(hit) = world.hit(ray, 0.001, f64::INFINITY) else {
+            return match self.channel {
+                DebugChannel::Albedo => background.color(ray),
+                DebugChannel::Normals | DebugChannel::Depth => Color::default(),
+            };
+        };
+
+        match self.channel {
+            DebugChannel::Normals => 0.5 * (hit.normal + Color::new(1.0, 1.0, 1.0)),
+            DebugChannel::Albedo => match hit.material.scatter(ray, &hit, sampler) {
+                Some(record) => record.attenuation,
+                None => Color::new(1.0, 1.0, 1.0),
+            },
+            DebugChannel::Depth => {
+                let distance = hit.t * ray.direction().length();
+                Color::new(distance, distance, distance)
+            }
+        }
+    }
+}
+
+// Classic recursive ray tracing: mirrors and glass are followed, and matte surfaces only get the
+// light coming straight from the lights, without soft bounced light or background lighting.
+pub struct WhittedIntegrator {
+    pub depth: PathDepth,
+}
+
+impl Integrator for WhittedIntegrator {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        world: &dyn Hittable,
+        lights: &HittableList,
+        background: &Background,
+        sampler: &mut dyn Sampler,
+    ) -> Color {
+        let mut color = Color::default();
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut ray = *ray;
+
+        for bounce in 0..self.depth.max {
+            let Some(hit) = world.hit(&ray, 0.001, f64::INFINITY) else {
+                color += throughput * background.color(&ray);
+                break;
+            };
+            color += throughput * hit.material.emitted(hit.u, hit.v, &hit.point);
+
+            if bounce + 1 == self.depth.max {
+                break;
+            }
+            let Some(record) = hit.material.scatter(&ray, &hit, sampler) else {
+                break;
+            };
+            match record.scatter {
+                Scatter::Specular(scattered) => {
+                    throughput = throughput * record.attenuation;
+                    ray = scattered;
+                }
+                Scatter::Diffuse(_) => {
+                    if !lights.is_empty() {
+                        let light_pdf = HittablePdf::new(lights, &hit.point);
+                        color += throughput
+                            * direct_light(
+                                &ray,
+                                &hit,
+                                record.attenuation,
+                                None,
+                                &light_pdf,
+                                world,
+                                sampler,
+                            );
+                    }
+                    break;
+                }
+            }
+        }
+        color
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum IntegratorKind {
+    #[default]
+    Path,
+    Direct,
+    AmbientOcclusion,
+    Normals,
+    Albedo,
+    Depth,
+    Whitted,
+    Bdpt,
+    Photon,
+}
+
+impl IntegratorKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            IntegratorKind::Path => "path",
+            IntegratorKind::Direct => "direct",
+            IntegratorKind::AmbientOcclusion => "ao",
+            IntegratorKind::Normals => "normals",
+            IntegratorKind::Albedo => "albedo",
+            IntegratorKind::Depth => "depth",
+            IntegratorKind::Whitted => "whitted",
+            IntegratorKind::Bdpt => "bdpt",
+            IntegratorKind::Photon => "photon",
+        }
+    }
+
+    // Ambient occlusion looks for occluders within a tenth of the size of `world`. The
+    // bidirectional path tracer adds the light paths reaching `camera` to `splats`, and photon
+    // mapping gathers the caustics from `photons`.
+    pub fn integrator<'a>(
+        &self,
+        depth: &PathDepth,
+        world: &dyn Hittable,
+        camera: &'a Camera,
+        splats: &'a Splats,
+        photons: Option<&'a PhotonMap>,
+    ) -> Box<dyn Integrator + 'a> {
+        let depth = *depth;
+        match self {
+            IntegratorKind::Path => Box::new(PathIntegrator {
+                depth,
+                caustics: None,
+            }),
+            IntegratorKind::Direct => Box::new(DirectLightingIntegrator { depth }),
+            IntegratorKind::AmbientOcclusion => {
+                let distance = match world.bounding_box(0.0, 1.0) {
+                    Some(bounds) => 0.1 * (bounds.max() - bounds.min()).length(),
+                    None => f64::INFINITY,
+                };
+                Box::new(AmbientOcclusionIntegrator { distance })
+            }
+            IntegratorKind::Normals => Box::new(DebugIntegrator {
+                channel: DebugChannel::Normals,
+            }),
+            IntegratorKind::Albedo => Box::new(DebugIntegrator {
+                channel: DebugChannel::Albedo,
+            }),
+            IntegratorKind::Depth => Box::new(DebugIntegrator {
+                channel: DebugChannel::Depth,
+            }),
+            IntegratorKind::Whitted => Box::new(WhittedIntegrator { depth }),
+            IntegratorKind::Bdpt => Box::new(BdptIntegrator {
+                depth,
+                camera,
+                splats,
+            }),
+            IntegratorKind::Photon => Box::new(PathIntegrator {
+                depth,
+                caustics: photons,
+            }),
+        }
+    }
+}
+
+impl FromStr for IntegratorKind {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "path" => Ok(IntegratorKind::Path),
+            "direct" => Ok(IntegratorKind::Direct),
+            "ao" => Ok(IntegratorKind::AmbientOcclusion),
+            "normals" => Ok(IntegratorKind::Normals),
+            "albedo" => Ok(IntegratorKind::Albedo),
+            "depth" => Ok(IntegratorKind::Depth),
+            "whitted" => Ok(IntegratorKind::Whitted),
+            "bdpt" => Ok(IntegratorKind::Bdpt),
+            "photon" => Ok(IntegratorKind::Photon),
+            _ => Err(format!(
+                "unknown integrator '{name}', expected path, direct, ao, normals, albedo, depth, whitted, bdpt or photon"
+            )),
+        }
+    }
+}
+
+// Light reaching the hit point from a direction drawn from `light_pdf`. With the density `pdf` the
+// material draws its own directions from, the light is weighted for multiple importance sampling.
+fn direct_light(
+    ray: &Ray,
+    hit: &HitRecord,
+    attenuation: Color,
+    pdf: Option<&dyn Pdf>,
+    light_pdf: &dyn Pdf,
+    world: &dyn Hittable,
+    sampler: &mut dyn Sampler,
+) -> Color {
+    let direction = light_pdf.generate(sampler);
+    let light_pdf_value = light_pdf.value(&direction);
+    if light_pdf_value <= 0.0 {
+        return Color::default();
+    }
+
+    let shadow_ray = Ray::new(hit.point, direction, ray.time());
+    let scattering_pdf = hit.material.scattering_pdf(ray, hit, &shadow_ray);
+    if scattering_pdf <= 0.0 {
+        return Color::default();
+    }
+
+    // Whatever the ray hits first, an occluder emits nothing.
+    let Some(light_hit) = world.hit(&shadow_ray, 0.001, f64::INFINITY) else {
+        return Color::default();
+    };
+    let emitted = light_hit
+        .material
+        .emitted(light_hit.u, light_hit.v, &light_hit.point);
+
+    let weight = pdf.map_or(1.0, |pdf| {
+        power_heuristic(light_pdf_value, pdf.value(&direction))
+    });
+    attenuation * emitted * (scattering_pdf * weight / light_pdf_value)
+}
+
+// Weight of the light `emitted` found along `ray`, chosen by a material with density `pdf_value`
+// at a bounce where the lights were also sampled.
+fn emission_weight(ray: &Ray, emitted: Color, pdf_value: f64, lights: &HittableList) -> f64 {
+    if emitted == Color::default() || lights.is_empty() {
+        return 1.0;
+    }
+    let light_pdf_value = lights.pdf_value(&ray.origin(), &ray.direction());
+    power_heuristic(pdf_value, light_pdf_value)
+}
+
+// Weight of a sample taken with density `pdf` when another strategy has density `other_pdf`.
+fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    let (pdf, other_pdf) = (pdf * pdf, other_pdf * other_pdf);
+    pdf / (pdf + other_pdf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::IndependentSampler;
+    use crate::sphere::Sphere;
+    use crate::{DiffuseLight, Lambertian, Point3, Vec3};
+
+    #[test]
+    fn test_lights() {
+        let floor = || {
+            Sphere::new(
+                Point3::new(0.0, -100.0, 0.0),
+                100.0,
+                Lambertian::new(&Color::new(0.5, 0.5, 0.5)),
+            )
+        };
+        let light = || {
+            Sphere::new(
+                Point3::new(0.0, 2.0, 0.0),
+                0.5,
+                DiffuseLight::new(&Color::new(4.0, 4.0, 4.0)),
+            )
+        };
+        let mut world = HittableList::new();
+        world.add(Box::new(floor()));
+        world.add(Box::new(light()));
+        let mut lights = HittableList::new();
+        lights.add(Box::new(light()));
+        let ray = Ray::new(Point3::new(1.0, 1.0, 0.0), Vec3::new(-1.0, -1.0, 0.0), 0.0);
+        let background = Background::Solid(Color::default());
+
+        let integrator = PathIntegrator {
+            depth: PathDepth::new(5),
+            caustics: None,
+        };
+
+        // Mean and variance of the light reaching the floor under the light.
+        let estimate = |lights: &HittableList| {
+            let mut sampler = IndependentSampler::new(4);
+            let count = 20000;
+            let values: Vec<f64> = (0..count)
+                .map(|index| {
+                    sampler.start_sample(0, index);
+                    integrator
+                        .radiance(&ray, &world, lights, &background, &mut sampler)
+                        .y()
+                })
+                .collect();
+            let mean = values.iter().sum::<f64>() / count as f64;
+            let variance = values
+                .iter()
+                .map(|value| (value - mean).powi(2))
+                .sum::<f64>()
+                / count as f64;
+            (mean, variance)
+        };
+        let (sampled, sampled_variance) = estimate(&lights);
+        let (unsampled, unsampled_variance) = estimate(&HittableList::new());
+        assert!(
+            (sampled - unsampled).abs() < 0.05 * unsampled,
+            "{sampled} {unsampled}"
+        );
+        assert!(sampled_variance * 10.0 < unsampled_variance);
+    }
+
+    #[test]
+    fn test_names() {
+        for kind in [
+            IntegratorKind::Path,
+            IntegratorKind::Direct,
+            IntegratorKind::AmbientOcclusion,
+            IntegratorKind::Normals,
+            IntegratorKind::Albedo,
+            IntegratorKind::Depth,
+            IntegratorKind::Whitted,
+            IntegratorKind::Bdpt,
+            IntegratorKind::Photon,
+        ] {
+            assert_eq!(Ok(kind), kind.name().parse());
+        }
+        assert!("mlt".parse::<IntegratorKind>().is_err());
+    }
+
+    #[test]
+    fn test_debug() {
+        let sphere = Sphere::new(
+            Point3::new(0.0, 0.0, -3.0),
+            1.0,
+            Lambertian::new(&Color::new(0.2, 0.4, 0.6)),
+        );
+        let ray = Ray::new(Point3::default(), Vec3::new(0.0, 0.0, -2.0), 0.0);
+        let background = Background::Solid(Color::new(0.1, 0.1, 0.1));
+        let lights = HittableList::new();
+        let camera = Camera::default();
+        let splats = Splats::new(1, 1);
+        let mut sampler = IndependentSampler::new(0);
+        let mut radiance = |kind: IntegratorKind, ray: &Ray| {
+            kind.integrator(&PathDepth::new(5), &sphere, &camera, &splats, None)
+                .radiance(ray, &sphere, &lights, &background, &mut sampler)
+        };
+
+        assert_eq!(
+            Color::new(0.5, 0.5, 1.0),
+            radiance(IntegratorKind::Normals, &ray)
+        );
+        assert_eq!(
+            Color::new(0.2, 0.4, 0.6),
+            radiance(IntegratorKind::Albedo, &ray)
+        );
+        assert_eq!(
+            Color::new(2.0, 2.0, 2.0),
+            radiance(IntegratorKind::Depth, &ray)
+        );
+        // The front of a lone sphere is not occluded.
+        assert_eq!(
+            Color::new(1.0, 1.0, 1.0),
+            radiance(IntegratorKind::AmbientOcclusion, &ray)
+        );
+
+        let miss = Ray::new(Point3::default(), Vec3::new(0.0, 1.0, 0.0), 0.0);
+        assert_eq!(
+            Color::new(0.1, 0.1, 0.1),
+            radiance(IntegratorKind::Albedo, &miss)
+        );
+        assert_eq!(Color::default(), radiance(IntegratorKind::Depth, &miss));
+    }
+
+    #[test]
+    fn test_direct() {
+        let sphere = Sphere::new(
+            Point3::new(0.0, 0.0, -3.0),
+            1.0,
+            Lambertian::new(&Color::new(0.5, 0.5, 0.5)),
+        );
+        let ray = Ray::new(Point3::default(), Vec3::new(0.1, 0.2, -1.0), 0.0);
+        let lights = HittableList::new();
+        let mean = |integrator: &dyn Integrator| {
+            let mut sampler = IndependentSampler::new(1);
+            let count = 20000;
+            let sum = (0..count)
+                .map(|index| {
+                    sampler.start_sample(0, index);
+                    integrator
+                        .radiance(&ray, &sphere, &lights, &Background::Sky, &mut sampler)
+                        .y()
+                })
+                .sum::<f64>();
+            sum / count as f64
+        };
+
+        // A lone convex object only gets light straight from the sky, which the direct lighting
+        // finds as well as the paths.
+        let direct = mean(&DirectLightingIntegrator {
+            depth: PathDepth::new(5),
+        });
+        let path = mean(&PathIntegrator {
+            depth: PathDepth::new(5),
+            caustics: None,
+        });
+        assert!((direct - path).abs() < 0.01 * path, "{direct} {path}");
+
+        // Without lights, Whitted ray tracing leaves matte surfaces black.
+        let whitted = mean(&WhittedIntegrator {
+            depth: PathDepth::new(5),
+        });
+        assert_eq!(0.0, whitted);
+    }
+}
@@ -0,0 +1,104 @@
+use crate::hittable::HitRecord;
+use crate::material::{Material, Scatter, ScatterRecord};
+use crate::pdf::{Pdf, SpherePdf};
+use crate::sampler::Sampler;
+use crate::texture::{SolidColor, Texture};
+use crate::{Color, Ray};
+
+// Phase function of a medium scattering light the same in every direction, like smoke or fog.
+#[derive(Debug, Copy, Clone)]
+pub struct Isotropic<T: Texture = SolidColor> {
+    albedo: T,
+}
+
+impl Isotropic {
+    pub fn new(color: &Color) -> Isotropic {
+        Isotropic {
+            albedo: SolidColor::new(*color),
+        }
+    }
+}
+
+impl<T: Texture> Isotropic<T> {
+    pub fn with_texture(albedo: T) -> Self {
+        Isotropic { albedo }
+    }
+}
+
+impl<T: Texture> Material for Isotropic<T> {
+    fn scatter(
+        &self,
+        _ray: &Ray,
+        hit_record: &HitRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        let attenuation = self
+            .albedo
+            .value(hit_record.u, hit_record.v, &hit_record.point);
+        Some(ScatterRecord {
+            attenuation,
+            scatter: Scatter::Diffuse(Box::new(SpherePdf)),
+        })
+    }
+
+    fn scattering_pdf(&self, _ray: &Ray, _hit_record: &HitRecord, scattered: &Ray) -> f64 {
+        SpherePdf.value(&scattered.direction())
+    }
+
+    fn is_medium(&self) -> bool {
+        true
+    }
+}
+
+impl<T: Texture + PartialEq> PartialEq for Isotropic<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.albedo == other.albedo
+    }
+}
+
+impl<T: Texture + PartialEq> Eq for Isotropic<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::IndependentSampler;
+    use crate::{Lambertian, Point3, Vec3};
+
+    #[test]
+    fn test_scatter() {
+        let isotropic = Isotropic::new(&Color::new(0.8, 0.8, 0.8));
+        let hit_record = HitRecord {
+            point: Point3::default(),
+            normal: Vec3::default(),
+            t: 1.0,
+            u: 0.0,
+            v: 0.0,
+            front_face: true,
+            material: &isotropic,
+        };
+        assert!(hit_record.is_in_medium());
+        // Surfaces are never in a medium, even without a normal.
+        let matte = Lambertian::new(&Color::new(0.8, 0.8, 0.8));
+        let surface = HitRecord {
+            material: &matte,
+            ..hit_record
+        };
+        assert!(!surface.is_in_medium());
+
+        let ray = Ray::new(Point3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let mut sampler = IndependentSampler::new(0);
+        let record = isotropic.scatter(&ray, &hit_record, &mut sampler).unwrap();
+        assert_eq!(Color::new(0.8, 0.8, 0.8), record.attenuation);
+
+        // Light is sent back as much as forward.
+        let Scatter::Diffuse(pdf) = record.scatter else {
+            panic!("a medium scatters diffusely");
+        };
+        for direction in [ray.direction(), -ray.direction()] {
+            let scattered = Ray::new(Point3::default(), direction, 0.0);
+            let scattering_pdf = isotropic.scattering_pdf(&ray, &hit_record, &scattered);
+            assert_eq!(pdf.value(&direction), scattering_pdf);
+            assert!((scattering_pdf - 0.25 / std::f64::consts::PI).abs() < 1e-12);
+        }
+    }
+}
@@ -1,49 +1,71 @@
 use crate::hittable::HitRecord;
-use crate::material::Material;
-use crate::{Color, Ray, Vec3};
+use crate::material::{Material, Scatter, ScatterRecord};
+use crate::pdf::CosinePdf;
+use crate::sampler::Sampler;
+use crate::texture::{SolidColor, Texture};
+use crate::{dot, Color, Ray};
 
 #[derive(Debug, Copy, Clone)]
-pub struct Lambertian {
-    albedo: Color,
+pub struct Lambertian<T: Texture = SolidColor> {
+    albedo: T,
 }
 
 impl Lambertian {
     pub fn new(color: &Color) -> Lambertian {
-        Lambertian { albedo: *color }
+        Lambertian {
+            albedo: SolidColor::new(*color),
+        }
     }
 }
 
-impl Material for Lambertian {
-    fn scatter(&self, ray: &Ray, hit_record: &HitRecord) -> Option<(Ray, Color)> {
-        let mut scatter_direction = hit_record.normal + Vec3::random_unit_vector();
+impl<T: Texture> Lambertian<T> {
+    pub fn with_texture(albedo: T) -> Self {
+        Lambertian { albedo }
+    }
+}
 
-        // Catch degenerate scatter direction
-        if scatter_direction.near_zero() {
-            scatter_direction = hit_record.normal;
-        }
+impl<T: Texture> Material for Lambertian<T> {
+    fn scatter(
+        &self,
+        _ray: &Ray,
+        hit_record: &HitRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        let attenuation = self
+            .albedo
+            .value(hit_record.u, hit_record.v, &hit_record.point);
+        Some(ScatterRecord {
+            attenuation,
+            scatter: Scatter::Diffuse(Box::new(CosinePdf::new(&hit_record.normal))),
+        })
+    }
 
-        let result = Ray::new(hit_record.point, scatter_direction, ray.time());
-        Some((result, self.albedo))
+    // The scattered directions follow the cosine to the normal.
+    fn scattering_pdf(&self, _ray: &Ray, hit_record: &HitRecord, scattered: &Ray) -> f64 {
+        let cosine = dot(&hit_record.normal, &scattered.direction().unit_vector());
+        cosine.max(0.0) / std::f64::consts::PI
     }
 }
 
-impl PartialEq for Lambertian {
+impl<T: Texture + PartialEq> PartialEq for Lambertian<T> {
     fn eq(&self, other: &Self) -> bool {
         self.albedo == other.albedo
     }
 }
 
-impl Eq for Lambertian {}
+impl<T: Texture + PartialEq> Eq for Lambertian<T> {}
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sampler::IndependentSampler;
+    use crate::{Point3, Vec3};
 
     #[test]
     fn test_init() {
         let color = Color::new(1.0, 0.5, 0.1);
         let lambertian = Lambertian::new(&color);
-        assert_eq!(color, lambertian.albedo);
+        assert_eq!(SolidColor::new(color), lambertian.albedo);
     }
 
     #[test]
@@ -58,4 +80,33 @@ mod tests {
         assert_eq!(lambertian_a, lambertian_b);
         assert_ne!(lambertian_a, lambertian_c);
     }
+
+    #[test]
+    fn test_scatter() {
+        let lambertian = Lambertian::new(&Color::new(0.5, 0.5, 0.5));
+        let hit_record = HitRecord {
+            point: Point3::default(),
+            normal: Vec3::new(0.0, 0.0, 1.0),
+            t: 1.0,
+            u: 0.0,
+            v: 0.0,
+            front_face: true,
+            material: &lambertian,
+        };
+        let ray = Ray::new(Point3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let mut sampler = IndependentSampler::new(0);
+        let record = lambertian.scatter(&ray, &hit_record, &mut sampler).unwrap();
+        assert_eq!(Color::new(0.5, 0.5, 0.5), record.attenuation);
+
+        // Directions are drawn exactly as the surface scatters them.
+        let Scatter::Diffuse(pdf) = record.scatter else {
+            panic!("a matte surface scatters diffusely");
+        };
+        for _ in 0..10 {
+            let scattered = Ray::new(Point3::default(), pdf.generate(&mut sampler), 0.0);
+            let scattering_pdf = lambertian.scattering_pdf(&ray, &hit_record, &scattered);
+            assert!(scattering_pdf > 0.0);
+            assert!((pdf.value(&scattered.direction()) - scattering_pdf).abs() < 1e-12);
+        }
+    }
 }
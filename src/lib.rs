@@ -0,0 +1,74 @@
+// Ray tracer following the Ray Tracing in One Weekend series. The types needed to build a scene
+// and render it are re-exported here, the modules give access to the rest.
+
+pub use crate::background::Background;
+pub use crate::bvh::BvhNode;
+pub use crate::camera::{Camera, CameraSettings};
+pub use crate::constant_medium::ConstantMedium;
+pub use crate::dielectric::Dielectric;
+pub use crate::diffuse_light::DiffuseLight;
+pub use crate::filter::{FilterKind, PixelFilter};
+pub use crate::framebuffer::FrameBuffer;
+pub use crate::hittable::{HitRecord, Hittable};
+pub use crate::hittable_list::HittableList;
+pub use crate::image::{Image, ImageFormat};
+pub use crate::integrator::{Integrator, IntegratorKind};
+pub use crate::isotropic::Isotropic;
+pub use crate::lambertian::Lambertian;
+pub use crate::material::{Material, Scatter, ScatterRecord};
+pub use crate::metal::Metal;
+pub use crate::photon_map::PhotonSettings;
+pub use crate::ray::Ray;
+pub use crate::render::{render, PathDepth, RenderOptions, Splats};
+pub use crate::sampler::{Sampler, SamplerKind};
+pub use crate::scene_file::{load_scene, parse_scene, Scene, SceneError};
+pub use crate::scenes::{builtin_scene, SceneName};
+pub use crate::texture::Texture;
+pub use crate::tone_mapping::DisplayTransform;
+pub use crate::vec3::{cross, dot, Vec3};
+pub use crate::vec3::{Vec3 as Color, Vec3 as Point3};
+
+pub mod aabb;
+pub mod aarect;
+pub mod background;
+pub mod bdpt;
+pub mod box_shape;
+pub mod bvh;
+pub mod camera;
+pub mod checkpoint;
+pub mod constant_medium;
+pub mod dielectric;
+pub mod diffuse_light;
+pub mod filter;
+pub mod flip_face;
+pub mod framebuffer;
+#[cfg(test)]
+mod golden;
+mod hdr;
+pub mod hittable;
+pub mod hittable_list;
+pub mod image;
+pub mod integrator;
+pub mod isotropic;
+pub mod lambertian;
+pub mod material;
+pub mod mesh;
+pub mod metal;
+pub mod moving_sphere;
+pub mod obj;
+pub mod onb;
+pub mod pdf;
+pub mod perlin;
+pub mod photon_map;
+mod png;
+pub mod ray;
+pub mod render;
+pub mod sampler;
+pub mod scene_file;
+pub mod scenes;
+pub mod sphere;
+pub mod texture;
+pub mod tone_mapping;
+pub mod triangle;
+pub mod utils;
+pub mod vec3;
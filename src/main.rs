@@ -1,249 +1,405 @@
 use std::fs::File;
-use std::io::Write;
-
-use moving_sphere::MovingSphere;
-use rayon::prelude::*;
-
-use Vec3 as Color;
-use Vec3 as Point3;
-
-use crate::camera::Camera;
-use crate::dielectric::Dielectric;
-use crate::hittable::Hittable;
-use crate::hittable_list::HittableList;
-use crate::lambertian::Lambertian;
-use crate::metal::Metal;
-use crate::ray::Ray;
-use crate::sphere::Sphere;
-use crate::utils::{color_to_rbg, random_f64, random_f64_range};
-use crate::vec3::{dot, Vec3};
-
-mod camera;
-mod dielectric;
-mod hittable;
-mod hittable_list;
-mod lambertian;
-mod material;
-mod metal;
-mod moving_sphere;
-mod ray;
-mod sphere;
-mod utils;
-mod vec3;
-
-fn linear_blend(t: f64, start: Color, end: Color) -> Color {
-    (1.0 - t) * start + t * end
-}
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
-fn ray_color(ray: &Ray, world: &HittableList, depth: i32) -> Color {
-    let white = Color::new(1.0, 1.0, 1.0);
-    let light_blue = Color::new(0.5, 0.7, 1.0);
+use ray_tracing_in_one_weekend::aabb::Aabb;
+use ray_tracing_in_one_weekend::checkpoint::{files_hash, Checkpoint};
+use ray_tracing_in_one_weekend::render::ProgressiveRender;
+use ray_tracing_in_one_weekend::sampler::SamplerKind;
+use ray_tracing_in_one_weekend::{
+    builtin_scene, load_scene, Background, BvhNode, ConstantMedium, DisplayTransform, FrameBuffer,
+    Hittable, ImageFormat, Isotropic, RenderOptions, Vec3,
+};
 
-    if depth <= 0 {
-        return Color::new(0.0, 0.0, 0.0);
-    }
+use crate::cli::Command;
 
-    if let Some(hit) = world.hit(ray, 0.001, f64::INFINITY) {
-        if let Some((scattered, attenuation)) = hit.material.scatter(ray, &hit) {
-            return attenuation * ray_color(&scattered, world, depth - 1);
-        }
-        return Color::default();
-    }
+mod cli;
 
-    let unit_direction = ray.direction().unit_vector();
-    let t = 0.5 * (unit_direction.y() + 1.0);
+// Time between two saves of the checkpoint file.
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(30);
 
-    linear_blend(t, white, light_blue)
+fn vector(vector: &Vec3) -> String {
+    format!("{},{},{}", vector.x(), vector.y(), vector.z())
 }
 
-#[allow(dead_code)]
-fn scene() -> HittableList {
-    let mut world_mut = HittableList::new();
-
-    let ground = Lambertian::new(&Color::new(0.8, 0.8, 0.0));
-    let center = Lambertian::new(&Color::new(0.1, 0.2, 0.5));
-    let left = Dielectric::new(1.5);
-    let right = Metal::new(&Color::new(0.8, 0.6, 0.2), 0.0);
-
-    world_mut.add(Box::new(Sphere::new(
-        Point3::new(0.0, -100.5, -1.0),
-        100.0,
-        ground,
-    )));
-    world_mut.add(Box::new(Sphere::new(
-        Point3::new(0.0, 0.0, -1.0),
-        0.5,
-        center,
-    )));
-    world_mut.add(Box::new(Sphere::new(
-        Point3::new(-1.0, 0.0, -1.0),
-        0.5,
-        left,
-    )));
-    world_mut.add(Box::new(Sphere::new(
-        Point3::new(-1.0, 0.0, -1.0),
-        -0.45,
-        left,
-    )));
-    world_mut.add(Box::new(Sphere::new(
-        Point3::new(1.0, 0.0, -1.0),
-        0.5,
-        right,
-    )));
-
-    world_mut
+// Check that a checkpoint was made with the settings of `expected`, the checkpoint of this
+// render, to be resumed. The seed is only checked when given on the command line.
+fn check_checkpoint(
+    checkpoint: &Checkpoint,
+    expected: &Checkpoint,
+    seed: Option<u64>,
+) -> Result<(), String> {
+    if checkpoint.scene != expected.scene {
+        return Err(format!(
+            "the checkpoint is a render of {}, not of {}",
+            checkpoint.scene, expected.scene
+        ));
+    }
+    if checkpoint.scene_hash != expected.scene_hash {
+        return Err(format!(
+            "{} changed since the checkpoint was saved",
+            expected.scene
+        ));
+    }
+    if checkpoint.camera != expected.camera {
+        let camera = checkpoint.camera;
+        return Err(format!(
+            "the checkpoint was rendered with --look-from {} --look-at {} --view-up {} --vfov {} --aperture {} --focus-distance {}",
+            vector(&camera.look_from),
+            vector(&camera.look_at),
+            vector(&camera.view_up),
+            camera.vertical_field_of_view,
+            camera.aperture,
+            camera.focus_distance
+        ));
+    }
+    if checkpoint.background != expected.background {
+        return Err(match checkpoint.background {
+            Background::Sky => "the checkpoint was rendered with --background sky".to_string(),
+            Background::Solid(color) => format!(
+                "the checkpoint was rendered with --background {}",
+                vector(&color)
+            ),
+        });
+    }
+    if (checkpoint.image_width, checkpoint.image_height)
+        != (expected.image_width, expected.image_height)
+    {
+        return Err(format!(
+            "the checkpoint is a {}x{} image, not {}x{}",
+            checkpoint.image_width,
+            checkpoint.image_height,
+            expected.image_width,
+            expected.image_height
+        ));
+    }
+    if checkpoint.adaptive != expected.adaptive {
+        return Err(match checkpoint.adaptive {
+            Some(adaptive) => format!(
+                "the checkpoint was rendered with --adaptive {} --min-samples {}",
+                adaptive.threshold, adaptive.min_samples
+            ),
+            None => "the checkpoint was rendered without --adaptive".to_string(),
+        });
+    }
+    if checkpoint.depth != expected.depth {
+        let depth = checkpoint.depth;
+        return Err(format!(
+            "the checkpoint was rendered with --depth {} --diffuse-depth {} --specular-depth {} --transmission-depth {} --roulette-depth {}",
+            depth.max, depth.diffuse, depth.specular, depth.transmission, depth.roulette
+        ));
+    }
+    if seed.is_some_and(|seed| seed != checkpoint.seed) {
+        return Err(format!(
+            "the checkpoint was rendered with --seed {}",
+            checkpoint.seed
+        ));
+    }
+    if checkpoint.filter != expected.filter {
+        return Err(format!(
+            "the checkpoint was rendered with --filter {} --filter-radius {}",
+            checkpoint.filter.kind.name(),
+            checkpoint.filter.radius
+        ));
+    }
+    if checkpoint.integrator != expected.integrator {
+        return Err(format!(
+            "the checkpoint was rendered with --integrator {}",
+            checkpoint.integrator.name()
+        ));
+    }
+    if checkpoint.photons != expected.photons {
+        return Err(format!(
+            "the checkpoint was rendered with --photons {} --photon-nearest {}",
+            checkpoint.photons.count, checkpoint.photons.nearest
+        ));
+    }
+    if checkpoint.sampler != expected.sampler {
+        return Err(format!(
+            "the checkpoint was rendered with --sampler {}",
+            checkpoint.sampler.name()
+        ));
+    }
+    // The strata depend on the number of samples, adding samples would change the image.
+    if checkpoint.sampler == SamplerKind::Stratified
+        && checkpoint.samples_per_pixel != expected.samples_per_pixel
+    {
+        return Err(format!(
+            "the checkpoint was rendered with --sampler stratified --samples {}",
+            checkpoint.samples_per_pixel
+        ));
+    }
+    Ok(())
 }
 
-fn random_scene() -> HittableList {
-    let mut world = HittableList::new();
-
-    let ground_material = Lambertian::new(&Color::new(0.5, 0.5, 0.5));
-    world.add(Box::new(Sphere::new(
-        Point3::new(0.0, -1000.0, 0.0),
-        1000.0,
-        ground_material,
-    )));
-
-    for a in -11..11 {
-        for b in -11..11 {
-            let center = Point3::new(
-                a as f64 + 0.9 * random_f64(),
-                0.2,
-                b as f64 + 0.9 * random_f64(),
-            );
-
-            let random = random_f64();
-            if random < 0.8 {
-                // diffuse
-                let albedo = Color::random() * Color::random();
-                let center2 = center + Vec3::new(0.0, random_f64_range(0.0, 0.5), 0.0);
-                world.add(Box::new(MovingSphere::new(
-                    center,
-                    center2,
-                    0.0,
-                    1.0,
-                    0.2,
-                    Lambertian::new(&albedo),
-                )));
-            } else if random < 0.95 {
-                // metal
-                world.add(Box::new(Sphere::new(
-                    center,
-                    0.2,
-                    Metal::new(&Color::random(), random_f64_range(0.0, 0.5)),
-                )));
-            } else {
-                // glass
-                world.add(Box::new(Sphere::new(center, 0.2, Dielectric::new(1.5))));
-            }
+fn main() {
+    let settings = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(Command::Render(settings)) => *settings,
+        Ok(Command::Help) => {
+            print!("{}", cli::USAGE);
+            return;
+        }
+        Err(message) => {
+            eprintln!("error: {message}\n\n{}", cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if let Some(threads) = settings.threads {
+        if let Err(error) = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+        {
+            eprintln!("error: unable to start {threads} render threads: {error}");
+            std::process::exit(1);
         }
     }
 
-    world.add(Box::new(Sphere::new(
-        Point3::new(0.0, 1.0, 0.0),
-        1.0,
-        Dielectric::new(1.5),
-    )));
-
-    world.add(Box::new(Sphere::new(
-        Point3::new(-4.0, 1.0, 0.0),
-        1.0,
-        Lambertian::new(&Color::new(0.4, 0.2, 0.1)),
-    )));
-
-    world.add(Box::new(Sphere::new(
-        Point3::new(4.0, 1.0, 0.0),
-        1.0,
-        Metal::new(&Color::new(0.7, 0.6, 0.5), 0.0),
-    )));
-
-    world
-}
+    // What the checkpoint records of the scene besides the hash of the scene file: where it comes
+    // from and the fog.
+    let mut scene_description = match &settings.scene_file {
+        Some(path) => path.clone(),
+        None => format!("the {:?} scene", settings.scene).to_lowercase(),
+    };
+    if let Some(fog) = settings.fog {
+        let albedo = fog.albedo;
+        scene_description += &format!(
+            " in fog of density {} and albedo {},{},{}",
+            fog.density,
+            albedo.x(),
+            albedo.y(),
+            albedo.z()
+        );
+    }
+    // Checked once the camera is known.
+    let checkpoint = match &settings.checkpoint {
+        Some(path) if settings.resume => match Checkpoint::load(path) {
+            Ok(checkpoint) => Some(checkpoint),
+            Err(error) => {
+                eprintln!("error: {path}: {error}");
+                std::process::exit(1);
+            }
+        },
+        _ => None,
+    };
 
-fn main() {
-    // Image
-    let vertical_field_of_view = 20.0;
-    let aspect_ratio = 16.0 / 9.0;
-    let image_width: u32 = 400;
-    let image_height = (image_width as f64 / aspect_ratio) as u32;
-    let samples_per_pixel = 100;
-    let max_depth = 50;
+    // Every random number derives from the seed, so the same seed gives the same image. A resumed
+    // render keeps the seed of its checkpoint.
+    let seed = match &checkpoint {
+        Some(checkpoint) => checkpoint.seed,
+        None => settings.seed.unwrap_or_else(rand::random),
+    };
 
     // World
-    let world = random_scene();
+    let scene = match &settings.scene_file {
+        Some(path) => match load_scene(path) {
+            Ok(scene) => scene,
+            Err(error) => {
+                eprintln!("error: {path}: {error}");
+                std::process::exit(1);
+            }
+        },
+        None => builtin_scene(settings.scene, seed),
+    };
+    if scene.world.is_empty() {
+        eprintln!("error: the scene does not contain any object");
+        std::process::exit(1);
+    }
+    let lights = scene.lights;
+    let background = settings.background.unwrap_or(scene.background);
+    // The scene file and the meshes and images it refers to, read again as they were just loaded.
+    let scene_hash = settings.scene_file.as_ref().map_or(0, |path| {
+        let mut files = vec![PathBuf::from(path)];
+        files.extend(scene.files.iter().cloned());
+        files_hash(&files)
+    });
 
     // Camera
-    let look_from = Point3::new(13.0, 2.0, 3.0);
-    let look_at = Point3::new(0.0, 0.0, 0.0);
-    let view_up = Vec3::new(0.0, 1.0, 0.0);
-    let aperture = 0.1;
-    let dist_to_focus = 10.0;
-    let camera = Camera::new(
-        &look_from,
-        &look_at,
-        &view_up,
-        vertical_field_of_view,
-        aspect_ratio,
-        aperture,
-        dist_to_focus,
-        0.0,
-        1.0,
-    );
-
-    // Render
-    let image = (0..image_height)
-        .into_par_iter()
-        .rev()
-        .flat_map(|index_height| {
-            eprintln!("Lines remaining: {index_height}");
-            (0..image_width)
-                .flat_map(|index_width| {
-                    let pixel_color: Vec3 = (0..samples_per_pixel)
-                        .map(|_| {
-                            let u =
-                                (index_width as f64 + random_f64()) / ((image_width - 1) as f64);
-                            let v =
-                                (index_height as f64 + random_f64()) / ((image_height - 1) as f64);
-                            let ray = camera.get_ray(u, v);
-                            ray_color(&ray, &world, max_depth)
-                        })
-                        .sum();
-
-                    color_to_rbg(pixel_color, samples_per_pixel)
-                })
-                .collect::<Vec<u8>>()
-        })
-        .collect::<Vec<u8>>();
-
-    // Write result to file
-    let mut file = File::create("result.ppm").unwrap();
-    file.write_all(b"P3\n").expect("Unable to write data");
-    file.write_all(format!("{image_width} {image_height}\n").as_bytes())
-        .expect("Unable to write data");
-    file.write_all(b"255\n").expect("Unable to write data");
-
-    for rgb in image.chunks(3) {
-        file.write_all(format!("{} {} {}\n", rgb[0], rgb[1], rgb[2]).as_bytes())
-            .expect("Unable to write data");
+    let camera_settings = match settings.camera.apply(&scene.camera) {
+        Ok(camera_settings) => camera_settings,
+        Err(message) => {
+            eprintln!("error: {message}");
+            std::process::exit(2);
+        }
+    };
+    let camera = camera_settings.build(settings.aspect_ratio());
+
+    // The checkpoint of this render, without its tiles yet.
+    let expected = Checkpoint {
+        scene: scene_description,
+        scene_hash,
+        camera: camera_settings,
+        background,
+        image_width: settings.image_width,
+        image_height: settings.image_height,
+        samples_per_pixel: settings.samples_per_pixel,
+        adaptive: settings.adaptive,
+        depth: settings.depth,
+        seed,
+        integrator: settings.integrator,
+        photons: settings.photons,
+        sampler: settings.sampler,
+        filter: settings.filter,
+        tiles: Vec::new(),
+        splats: Vec::new(),
+    };
+    if let Some(checkpoint) = &checkpoint {
+        if let Err(error) = check_checkpoint(checkpoint, &expected, settings.seed) {
+            eprintln!(
+                "error: {}: {error}",
+                settings.checkpoint.as_deref().unwrap_or_default()
+            );
+            std::process::exit(1);
+        }
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
 
-    #[test]
-    fn linear_blend_test() {
-        let black = Color::new(0.0, 0.0, 0.0);
-        let white = Color::new(1.0, 1.0, 1.0);
-        let expected_grey = Color::new(0.5, 0.5, 0.5);
-
-        let grey_0_0 = linear_blend(0.0, white, black);
-        let grey_0_5 = linear_blend(0.5, white, black);
-        let grey_1_0 = linear_blend(1.0, white, black);
+    // The fog reaches past the scene and the camera.
+    let mut world = scene.world;
+    if let Some(fog) = settings.fog {
+        let look_from = camera_settings.look_from;
+        let bounds = world
+            .bounding_box(0.0, 1.0)
+            .unwrap_or(Aabb::new(look_from, look_from));
+        world.add(Box::new(ConstantMedium::fog(
+            &bounds,
+            &look_from,
+            fog.density,
+            Isotropic::new(&fog.albedo),
+        )));
+    }
+    let world = BvhNode::new(world, 0.0, 1.0);
+
+    // Render, in one go unless the render is progressive or saves checkpoints.
+    let in_one_go = !settings.is_progressive() && settings.checkpoint.is_none();
+    let mut options = RenderOptions::default();
+    options.image_width = settings.image_width;
+    options.image_height = settings.image_height;
+    options.samples_per_pixel = settings.samples_per_pixel;
+    options.depth = settings.depth;
+    options.seed = seed;
+    options.integrator = settings.integrator;
+    options.photons = settings.photons;
+    options.sampler = settings.sampler;
+    options.filter = settings.filter;
+    options.adaptive = settings.adaptive;
+    options.show_progress = in_one_go;
+    let display = DisplayTransform::new(
+        settings.exposure,
+        settings.tone_mapper.tone_mapper(),
+        settings.transfer_function,
+    );
+    // Written next to the output then renamed, so an image viewer never sees a partial preview.
+    let save = |framebuffer: &FrameBuffer| {
+        let partial = format!("{}.part", settings.output);
+        if let Err(error) = framebuffer
+            .save(&partial, settings.format, &display)
+            .and_then(|_| std::fs::rename(&partial, &settings.output))
+        {
+            eprintln!("error: unable to write {}: {error}", settings.output);
+            std::process::exit(1);
+        }
+    };
+
+    let mut progressive = match checkpoint {
+        Some(checkpoint) => {
+            match ProgressiveRender::with_tiles(
+                &world,
+                &lights,
+                &camera,
+                &background,
+                &options,
+                checkpoint.tiles,
+                checkpoint.splats,
+            ) {
+                Ok(progressive) => progressive,
+                Err(error) => {
+                    eprintln!(
+                        "error: {}: {error}",
+                        settings.checkpoint.unwrap_or_default()
+                    );
+                    std::process::exit(1);
+                }
+            }
+        }
+        None => ProgressiveRender::new(&world, &lights, &camera, &background, &options),
+    };
+    let save_checkpoint = |progressive: &ProgressiveRender| {
+        let Some(path) = &settings.checkpoint else {
+            return;
+        };
+        let checkpoint = Checkpoint {
+            tiles: progressive.tiles().to_vec(),
+            splats: progressive.splats().sums(),
+            ..expected.clone()
+        };
+        if let Err(error) = checkpoint.save(path) {
+            eprintln!("error: unable to write {path}: {error}");
+            std::process::exit(1);
+        }
+    };
+
+    // Progressive renders add one sample per pixel at a time, the others finish a few tiles at a
+    // time, writing previews and checkpoints along the way.
+    let start = Instant::now();
+    let mut last_preview = start;
+    let mut last_checkpoint = start;
+    if in_one_go {
+        progressive.finish_tiles(usize::MAX);
+    }
+    while !progressive.is_done() {
+        if settings.is_progressive() {
+            progressive.add_samples(1);
+            eprintln!(
+                "Samples: {}/{}",
+                progressive.samples(),
+                settings.samples_per_pixel
+            );
+        } else {
+            progressive.finish_tiles(rayon::current_num_threads());
+            eprintln!("Tiles remaining: {}", progressive.unfinished_tiles());
+        }
 
-        assert_eq!(white, grey_0_0);
-        assert_eq!(expected_grey, grey_0_5);
-        assert_eq!(black, grey_1_0);
+        if settings
+            .time_limit
+            .is_some_and(|limit| start.elapsed() >= limit)
+        {
+            eprintln!("Time limit reached");
+            break;
+        }
+        if settings
+            .preview_interval
+            .is_some_and(|interval| last_preview.elapsed() >= interval)
+        {
+            save(&progressive.framebuffer());
+            last_preview = Instant::now();
+        }
+        if last_checkpoint.elapsed() >= CHECKPOINT_INTERVAL {
+            save_checkpoint(&progressive);
+            last_checkpoint = Instant::now();
+        }
+    }
+    save_checkpoint(&progressive);
+    save(&progressive.framebuffer());
+
+    if settings.adaptive.is_some() {
+        let counts = progressive.sample_counts();
+        let total: u64 = counts.iter().map(|&count| count as u64).sum();
+        eprintln!(
+            "Average samples per pixel: {:.1}",
+            total as f64 / counts.len() as f64
+        );
+    }
+    if let Some(path) = &settings.heatmap {
+        let format = ImageFormat::from_path(path).unwrap_or(ImageFormat::Png);
+        let result = File::create(path).and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            progressive.heatmap().write(&mut writer, format)?;
+            writer.flush()
+        });
+        if let Err(error) = result {
+            eprintln!("error: unable to write {path}: {error}");
+            std::process::exit(1);
+        }
     }
 }
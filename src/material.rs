@@ -1,6 +1,46 @@
 use crate::hittable::HitRecord;
-use crate::{Color, Ray};
+use crate::pdf::Pdf;
+use crate::sampler::Sampler;
+use crate::{Color, Point3, Ray};
+
+// How a material sends on the light arriving along a ray.
+pub struct ScatterRecord {
+    pub attenuation: Color,
+    pub scatter: Scatter,
+}
+
+pub enum Scatter {
+    // The one direction chosen by a mirror or glass, with no density to weight it: the light found
+    // along the ray is attenuated as is, and lights are not sampled.
+    Specular(Ray),
+    // Directions to draw from the density, the light found along them being weighted by
+    // `scattering_pdf` over the density.
+    Diffuse(Box<dyn Pdf>),
+}
 
 pub trait Material: Sync {
-    fn scatter(&self, ray: &Ray, hit_record: &HitRecord) -> Option<(Ray, Color)>;
+    fn scatter(
+        &self,
+        ray: &Ray,
+        hit_record: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord>;
+
+    // Density over solid angle of the light arriving along `scattered` being sent back along `ray`,
+    // the surface reflecting `attenuation * scattering_pdf` of it. Only used for
+    // `Scatter::Diffuse`.
+    fn scattering_pdf(&self, _ray: &Ray, _hit_record: &HitRecord, _scattered: &Ray) -> f64 {
+        0.0
+    }
+
+    // Light given off by the surface, black for everything but light sources.
+    fn emitted(&self, _u: f64, _v: f64, _point: &Point3) -> Color {
+        Color::default()
+    }
+
+    // Phase function of a participating medium, scattering light inside a volume rather than at a
+    // surface, so its hits have no normal.
+    fn is_medium(&self) -> bool {
+        false
+    }
 }
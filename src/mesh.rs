@@ -0,0 +1,291 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::triangle::{intersect_triangle, triangle_box, triangle_hit};
+use crate::{Point3, Ray, Vec3};
+
+// Faces per leaf of the mesh hierarchy, testing a few triangles is cheaper than another level.
+const MAX_LEAF_FACES: usize = 4;
+
+// A triangle of a mesh, indexing the vertex attributes and the materials shared by the mesh.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MeshFace {
+    pub positions: [usize; 3],
+    pub normals: Option<[usize; 3]>,
+    pub texture_coordinates: Option<[usize; 3]>,
+    pub material: usize,
+}
+
+// Node of the bounding volume hierarchy over the faces, stored in a flat array. Leaves cover
+// `count` faces starting at `start`, inner nodes have their left child right after them and their
+// right child at `start`.
+struct MeshNode {
+    bounding_box: Aabb,
+    start: usize,
+    count: usize,
+}
+
+// Triangle mesh storing each vertex once, with its own hierarchy so the scene BVH sees a single
+// object.
+pub struct Mesh<Mat: Material> {
+    positions: Vec<Point3>,
+    normals: Vec<Vec3>,
+    texture_coordinates: Vec<(f64, f64)>,
+    faces: Vec<MeshFace>,
+    materials: Vec<Mat>,
+    nodes: Vec<MeshNode>,
+}
+
+impl<Mat: Material> Mesh<Mat> {
+    // Faces must index existing attributes and materials, the mesh must have at least one face.
+    pub fn new(
+        positions: Vec<Point3>,
+        normals: Vec<Vec3>,
+        texture_coordinates: Vec<(f64, f64)>,
+        faces: Vec<MeshFace>,
+        materials: Vec<Mat>,
+    ) -> Self {
+        assert!(!faces.is_empty(), "Cannot build a Mesh without faces");
+        for face in faces.iter() {
+            assert!(face.positions.iter().all(|&index| index < positions.len()));
+            assert!(face
+                .normals
+                .iter()
+                .flatten()
+                .all(|&index| index < normals.len()));
+            assert!(face
+                .texture_coordinates
+                .iter()
+                .flatten()
+                .all(|&index| index < texture_coordinates.len()));
+            assert!(face.material < materials.len());
+        }
+
+        let mut mesh = Mesh {
+            positions,
+            normals,
+            texture_coordinates,
+            faces,
+            materials,
+            nodes: Vec::new(),
+        };
+        mesh.build_hierarchy();
+        mesh
+    }
+
+    fn vertices(&self, face: &MeshFace) -> [Point3; 3] {
+        face.positions.map(|index| self.positions[index])
+    }
+
+    fn build_hierarchy(&mut self) {
+        let mut boxes: Vec<(Aabb, MeshFace)> = self
+            .faces
+            .iter()
+            .map(|face| (triangle_box(&self.vertices(face)), *face))
+            .collect();
+        let count = boxes.len();
+
+        self.nodes.clear();
+        Mesh::<Mat>::build_node(&mut self.nodes, &mut boxes, 0, count);
+        self.faces = boxes.into_iter().map(|(_, face)| face).collect();
+    }
+
+    // Same median split as the scene BVH, reordering the faces so each leaf is a contiguous range.
+    fn build_node(
+        nodes: &mut Vec<MeshNode>,
+        boxes: &mut [(Aabb, MeshFace)],
+        start: usize,
+        count: usize,
+    ) {
+        let faces = &mut boxes[start..start + count];
+        let bounding_box = faces
+            .iter()
+            .map(|(face_box, _)| *face_box)
+            .reduce(|lhs, rhs| Aabb::surrounding_box(&lhs, &rhs))
+            .unwrap_or_default();
+
+        let index = nodes.len();
+        nodes.push(MeshNode {
+            bounding_box,
+            start,
+            count,
+        });
+        if count <= MAX_LEAF_FACES {
+            return;
+        }
+
+        let centroids = faces
+            .iter()
+            .map(|(face_box, _)| Aabb::new(face_box.centroid(), face_box.centroid()))
+            .reduce(|lhs, rhs| Aabb::surrounding_box(&lhs, &rhs))
+            .unwrap_or_default();
+        let extent = centroids.max() - centroids.min();
+        let axis = if extent.x() > extent.y() && extent.x() > extent.z() {
+            0
+        } else if extent.y() > extent.z() {
+            1
+        } else {
+            2
+        };
+
+        let middle = count / 2;
+        faces.select_nth_unstable_by(middle, |(lhs, _), (rhs, _)| {
+            lhs.centroid()[axis].total_cmp(&rhs.centroid()[axis])
+        });
+
+        Mesh::<Mat>::build_node(nodes, boxes, start, middle);
+        let right = nodes.len();
+        Mesh::<Mat>::build_node(nodes, boxes, start + middle, count - middle);
+        nodes[index].start = right;
+        nodes[index].count = 0;
+    }
+}
+
+impl<Mat: Material> Hittable for Mesh<Mat> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let mut closest: Option<(usize, (f64, f64, f64))> = None;
+        let mut closest_so_far = t_max;
+        // Nodes left to visit, on the stack to not allocate for every ray. Nodes split their faces
+        // at the median so the tree is at most log2 of the face count deep, and the stack never
+        // holds more than one node per level plus one.
+        let mut stack = [0; 64];
+        let mut length = 1;
+
+        while length > 0 {
+            length -= 1;
+            let index = stack[length];
+            let node = &self.nodes[index];
+            if !node.bounding_box.hit(ray, t_min, closest_so_far) {
+                continue;
+            }
+
+            if node.count == 0 {
+                stack[length] = node.start;
+                stack[length + 1] = index + 1;
+                length += 2;
+                continue;
+            }
+
+            for face_index in node.start..node.start + node.count {
+                let vertices = self.vertices(&self.faces[face_index]);
+                if let Some(intersection) =
+                    intersect_triangle(ray, &vertices, t_min, closest_so_far)
+                {
+                    closest_so_far = intersection.0;
+                    closest = Some((face_index, intersection));
+                }
+            }
+        }
+
+        // Only the closest face needs a full hit record.
+        let (face_index, intersection) = closest?;
+        let face = &self.faces[face_index];
+        Some(triangle_hit(
+            ray,
+            &self.vertices(face),
+            face.normals
+                .map(|indices| indices.map(|index| self.normals[index])),
+            face.texture_coordinates
+                .map(|indices| indices.map(|index| self.texture_coordinates[index])),
+            intersection,
+            &self.materials[face.material],
+        ))
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<Aabb> {
+        Some(self.nodes[0].bounding_box)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Color, Lambertian};
+
+    fn face(positions: [usize; 3], material: usize) -> MeshFace {
+        MeshFace {
+            positions,
+            normals: None,
+            texture_coordinates: None,
+            material,
+        }
+    }
+
+    // A row of unit squares along x, two triangles each, alternating between two materials.
+    fn strip(squares: usize) -> Mesh<Lambertian> {
+        let mut positions = Vec::new();
+        for i in 0..=squares {
+            positions.push(Point3::new(i as f64, 0.0, 0.0));
+            positions.push(Point3::new(i as f64, 1.0, 0.0));
+        }
+        let mut faces = Vec::new();
+        for i in 0..squares {
+            let corner = 2 * i;
+            faces.push(face([corner, corner + 2, corner + 3], i % 2));
+            faces.push(face([corner, corner + 3, corner + 1], i % 2));
+        }
+        let materials = vec![
+            Lambertian::new(&Color::new(1.0, 0.0, 0.0)),
+            Lambertian::new(&Color::new(0.0, 1.0, 0.0)),
+        ];
+
+        Mesh::new(positions, Vec::new(), Vec::new(), faces, materials)
+    }
+
+    #[test]
+    fn test_hit() {
+        let mesh = strip(50);
+        assert_eq!(100, mesh.faces.len());
+
+        for x in [0.25, 17.5, 49.9] {
+            let ray = Ray::new(Point3::new(x, 0.5, 3.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+            let hit = mesh.hit(&ray, 0.001, f64::INFINITY).unwrap();
+            assert_eq!(3.0, hit.t);
+            assert_eq!(Point3::new(x, 0.5, 0.0), hit.point);
+            assert_eq!(Vec3::new(0.0, 0.0, 1.0), hit.normal);
+        }
+
+        let outside = Ray::new(Point3::new(50.5, 0.5, 3.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        assert!(mesh.hit(&outside, 0.001, f64::INFINITY).is_none());
+        let along = Ray::new(Point3::new(-1.0, 0.5, 1.0), Vec3::new(1.0, 0.0, 0.0), 0.0);
+        assert!(mesh.hit(&along, 0.001, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_closest_face() {
+        let positions = vec![
+            Point3::new(-1.0, -1.0, 0.0),
+            Point3::new(1.0, -1.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+            Point3::new(-1.0, -1.0, -2.0),
+            Point3::new(1.0, -1.0, -2.0),
+            Point3::new(0.0, 1.0, -2.0),
+        ];
+        let normals = vec![Vec3::new(0.0, 1.0, 0.0)];
+        let back = face([3, 4, 5], 0);
+        let front = MeshFace {
+            normals: Some([0, 0, 0]),
+            ..face([0, 1, 2], 0)
+        };
+        let mesh = Mesh::new(
+            positions,
+            normals,
+            Vec::new(),
+            vec![back, front],
+            vec![Lambertian::new(&Color::new(1.0, 1.0, 1.0))],
+        );
+
+        let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let hit = mesh.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(5.0, hit.t);
+        assert_eq!(Vec3::new(0.0, 1.0, 0.0), hit.normal); // shading normal of the front face
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        let bounding_box = strip(10).bounding_box(0.0, 1.0).unwrap();
+
+        assert!((bounding_box.min() - Point3::new(0.0, 0.0, 0.0)).length() < 0.001);
+        assert!((bounding_box.max() - Point3::new(10.0, 1.0, 0.0)).length() < 0.001);
+    }
+}
@@ -1,30 +1,50 @@
 use crate::hittable::HitRecord;
-use crate::material::Material;
-use crate::{Color, Ray, Vec3};
+use crate::material::{Material, Scatter, ScatterRecord};
+use crate::sampler::{sample_unit_ball, Sampler};
+use crate::texture::{SolidColor, Texture};
+use crate::{Color, Ray};
 
 #[derive(Debug, Copy, Clone)]
-pub struct Metal {
-    albedo: Color,
+pub struct Metal<T: Texture = SolidColor> {
+    albedo: T,
     fuzziness: f64,
 }
 
 impl Metal {
     pub fn new(color: &Color, fuzziness: f64) -> Metal {
         Metal {
-            albedo: *color,
+            albedo: SolidColor::new(*color),
             fuzziness,
         }
     }
 }
 
-impl Material for Metal {
-    fn scatter(&self, ray: &Ray, hit_record: &HitRecord) -> Option<(Ray, Color)> {
+impl<T: Texture> Metal<T> {
+    pub fn with_texture(albedo: T, fuzziness: f64) -> Self {
+        Metal { albedo, fuzziness }
+    }
+}
+
+impl<T: Texture> Material for Metal<T> {
+    fn scatter(
+        &self,
+        ray: &Ray,
+        hit_record: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
         let reflected = ray.direction().unit_vector().reflect(&hit_record.normal);
+        let fuzz = sample_unit_ball(sampler.next_2d(), sampler.next_1d());
         let result = Ray::new(
             hit_record.point,
-            reflected + self.fuzziness * Vec3::random_in_unit_sphere(),
+            reflected + self.fuzziness * fuzz,
             ray.time(),
         );
-        Some((result, self.albedo))
+        let attenuation = self
+            .albedo
+            .value(hit_record.u, hit_record.v, &hit_record.point);
+        Some(ScatterRecord {
+            attenuation,
+            scatter: Scatter::Specular(result),
+        })
     }
 }
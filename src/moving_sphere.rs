@@ -1,5 +1,7 @@
+use crate::aabb::Aabb;
 use crate::hittable::{HitRecord, Hittable};
 use crate::material::Material;
+use crate::sphere::sphere_uv;
 use crate::{dot, Point3, Ray, Vec3};
 
 pub struct MovingSphere<Mat: Material> {
@@ -37,7 +39,7 @@ impl<Mat: Material> MovingSphere<Mat> {
 }
 
 impl<Mat: Material> Hittable for MovingSphere<Mat> {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
         let oc = ray.origin() - self.center(ray.time());
         let a = ray.direction().length_squared();
         let half_b = dot(&oc, &ray.direction());
@@ -57,19 +59,31 @@ impl<Mat: Material> Hittable for MovingSphere<Mat> {
             }
         }
 
+        let point = ray.at(root);
+        let outward_normal = (point - self.center(ray.time())) / self.radius;
+        let (u, v) = sphere_uv(&outward_normal);
         let mut hit = HitRecord {
-            point: ray.at(root),
+            point,
             normal: Vec3::default(),
             t: root,
+            u,
+            v,
             front_face: false,
             material: &self.material,
         };
-
-        let outward_normal = (hit.point - self.center(ray.time())) / self.radius;
         hit.set_face_normal(ray, &outward_normal);
 
         Some(hit)
     }
+
+    fn bounding_box(&self, time0: f64, time1: f64) -> Option<Aabb> {
+        // The sphere moves linearly, so the swept volume is bounded by its boxes at both ends.
+        let radius = self.radius.abs();
+        let extent = Vec3::new(radius, radius, radius);
+        let box0 = Aabb::new(self.center(time0) - extent, self.center(time0) + extent);
+        let box1 = Aabb::new(self.center(time1) - extent, self.center(time1) + extent);
+        Some(Aabb::surrounding_box(&box0, &box1))
+    }
 }
 
 impl<Mat: Material> PartialEq<Self> for MovingSphere<Mat> {
@@ -81,3 +95,25 @@ impl<Mat: Material> PartialEq<Self> for MovingSphere<Mat> {
 }
 
 impl<Mat: Material> Eq for MovingSphere<Mat> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Color, Lambertian};
+
+    #[test]
+    fn test_bounding_box() {
+        let lambertian_white = Lambertian::new(&Color::new(1.0, 1.0, 1.0));
+        let sphere = MovingSphere::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 2.0, 0.0),
+            0.0,
+            1.0,
+            1.0,
+            lambertian_white,
+        );
+
+        let expected = Aabb::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 3.0, 1.0));
+        assert_eq!(Some(expected), sphere.bounding_box(0.0, 1.0));
+    }
+}
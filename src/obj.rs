@@ -0,0 +1,609 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::dielectric::Dielectric;
+use crate::diffuse_light::DiffuseLight;
+use crate::lambertian::Lambertian;
+use crate::mesh::{Mesh, MeshFace};
+use crate::metal::Metal;
+use crate::scene_file::{SceneMaterial, SceneTexture};
+use crate::texture::ImageTexture;
+use crate::{Color, Point3, Vec3};
+
+// Wavefront OBJ reader. Supported statements:
+//
+//   v x y z [w]       vertex position, w is ignored
+//   vn x y z          vertex normal
+//   vt u [v] [w]      texture coordinates
+//   f v1 v2 v3 ...    face, each vertex being v, v/vt, v//vn or v/vt/vn, negative indices count
+//                     from the end; polygons are split in a fan of triangles, so must be convex
+//   mtllib a.mtl ...  material libraries, relative to the OBJ file
+//   usemtl name       material of the following faces
+//
+// Other statements (groups, smoothing groups, lines...) are ignored.
+
+// Mesh data read from an OBJ file, with the materials its faces refer to.
+pub struct ObjModel {
+    pub positions: Vec<Point3>,
+    pub normals: Vec<Vec3>,
+    pub texture_coordinates: Vec<(f64, f64)>,
+    pub faces: Vec<MeshFace>,
+    pub materials: Vec<SceneMaterial>,
+    // Files read by `load_obj`: the OBJ, its material libraries and their textures.
+    pub files: Vec<PathBuf>,
+}
+
+impl ObjModel {
+    pub fn into_mesh(self) -> Mesh<SceneMaterial> {
+        Mesh::new(
+            self.positions,
+            self.normals,
+            self.texture_coordinates,
+            self.faces,
+            self.materials,
+        )
+    }
+}
+
+// Load an OBJ file. When `material` is given every face uses it and the material libraries are
+// not read, otherwise `usemtl` names are looked up in the libraries, then in `scene_materials`.
+pub fn load_obj(
+    path: &Path,
+    scene_materials: &HashMap<String, SceneMaterial>,
+    material: Option<SceneMaterial>,
+) -> Result<ObjModel, String> {
+    let read = |path: &Path| {
+        std::fs::read_to_string(path)
+            .map_err(|error| format!("unable to read '{}': {error}", path.display()))
+    };
+    let source = read(path)?;
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut files = vec![path.to_path_buf()];
+    let load_library = |name: &str| {
+        let library_path = directory.join(name);
+        let library_directory = library_path.parent().unwrap_or_else(|| Path::new(""));
+        let source = read(&library_path)?;
+        files.push(library_path.clone());
+        parse_mtl(&source, library_directory, &mut files)
+            .map_err(|error| format!("{}: {error}", library_path.display()))
+    };
+    let mut model = parse_obj(&source, load_library, scene_materials, material)
+        .map_err(|error| format!("{}: {error}", path.display()))?;
+    model.files = files;
+    Ok(model)
+}
+
+pub fn parse_obj<F>(
+    source: &str,
+    mut load_library: F,
+    scene_materials: &HashMap<String, SceneMaterial>,
+    material: Option<SceneMaterial>,
+) -> Result<ObjModel, String>
+where
+    F: FnMut(&str) -> Result<HashMap<String, SceneMaterial>, String>,
+{
+    let single_material = material.is_some();
+    let mut current_material = material.as_ref().map(|_| 0);
+    let mut model = ObjModel {
+        positions: Vec::new(),
+        normals: Vec::new(),
+        texture_coordinates: Vec::new(),
+        faces: Vec::new(),
+        materials: material.into_iter().collect(),
+        files: Vec::new(),
+    };
+    let mut library: HashMap<String, SceneMaterial> = HashMap::new();
+    // Index in `model.materials` of the materials used so far, by name.
+    let mut used_materials: HashMap<String, usize> = HashMap::new();
+
+    for (index, line) in source.lines().enumerate() {
+        let error = |message: String| format!("line {}: {message}", index + 1);
+
+        let content = line.split('#').next().unwrap_or_default();
+        let mut tokens = content.split_whitespace();
+        let statement = match tokens.next() {
+            Some(statement) => statement,
+            None => continue,
+        };
+        let arguments: Vec<&str> = tokens.collect();
+
+        match statement {
+            "v" => {
+                let coordinates = parse_numbers(&arguments, 3, 4).map_err(error)?;
+                model
+                    .positions
+                    .push(Point3::new(coordinates[0], coordinates[1], coordinates[2]));
+            }
+            "vn" => {
+                let coordinates = parse_numbers(&arguments, 3, 3).map_err(error)?;
+                model
+                    .normals
+                    .push(Vec3::new(coordinates[0], coordinates[1], coordinates[2]));
+            }
+            "vt" => {
+                let coordinates = parse_numbers(&arguments, 1, 3).map_err(error)?;
+                let v = coordinates.get(1).copied().unwrap_or(0.0);
+                model.texture_coordinates.push((coordinates[0], v));
+            }
+            "f" => {
+                if arguments.len() < 3 {
+                    return Err(error("a face needs at least 3 vertices".to_string()));
+                }
+                let material = current_material.ok_or_else(|| {
+                    error(
+                        "face without a material, use 'usemtl' or give the mesh a material"
+                            .to_string(),
+                    )
+                })?;
+                let vertices = arguments
+                    .iter()
+                    .map(|vertex| parse_face_vertex(vertex, &model))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(error)?;
+
+                // Fan triangulation around the first vertex.
+                for i in 1..vertices.len() - 1 {
+                    let corners = [vertices[0], vertices[i], vertices[i + 1]];
+                    let all = |attribute: fn(&FaceVertex) -> Option<usize>| {
+                        let indices = corners.map(|corner| attribute(&corner));
+                        match indices {
+                            [Some(a), Some(b), Some(c)] => Some([a, b, c]),
+                            _ => None,
+                        }
+                    };
+                    model.faces.push(MeshFace {
+                        positions: corners.map(|corner| corner.position),
+                        normals: all(|corner| corner.normal),
+                        texture_coordinates: all(|corner| corner.texture_coordinates),
+                        material,
+                    });
+                }
+            }
+            "mtllib" => {
+                if single_material {
+                    continue;
+                }
+                if arguments.is_empty() {
+                    return Err(error("missing material library name".to_string()));
+                }
+                for name in arguments {
+                    library.extend(load_library(name).map_err(error)?);
+                }
+            }
+            "usemtl" => {
+                let name = match arguments[..] {
+                    [name] => name,
+                    _ => return Err(error("expected a single material name".to_string())),
+                };
+                if single_material {
+                    continue;
+                }
+                let material_index = match used_materials.get(name) {
+                    Some(&material_index) => material_index,
+                    None => {
+                        let found = library
+                            .get(name)
+                            .or_else(|| scene_materials.get(name))
+                            .ok_or_else(|| error(format!("unknown material '{name}'")))?;
+                        model.materials.push(found.clone());
+                        used_materials.insert(name.to_string(), model.materials.len() - 1);
+                        model.materials.len() - 1
+                    }
+                };
+                current_material = Some(material_index);
+            }
+            _ => {}
+        }
+    }
+
+    if model.faces.is_empty() {
+        return Err("the model has no faces".to_string());
+    }
+
+    Ok(model)
+}
+
+#[derive(Copy, Clone)]
+struct FaceVertex {
+    position: usize,
+    texture_coordinates: Option<usize>,
+    normal: Option<usize>,
+}
+
+fn parse_face_vertex(vertex: &str, model: &ObjModel) -> Result<FaceVertex, String> {
+    let mut parts = vertex.split('/');
+    let position = parts.next().unwrap_or_default();
+    let texture_coordinates = parts.next().filter(|part| !part.is_empty());
+    let normal = parts.next().filter(|part| !part.is_empty());
+    if parts.next().is_some() {
+        return Err(format!("invalid face vertex '{vertex}'"));
+    }
+
+    Ok(FaceVertex {
+        position: resolve_index(position, model.positions.len(), "vertex")?,
+        texture_coordinates: texture_coordinates
+            .map(|index| {
+                resolve_index(index, model.texture_coordinates.len(), "texture coordinate")
+            })
+            .transpose()?,
+        normal: normal
+            .map(|index| resolve_index(index, model.normals.len(), "normal"))
+            .transpose()?,
+    })
+}
+
+// OBJ indices start at 1, negative ones are relative to the last element read so far.
+fn resolve_index(index: &str, count: usize, kind: &str) -> Result<usize, String> {
+    let value: i64 = index
+        .parse()
+        .map_err(|_| format!("invalid {kind} index '{index}'"))?;
+    let resolved = if value < 0 {
+        count as i64 + value
+    } else {
+        value - 1
+    };
+
+    if value == 0 || resolved < 0 || resolved >= count as i64 {
+        return Err(format!("{kind} index {value} is out of range"));
+    }
+
+    Ok(resolved as usize)
+}
+
+fn parse_numbers(arguments: &[&str], min: usize, max: usize) -> Result<Vec<f64>, String> {
+    if arguments.len() < min || arguments.len() > max {
+        return Err(if min == max {
+            format!("expected {min} numbers")
+        } else {
+            format!("expected {min} to {max} numbers")
+        });
+    }
+
+    arguments
+        .iter()
+        .map(|argument| {
+            argument
+                .parse::<f64>()
+                .map_err(|_| format!("invalid number '{argument}'"))
+        })
+        .collect()
+}
+
+// Companion material library. Only the parameters matching the renderer's materials are read:
+//
+//   Ke r g b   emission, any non black value makes a diffuse light
+//   d / Tr     dissolve / transparency, a transparent material is a dielectric of index Ni
+//   illum      illumination model, 4, 6, 7 and 9 are dielectrics, 3 and 5 metals
+//   Ks, Ns     albedo and Phong exponent of the metals, a low exponent giving a fuzzy metal
+//   Kd         albedo of everything else, which is lambertian
+//   map_Kd     image texture replacing Kd, relative to the library
+//
+// The paths of the textures read are added to `files`.
+pub fn parse_mtl(
+    source: &str,
+    directory: &Path,
+    files: &mut Vec<PathBuf>,
+) -> Result<HashMap<String, SceneMaterial>, String> {
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, MtlParameters)> = None;
+
+    for (index, line) in source.lines().enumerate() {
+        let error = |message: String| format!("line {}: {message}", index + 1);
+
+        let content = line.split('#').next().unwrap_or_default();
+        let mut tokens = content.split_whitespace();
+        let statement = match tokens.next() {
+            Some(statement) => statement,
+            None => continue,
+        };
+        let arguments: Vec<&str> = tokens.collect();
+
+        if statement == "newmtl" {
+            let name = match arguments[..] {
+                [name] => name,
+                _ => return Err(error("expected a single material name".to_string())),
+            };
+            if let Some((name, parameters)) = current.take() {
+                materials.insert(name, parameters.material());
+            }
+            current = Some((name.to_string(), MtlParameters::default()));
+            continue;
+        }
+
+        let parameters = match current.as_mut() {
+            Some((_, parameters)) => parameters,
+            None if ["Kd", "Ks", "Ke", "Ns", "Ni", "d", "Tr", "illum"].contains(&statement) => {
+                return Err(error(format!("'{statement}' before any 'newmtl'")))
+            }
+            None => continue,
+        };
+        let color = |arguments: &[&str]| {
+            let values = parse_numbers(arguments, 3, 3)?;
+            Ok::<Color, String>(Color::new(values[0], values[1], values[2]))
+        };
+        let number = |arguments: &[&str]| Ok::<f64, String>(parse_numbers(arguments, 1, 1)?[0]);
+
+        match statement {
+            "Kd" => parameters.diffuse = color(&arguments).map_err(error)?,
+            "Ks" => parameters.specular = color(&arguments).map_err(error)?,
+            "Ke" => parameters.emission = color(&arguments).map_err(error)?,
+            "Ns" => parameters.shininess = number(&arguments).map_err(error)?,
+            "Ni" => parameters.index_of_refraction = number(&arguments).map_err(error)?,
+            "d" => parameters.dissolve = number(&arguments).map_err(error)?,
+            "Tr" => parameters.dissolve = 1.0 - number(&arguments).map_err(error)?,
+            "illum" => parameters.illumination = number(&arguments).map_err(error)? as i32,
+            // Options may come before the file name, which is last.
+            "map_Kd" => {
+                let name = arguments
+                    .last()
+                    .ok_or_else(|| error("missing texture file name".to_string()))?;
+                let path = directory.join(name);
+                parameters.diffuse_map = Some(ImageTexture::load(&path).map_err(error)?);
+                files.push(path);
+            }
+            _ => {}
+        }
+    }
+
+    if let Some((name, parameters)) = current {
+        materials.insert(name, parameters.material());
+    }
+
+    Ok(materials)
+}
+
+struct MtlParameters {
+    diffuse: Color,
+    diffuse_map: Option<ImageTexture>,
+    specular: Color,
+    emission: Color,
+    shininess: f64,
+    index_of_refraction: f64,
+    dissolve: f64,
+    illumination: i32,
+}
+
+impl Default for MtlParameters {
+    fn default() -> Self {
+        MtlParameters {
+            diffuse: Color::new(0.8, 0.8, 0.8),
+            diffuse_map: None,
+            specular: Color::default(),
+            emission: Color::default(),
+            shininess: 0.0,
+            index_of_refraction: 1.5,
+            dissolve: 1.0,
+            illumination: 2,
+        }
+    }
+}
+
+impl MtlParameters {
+    fn material(&self) -> SceneMaterial {
+        if !self.emission.near_zero() {
+            SceneMaterial::DiffuseLight(DiffuseLight::with_texture(self.emission.into()))
+        } else if self.dissolve < 1.0 || [4, 6, 7, 9].contains(&self.illumination) {
+            SceneMaterial::Dielectric(Dielectric::new(self.index_of_refraction))
+        } else if [3, 5].contains(&self.illumination) {
+            // Usual conversion from a Phong exponent to a roughness.
+            let fuzz = (2.0 / (self.shininess.max(0.0) + 2.0)).sqrt();
+            SceneMaterial::Metal(Metal::with_texture(self.specular.into(), fuzz))
+        } else {
+            let albedo = match &self.diffuse_map {
+                Some(texture) => SceneTexture::Image(texture.clone()),
+                None => self.diffuse.into(),
+            };
+            SceneMaterial::Lambertian(Lambertian::with_texture(albedo))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::{HitRecord, Hittable};
+    use crate::material::Material;
+    use crate::sampler::IndependentSampler;
+    use crate::Ray;
+
+    fn no_library(name: &str) -> Result<HashMap<String, SceneMaterial>, String> {
+        Err(format!("unexpected library '{name}'"))
+    }
+
+    fn white() -> SceneMaterial {
+        SceneMaterial::Lambertian(Lambertian::with_texture(Color::new(1.0, 1.0, 1.0).into()))
+    }
+
+    fn parse_error(source: &str) -> String {
+        match parse_obj(source, no_library, &HashMap::new(), Some(white())) {
+            Ok(_) => panic!("expected the model to be rejected"),
+            Err(error) => error,
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let model = parse_obj(
+            "# a square made of a quad
+            o square
+            v 0 0 0
+            v 1 0 0
+            v 1 1 0
+            v 0 1 0 1.0
+            vt 0 0
+            vt 1 0
+            vt 1 1
+            vn 0 0 1
+            s off
+            f 1/1/1 2/2/1 3/3/1 -1//-1",
+            no_library,
+            &HashMap::new(),
+            Some(white()),
+        )
+        .unwrap();
+
+        assert_eq!(4, model.positions.len());
+        assert_eq!(1, model.materials.len());
+        assert_eq!(
+            vec![
+                MeshFace {
+                    positions: [0, 1, 2],
+                    normals: Some([0, 0, 0]),
+                    texture_coordinates: Some([0, 1, 2]),
+                    material: 0,
+                },
+                // The last corner has no texture coordinates.
+                MeshFace {
+                    positions: [0, 2, 3],
+                    normals: Some([0, 0, 0]),
+                    texture_coordinates: None,
+                    material: 0,
+                },
+            ],
+            model.faces
+        );
+
+        let mesh = model.into_mesh();
+        let ray = Ray::new(Point3::new(0.2, 0.7, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        assert_eq!(1.0, mesh.hit(&ray, 0.001, f64::INFINITY).unwrap().t);
+    }
+
+    #[test]
+    fn test_materials() {
+        let library = |name: &str| {
+            assert_eq!("shapes.mtl", name);
+            parse_mtl(
+                "newmtl glass
+                Ni 1.33
+                d 0.5
+                newmtl lamp
+                Ke 4 4 4",
+                Path::new("scenes"),
+                &mut Vec::new(),
+            )
+        };
+        let mut scene_materials = HashMap::new();
+        scene_materials.insert("floor".to_string(), white());
+
+        let model = parse_obj(
+            "mtllib shapes.mtl
+            v 0 0 0
+            v 1 0 0
+            v 0 1 0
+            usemtl lamp
+            f 1 2 3
+            usemtl floor
+            f 1 2 3
+            usemtl lamp
+            f 1 2 3",
+            library,
+            &scene_materials,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(2, model.materials.len());
+        assert!(matches!(model.materials[0], SceneMaterial::DiffuseLight(_)));
+        assert!(matches!(model.materials[1], SceneMaterial::Lambertian(_)));
+        let materials: Vec<usize> = model.faces.iter().map(|face| face.material).collect();
+        assert_eq!(vec![0, 1, 0], materials);
+    }
+
+    #[test]
+    fn test_mtl() {
+        let mut files = Vec::new();
+        let materials = parse_mtl(
+            "# exported
+            newmtl plastic
+            Kd 0.5 0.1 0.1
+            newmtl chrome
+            illum 3
+            Ks 0.9 0.9 0.9
+            Ns 1000
+            newmtl water
+            Tr 0.2
+            newmtl wall
+            map_Kd -s 2 2 1 bricks.png",
+            Path::new("scenes"),
+            &mut files,
+        )
+        .unwrap();
+
+        assert_eq!(4, materials.len());
+        assert_eq!(vec![Path::new("scenes").join("bricks.png")], files);
+        assert!(matches!(materials["plastic"], SceneMaterial::Lambertian(_)));
+        assert!(matches!(materials["chrome"], SceneMaterial::Metal(_)));
+        assert!(matches!(materials["water"], SceneMaterial::Dielectric(_)));
+        // The brick texture is red with light mortar lines.
+        let SceneMaterial::Lambertian(wall) = &materials["wall"] else {
+            panic!("expected a lambertian material");
+        };
+        let hit = HitRecord {
+            point: Point3::default(),
+            normal: Vec3::new(0.0, 1.0, 0.0),
+            t: 1.0,
+            u: 0.3,
+            v: 0.3,
+            front_face: true,
+            material: wall,
+        };
+        let ray = Ray::new(Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        let attenuation = wall
+            .scatter(&ray, &hit, &mut IndependentSampler::new(0))
+            .unwrap()
+            .attenuation;
+        assert!(attenuation.x() > 2.0 * attenuation.y());
+
+        assert_eq!(
+            Err("line 1: 'Kd' before any 'newmtl'".to_string()),
+            parse_mtl("Kd 1 1 1", Path::new("scenes"), &mut Vec::new()).map(|_| ())
+        );
+        assert_eq!(
+            Err("line 2: invalid number 'red'".to_string()),
+            parse_mtl("newmtl a\nKd red 0 0", Path::new("scenes"), &mut Vec::new()).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            "line 2: vertex index 2 is out of range",
+            parse_error("v 0 0 0\nf 1 2 1")
+        );
+        assert_eq!(
+            "line 1: vertex index 0 is out of range",
+            parse_error("f 0 0 0")
+        );
+        assert_eq!(
+            "line 4: a face needs at least 3 vertices",
+            parse_error("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2")
+        );
+        assert_eq!("line 1: expected 3 to 4 numbers", parse_error("v 0 0"));
+        assert_eq!("line 1: invalid number 'x'", parse_error("vn x 0 0"));
+        assert_eq!(
+            "line 2: invalid face vertex '1/1/1/1'",
+            parse_error("v 0 0 0\nf 1/1/1/1 1 1")
+        );
+        assert_eq!("the model has no faces", parse_error("v 0 0 0"));
+
+        let missing = parse_obj(
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3",
+            no_library,
+            &HashMap::new(),
+            None,
+        );
+        assert_eq!(
+            Err(
+                "line 4: face without a material, use 'usemtl' or give the mesh a material"
+                    .to_string()
+            ),
+            missing.map(|_| ())
+        );
+        let unknown = parse_obj("usemtl gold", no_library, &HashMap::new(), None);
+        assert_eq!(
+            Err("line 1: unknown material 'gold'".to_string()),
+            unknown.map(|_| ())
+        );
+    }
+}
@@ -0,0 +1,52 @@
+use crate::vec3::cross;
+use crate::Vec3;
+
+// Orthonormal basis around `w`, to turn directions sampled around the Z axis into directions
+// around a normal or towards an object.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    pub fn from_w(w: &Vec3) -> Self {
+        let w = w.unit_vector();
+        let a = if w.x().abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let v = cross(&w, &a).unit_vector();
+        let u = cross(&w, &v);
+        Onb { u, v, w }
+    }
+
+    pub fn local(&self, direction: &Vec3) -> Vec3 {
+        direction.x() * self.u + direction.y() * self.v + direction.z() * self.w
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dot;
+
+    #[test]
+    fn test_from_w() {
+        for w in [
+            Vec3::new(0.0, 0.0, 2.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(-0.3, 0.5, 0.1),
+        ] {
+            let onb = Onb::from_w(&w);
+            assert!((onb.w - w.unit_vector()).length() < 1e-12);
+            assert!(dot(&onb.u, &onb.v).abs() < 1e-12);
+            assert!(dot(&onb.u, &onb.w).abs() < 1e-12);
+            assert!((onb.u.length() - 1.0).abs() < 1e-12);
+            assert!((onb.v.length() - 1.0).abs() < 1e-12);
+            assert!((onb.local(&Vec3::new(0.0, 0.0, 1.0)) - onb.w).length() < 1e-12);
+        }
+    }
+}
@@ -0,0 +1,244 @@
+use crate::hittable::Hittable;
+use crate::onb::Onb;
+use crate::sampler::{sample_cosine_direction, sample_unit_vector, Sampler};
+use crate::{dot, Point3, Vec3};
+
+// Distribution of directions, with its density over solid angle to weight what is found along
+// them.
+pub trait Pdf {
+    fn value(&self, direction: &Vec3) -> f64;
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3;
+}
+
+// Directions around a normal, more of them close to it, as a matte surface scatters light.
+pub struct CosinePdf {
+    uvw: Onb,
+}
+
+impl CosinePdf {
+    pub fn new(normal: &Vec3) -> Self {
+        CosinePdf {
+            uvw: Onb::from_w(normal),
+        }
+    }
+}
+
+impl Pdf for CosinePdf {
+    fn value(&self, direction: &Vec3) -> f64 {
+        let cosine = dot(&direction.unit_vector(), &self.uvw.w);
+        cosine.max(0.0) / std::f64::consts::PI
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        self.uvw.local(&sample_cosine_direction(sampler.next_2d()))
+    }
+}
+
+// Directions spread evenly over the sphere, as light scatters in a medium.
+pub struct SpherePdf;
+
+impl Pdf for SpherePdf {
+    fn value(&self, _direction: &Vec3) -> f64 {
+        1.0 / (4.0 * std::f64::consts::PI)
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        sample_unit_vector(sampler.next_2d())
+    }
+}
+
+// Directions light leaves a point of a light in, out of both faces and following the cosine to the
+// normal.
+pub struct EmissionPdf {
+    uvw: Onb,
+}
+
+impl EmissionPdf {
+    pub fn new(normal: &Vec3) -> Self {
+        EmissionPdf {
+            uvw: Onb::from_w(normal),
+        }
+    }
+}
+
+impl Pdf for EmissionPdf {
+    fn value(&self, direction: &Vec3) -> f64 {
+        dot(&direction.unit_vector(), &self.uvw.w).abs() / (2.0 * std::f64::consts::PI)
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        let side = if sampler.next_1d() < 0.5 { 1.0 } else { -1.0 };
+        let direction = sample_cosine_direction(sampler.next_2d());
+        self.uvw.local(&Vec3::new(
+            direction.x(),
+            direction.y(),
+            side * direction.z(),
+        ))
+    }
+}
+
+// Directions from `origin` towards an object, usually the lights.
+pub struct HittablePdf<'a> {
+    object: &'a dyn Hittable,
+    origin: Point3,
+}
+
+impl<'a> HittablePdf<'a> {
+    pub fn new(object: &'a dyn Hittable, origin: &Point3) -> Self {
+        HittablePdf {
+            object,
+            origin: *origin,
+        }
+    }
+}
+
+impl Pdf for HittablePdf<'_> {
+    fn value(&self, direction: &Vec3) -> f64 {
+        self.object.pdf_value(&self.origin, direction)
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        self.object.random(&self.origin, sampler)
+    }
+}
+
+// Directions drawn from `first` with probability `weight` and from `second` otherwise.
+pub struct MixturePdf<'a> {
+    first: &'a dyn Pdf,
+    second: &'a dyn Pdf,
+    weight: f64,
+}
+
+impl<'a> MixturePdf<'a> {
+    pub fn new(first: &'a dyn Pdf, second: &'a dyn Pdf, weight: f64) -> Self {
+        MixturePdf {
+            first,
+            second,
+            weight,
+        }
+    }
+}
+
+impl Pdf for MixturePdf<'_> {
+    fn value(&self, direction: &Vec3) -> f64 {
+        self.weight * self.first.value(direction)
+            + (1.0 - self.weight) * self.second.value(direction)
+    }
+
+    fn generate(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        if sampler.next_1d() < self.weight {
+            self.first.generate(sampler)
+        } else {
+            self.second.generate(sampler)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::IndependentSampler;
+    use crate::sphere::Sphere;
+    use crate::Color;
+    use crate::Lambertian;
+
+    // Integral of the density over every direction, estimated with uniform directions.
+    fn integral(pdf: &dyn Pdf) -> f64 {
+        let mut sampler = IndependentSampler::new(3);
+        let count = 100_000;
+        let mut sum = 0.0;
+        for index in 0..count {
+            sampler.start_sample(0, index);
+            sum += pdf.value(&sample_unit_vector(sampler.next_2d()));
+        }
+        4.0 * std::f64::consts::PI * sum / count as f64
+    }
+
+    #[test]
+    fn test_cosine() {
+        let normal = Vec3::new(0.0, 2.0, 0.0);
+        let pdf = CosinePdf::new(&normal);
+        assert!((integral(&pdf) - 1.0).abs() < 0.02);
+        assert_eq!(0.0, pdf.value(&Vec3::new(0.0, -1.0, 0.0)));
+        assert!((pdf.value(&normal) - 1.0 / std::f64::consts::PI).abs() < 1e-12);
+
+        // Generated directions lean towards the normal: the mean cosine is 2/3.
+        let mut sampler = IndependentSampler::new(5);
+        let count = 20_000;
+        let mut sum = 0.0;
+        for index in 0..count {
+            sampler.start_sample(0, index);
+            let direction = pdf.generate(&mut sampler);
+            assert!(pdf.value(&direction) > 0.0);
+            sum += direction.unit_vector().y();
+        }
+        assert!((sum / count as f64 - 2.0 / 3.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_sphere() {
+        assert!((integral(&SpherePdf) - 1.0).abs() < 1e-9);
+
+        // Directions go every way alike, their mean is close to zero.
+        let mut sampler = IndependentSampler::new(6);
+        let count = 20_000;
+        let mut sum = Vec3::default();
+        for index in 0..count {
+            sampler.start_sample(0, index);
+            sum += SpherePdf.generate(&mut sampler);
+        }
+        assert!((sum / count as f64).length() < 0.02);
+    }
+
+    #[test]
+    fn test_emission() {
+        let pdf = EmissionPdf::new(&Vec3::new(0.0, 0.0, 3.0));
+        assert!((integral(&pdf) - 1.0).abs() < 0.02);
+
+        // Both faces get half of the directions.
+        let mut sampler = IndependentSampler::new(2);
+        let count = 10_000;
+        let mut up = 0;
+        for index in 0..count {
+            sampler.start_sample(0, index);
+            let direction = pdf.generate(&mut sampler);
+            assert!(pdf.value(&direction) > 0.0);
+            if direction.z() > 0.0 {
+                up += 1;
+            }
+        }
+        assert!((up as f64 / count as f64 - 0.5).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_mixture() {
+        let sphere = Sphere::new(
+            Point3::new(0.0, 4.0, 0.0),
+            1.0,
+            Lambertian::new(&Color::new(1.0, 1.0, 1.0)),
+        );
+        let origin = Point3::default();
+        let towards_sphere = HittablePdf::new(&sphere, &origin);
+        let cosine = CosinePdf::new(&Vec3::new(0.0, -1.0, 0.0));
+        let mixture = MixturePdf::new(&towards_sphere, &cosine, 0.25);
+        assert!((integral(&mixture) - 1.0).abs() < 0.02);
+
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        assert_eq!(sphere.pdf_value(&origin, &up), towards_sphere.value(&up));
+        assert_eq!(0.25 * towards_sphere.value(&up), mixture.value(&up));
+
+        // A quarter of the directions go towards the sphere.
+        let mut sampler = IndependentSampler::new(7);
+        let count = 20_000;
+        let mut towards = 0;
+        for index in 0..count {
+            sampler.start_sample(0, index);
+            let direction = mixture.generate(&mut sampler);
+            if towards_sphere.value(&direction) > 0.0 {
+                towards += 1;
+            }
+        }
+        assert!((towards as f64 / count as f64 - 0.25).abs() < 0.02);
+    }
+}
@@ -0,0 +1,196 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::texture::Texture;
+use crate::utils::random_f64;
+use crate::{dot, Color, Point3, Vec3};
+
+const POINT_COUNT: usize = 256;
+
+// Gradient noise on a lattice of random unit vectors, hashed from three random permutations.
+#[derive(Debug)]
+pub struct Perlin {
+    gradients: Vec<Vec3>,
+    permutations: [Vec<usize>; 3],
+}
+
+impl Perlin {
+    pub fn new(rng: &mut StdRng) -> Self {
+        Perlin {
+            gradients: (0..POINT_COUNT)
+                .map(|_| Vec3::random_range(rng, -1.0, 1.0).unit_vector())
+                .collect(),
+            permutations: [
+                Perlin::permutation(rng),
+                Perlin::permutation(rng),
+                Perlin::permutation(rng),
+            ],
+        }
+    }
+
+    fn permutation(rng: &mut StdRng) -> Vec<usize> {
+        let mut result: Vec<usize> = (0..POINT_COUNT).collect();
+        for i in (1..POINT_COUNT).rev() {
+            let target = ((random_f64(rng) * (i + 1) as f64) as usize).min(i);
+            result.swap(i, target);
+        }
+        result
+    }
+
+    // Smooth noise in about [-1, 1], zero on the lattice points.
+    pub fn noise(&self, point: &Point3) -> f64 {
+        let floor = [point.x().floor(), point.y().floor(), point.z().floor()];
+        let fraction = [
+            point.x() - floor[0],
+            point.y() - floor[1],
+            point.z() - floor[2],
+        ];
+        let cell = floor.map(|value| value as i64);
+
+        let mut result = 0.0;
+        for di in 0..2 {
+            for dj in 0..2 {
+                for dk in 0..2 {
+                    let hash = self.permutations[0][(cell[0] + di) as usize & 255]
+                        ^ self.permutations[1][(cell[1] + dj) as usize & 255]
+                        ^ self.permutations[2][(cell[2] + dk) as usize & 255];
+                    let offset = Vec3::new(
+                        fraction[0] - di as f64,
+                        fraction[1] - dj as f64,
+                        fraction[2] - dk as f64,
+                    );
+
+                    // Trilinear interpolation with Hermite smoothing of the weights.
+                    let weight = |corner: i64, t: f64| {
+                        let smooth = t * t * (3.0 - 2.0 * t);
+                        if corner == 1 {
+                            smooth
+                        } else {
+                            1.0 - smooth
+                        }
+                    };
+                    result += weight(di, fraction[0])
+                        * weight(dj, fraction[1])
+                        * weight(dk, fraction[2])
+                        * dot(&self.gradients[hash], &offset);
+                }
+            }
+        }
+
+        result
+    }
+
+    // Sum of `depth` octaves of noise, each one at twice the frequency and half the weight.
+    pub fn turbulence(&self, point: &Point3, depth: usize) -> f64 {
+        let mut result = 0.0;
+        let mut sample_point = *point;
+        let mut weight = 1.0;
+
+        for _ in 0..depth {
+            result += weight * self.noise(&sample_point);
+            weight *= 0.5;
+            sample_point = 2.0 * sample_point;
+        }
+
+        result.abs()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NoiseKind {
+    Noise,
+    Turbulence,
+    Marble,
+}
+
+impl FromStr for NoiseKind {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "noise" => Ok(NoiseKind::Noise),
+            "turbulence" => Ok(NoiseKind::Turbulence),
+            "marble" => Ok(NoiseKind::Marble),
+            _ => Err(format!(
+                "unknown noise '{name}', expected noise, turbulence or marble"
+            )),
+        }
+    }
+}
+
+// Grey scale texture made of Perlin noise, `scale` being its frequency. The lattice comes from
+// `seed`, so the pattern does not depend on the seed of the render.
+#[derive(Debug, Clone)]
+pub struct NoiseTexture {
+    perlin: Arc<Perlin>,
+    kind: NoiseKind,
+    scale: f64,
+}
+
+impl NoiseTexture {
+    pub fn new(kind: NoiseKind, scale: f64, seed: u64) -> Self {
+        NoiseTexture {
+            perlin: Arc::new(Perlin::new(&mut StdRng::seed_from_u64(seed))),
+            kind,
+            scale,
+        }
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _u: f64, _v: f64, point: &Point3) -> Color {
+        let scaled = self.scale * *point;
+        let intensity = match self.kind {
+            NoiseKind::Noise => 0.5 * (1.0 + self.perlin.noise(&scaled)),
+            NoiseKind::Turbulence => self.perlin.turbulence(&scaled, 7),
+            // Veins following the z axis, displaced by the turbulence.
+            NoiseKind::Marble => {
+                0.5 * (1.0 + (scaled.z() + 10.0 * self.perlin.turbulence(point, 7)).sin())
+            }
+        };
+
+        intensity * Color::new(1.0, 1.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_noise() {
+        let perlin = Perlin::new(&mut StdRng::seed_from_u64(7));
+
+        // Zero on the lattice points, continuous and bounded in between.
+        assert_eq!(0.0, perlin.noise(&Point3::new(3.0, -2.0, 5.0)));
+        for i in 0..100 {
+            let point = Point3::new(0.37 * i as f64, 0.11 * i as f64, -0.23 * i as f64);
+            let value = perlin.noise(&point);
+            let close = perlin.noise(&(point + Vec3::new(1e-6, 0.0, 0.0)));
+            assert!(value.abs() <= 1.5);
+            assert!((value - close).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_texture() {
+        for kind in [NoiseKind::Noise, NoiseKind::Turbulence, NoiseKind::Marble] {
+            let texture = NoiseTexture::new(kind, 4.0, 7);
+            let color = texture.value(0.0, 0.0, &Point3::new(0.3, 0.7, 0.1));
+            assert!(color.x() >= 0.0);
+            assert_eq!(color.x(), color.z());
+        }
+
+        // The pattern only depends on the seed of the texture.
+        let point = Point3::new(0.3, 0.7, 0.1);
+        let noise = |seed| NoiseTexture::new(NoiseKind::Noise, 4.0, seed).value(0.0, 0.0, &point);
+        assert_eq!(noise(7), noise(7));
+        assert_ne!(noise(7), noise(8));
+
+        assert_eq!(Ok(NoiseKind::Marble), "marble".parse());
+        assert!("wood".parse::<NoiseKind>().is_err());
+    }
+}
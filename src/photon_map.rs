@@ -0,0 +1,429 @@
+use rayon::prelude::*;
+
+use crate::hittable::{HitRecord, Hittable};
+use crate::hittable_list::HittableList;
+use crate::material::Scatter;
+use crate::pdf::{EmissionPdf, Pdf};
+use crate::sampler::{Sampler, SobolSampler};
+use crate::{dot, Color, Point3, Ray, Vec3};
+
+// Photons traced from the lights for the caustics of the `photon` integrator, and how many of them
+// are gathered around each point of a matte surface.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PhotonSettings {
+    pub count: u32,
+    pub nearest: u32,
+}
+
+impl Default for PhotonSettings {
+    fn default() -> Self {
+        PhotonSettings {
+            count: 200_000,
+            nearest: 50,
+        }
+    }
+}
+
+// Light arriving at a matte surface, `direction` being the way it travels.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Photon {
+    pub position: Point3,
+    pub direction: Vec3,
+    pub power: Color,
+}
+
+// Photons of the caustics: the light that went through glass or off mirrors, and nothing else,
+// before reaching a matte surface. They are kept in a kd-tree, each node splitting its photons at
+// the median along `axes[node]`, with the nodes of each side stored before and after it.
+pub struct PhotonMap {
+    photons: Vec<Photon>,
+    axes: Vec<i32>,
+    nearest: usize,
+}
+
+impl PhotonMap {
+    // Radiance is estimated from the `nearest` photons around a point.
+    pub fn new(mut photons: Vec<Photon>, nearest: u32) -> Self {
+        let mut axes = vec![0; photons.len()];
+        build(&mut photons, &mut axes);
+        PhotonMap {
+            photons,
+            axes,
+            nearest: nearest.max(1) as usize,
+        }
+    }
+
+    // Trace `settings.count` photons from `lights`, bouncing at most `max_depth` times. The
+    // photons are numbered like the samples of a pixel of their own, so the map only depends on
+    // `seed`.
+    pub fn caustics(
+        world: &dyn Hittable,
+        lights: &HittableList,
+        settings: &PhotonSettings,
+        max_depth: u32,
+        seed: u64,
+        time: f64,
+    ) -> Self {
+        let count = settings.count;
+        let photons = (0..count)
+            .into_par_iter()
+            .filter_map(|index| {
+                let mut sampler = SobolSampler::new(seed);
+                sampler.start_sample(u64::MAX, index);
+                trace_photon(world, lights, count, max_depth, time, &mut sampler)
+            })
+            .collect();
+        PhotonMap::new(photons, settings.nearest)
+    }
+
+    pub fn len(&self) -> usize {
+        self.photons.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.photons.is_empty()
+    }
+
+    // Up to `count` photons closest to `point`, nearest first, with their squared distances.
+    pub fn nearest(&self, point: &Point3, count: usize) -> Vec<(f64, &Photon)> {
+        let mut found = Vec::with_capacity(count + 1);
+        if count > 0 {
+            self.search(0, self.photons.len(), point, count, &mut found);
+        }
+        found
+            .into_iter()
+            .map(|(distance_squared, index)| (distance_squared, &self.photons[index]))
+            .collect()
+    }
+
+    // Light sent back along `ray` by the photons around the hit point of a matte surface of
+    // reflectance `attenuation`, their power spread over the disk reaching the farthest of them.
+    pub fn radiance(&self, ray: &Ray, hit: &HitRecord, attenuation: Color) -> Color {
+        let photons = self.nearest(&hit.point, self.nearest);
+        let Some(&(radius_squared, _)) = photons.last() else {
+            return Color::default();
+        };
+        if radius_squared <= 0.0 {
+            return Color::default();
+        }
+
+        let mut sum = Color::default();
+        for (_, photon) in photons {
+            let towards_light = -photon.direction;
+            let cosine = dot(&hit.normal, &towards_light.unit_vector());
+            if cosine <= 0.0 {
+                continue;
+            }
+            let scattered = Ray::new(hit.point, towards_light, ray.time());
+            let scattering_pdf = hit.material.scattering_pdf(ray, hit, &scattered);
+            sum += photon.power * (scattering_pdf / cosine);
+        }
+        attenuation * sum / (std::f64::consts::PI * radius_squared)
+    }
+
+    // Add the photons of the nodes in [start, end) closer than the ones found so far, keeping
+    // `found` sorted by distance.
+    fn search(
+        &self,
+        start: usize,
+        end: usize,
+        point: &Point3,
+        count: usize,
+        found: &mut Vec<(f64, usize)>,
+    ) {
+        if start >= end {
+            return;
+        }
+        let median = (start + end) / 2;
+        let photon = &self.photons[median];
+        let offset = point[self.axes[median]] - photon.position[self.axes[median]];
+        let (near, far) = if offset < 0.0 {
+            ((start, median), (median + 1, end))
+        } else {
+            ((median + 1, end), (start, median))
+        };
+
+        self.search(near.0, near.1, point, count, found);
+
+        let distance_squared = (photon.position - *point).length_squared();
+        if found.len() < count || distance_squared < found[count - 1].0 {
+            let position = found.partition_point(|&(other, _)| other <= distance_squared);
+            found.insert(position, (distance_squared, median));
+            found.truncate(count);
+        }
+
+        // Photons past the splitting plane are only looked at if they can be closer.
+        if found.len() < count || offset * offset < found[count - 1].0 {
+            self.search(far.0, far.1, point, count, found);
+        }
+    }
+}
+
+// Sort `photons` into a kd-tree, splitting along the axis they spread the most on.
+fn build(photons: &mut [Photon], axes: &mut [i32]) {
+    if photons.len() <= 1 {
+        return;
+    }
+    let mut min = photons[0].position;
+    let mut max = photons[0].position;
+    for photon in photons.iter() {
+        let position = photon.position;
+        min = Point3::new(
+            min.x().min(position.x()),
+            min.y().min(position.y()),
+            min.z().min(position.z()),
+        );
+        max = Point3::new(
+            max.x().max(position.x()),
+            max.y().max(position.y()),
+            max.z().max(position.z()),
+        );
+    }
+    let extent = max - min;
+    let axis = (0..3)
+        .max_by(|&lhs, &rhs| extent[lhs].total_cmp(&extent[rhs]))
+        .unwrap_or(0);
+
+    let median = photons.len() / 2;
+    photons.select_nth_unstable_by(median, |lhs, rhs| {
+        lhs.position[axis].total_cmp(&rhs.position[axis])
+    });
+    axes[median] = axis;
+
+    let (left, right) = photons.split_at_mut(median);
+    let (left_axes, right_axes) = axes.split_at_mut(median);
+    build(left, left_axes);
+    build(&mut right[1..], &mut right_axes[1..]);
+}
+
+// Follow a photon from the lights through glass and off mirrors, keeping it where it reaches a
+// matte surface after at least one of them. Its power is its share of the light of `count`
+// photons.
+fn trace_photon(
+    world: &dyn Hittable,
+    lights: &HittableList,
+    count: u32,
+    max_depth: u32,
+    time: f64,
+    sampler: &mut dyn Sampler,
+) -> Option<Photon> {
+    let (light, area_pdf) = lights.sample_surface(sampler)?;
+    let emission = EmissionPdf::new(&light.normal);
+    let direction = emission.generate(sampler);
+    let pdf = emission.value(&direction);
+    if area_pdf <= 0.0 || pdf <= 0.0 {
+        return None;
+    }
+    let cosine = dot(&light.normal, &direction.unit_vector()).abs();
+    let mut power = light.material.emitted(light.u, light.v, &light.point)
+        * (cosine / (area_pdf * pdf * count as f64));
+
+    let mut ray = Ray::new(light.point, direction, time);
+    let mut specular = false;
+    for _ in 0..max_depth {
+        let hit = world.hit(&ray, 0.001, f64::INFINITY)?;
+        let record = hit.material.scatter(&ray, &hit, sampler)?;
+        match record.scatter {
+            Scatter::Specular(scattered) => {
+                power = power * record.attenuation;
+                ray = scattered;
+                specular = true;
+            }
+            // Photons scattered by a medium are not stored, the paths gather no caustics there.
+            Scatter::Diffuse(_) => {
+                return (specular && !hit.is_in_medium()).then(|| Photon {
+                    position: hit.point,
+                    direction: ray.direction().unit_vector(),
+                    power,
+                });
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aarect::XZRect;
+    use crate::background::Background;
+    use crate::camera::CameraSettings;
+    use crate::integrator::IntegratorKind;
+    use crate::render::tests::{mean_luminance, test_options};
+    use crate::render::RenderOptions;
+    use crate::sampler::IndependentSampler;
+    use crate::sphere::Sphere;
+    use crate::{Dielectric, DiffuseLight, Lambertian};
+
+    fn photon(position: Point3) -> Photon {
+        Photon {
+            position,
+            direction: Vec3::new(0.0, -1.0, 0.0),
+            power: Color::new(1.0, 1.0, 1.0),
+        }
+    }
+
+    #[test]
+    fn test_nearest() {
+        let mut sampler = IndependentSampler::new(3);
+        let positions: Vec<Point3> = (0..500)
+            .map(|index| {
+                sampler.start_sample(0, index);
+                let (x, y) = sampler.next_2d();
+                Point3::new(4.0 * x, 2.0 * y, sampler.next_1d())
+            })
+            .collect();
+        let map = PhotonMap::new(positions.iter().map(|&p| photon(p)).collect(), 10);
+        assert_eq!(500, map.len());
+
+        for point in [
+            Point3::new(1.0, 1.0, 0.5),
+            Point3::new(-3.0, 0.2, 0.0),
+            Point3::new(3.9, 1.9, 2.0),
+        ] {
+            let mut expected: Vec<f64> = positions
+                .iter()
+                .map(|position| (*position - point).length_squared())
+                .collect();
+            expected.sort_by(f64::total_cmp);
+            let found: Vec<f64> = map
+                .nearest(&point, 10)
+                .iter()
+                .map(|(distance_squared, _)| *distance_squared)
+                .collect();
+            assert_eq!(expected[..10], found[..]);
+        }
+        assert!(PhotonMap::new(Vec::new(), 10)
+            .nearest(&Point3::default(), 10)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_radiance() {
+        // Photons spread evenly on a floor carry 2 per unit area, which a matte surface of
+        // reflectance 0.5 sends back as 2 * 0.5 / pi in every direction.
+        let mut photons = Vec::new();
+        for x in 0..100 {
+            for z in 0..100 {
+                let position = Point3::new(x as f64 / 10.0, 0.0, z as f64 / 10.0);
+                photons.push(Photon {
+                    power: Color::new(0.02, 0.02, 0.02),
+                    ..photon(position)
+                });
+            }
+        }
+        let map = PhotonMap::new(photons, 200);
+
+        let floor = XZRect::new(
+            0.0,
+            10.0,
+            0.0,
+            10.0,
+            0.0,
+            Lambertian::new(&Color::default()),
+        );
+        let ray = Ray::new(Point3::new(5.0, 1.0, 5.0), Vec3::new(0.01, -1.0, 0.02), 0.0);
+        let hit = floor.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        let radiance = map.radiance(&ray, &hit, Color::new(0.5, 0.5, 0.5));
+        let expected = 1.0 / std::f64::consts::PI;
+        assert!((radiance.y() / expected - 1.0).abs() < 0.05, "{radiance}");
+    }
+
+    // Glass ball above a matte floor, lit by a panel above it.
+    fn glass_ball() -> (HittableList, HittableList) {
+        let light = || {
+            XZRect::new(
+                -0.5,
+                0.5,
+                -0.5,
+                0.5,
+                3.0,
+                DiffuseLight::new(&Color::new(4.0, 4.0, 4.0)),
+            )
+        };
+        let mut world = HittableList::new();
+        world.add(Box::new(XZRect::new(
+            -10.0,
+            10.0,
+            -10.0,
+            10.0,
+            0.0,
+            Lambertian::new(&Color::new(0.5, 0.5, 0.5)),
+        )));
+        world.add(Box::new(Sphere::new(
+            Point3::new(0.0, 1.0, 0.0),
+            0.5,
+            Dielectric::new(1.5),
+        )));
+        world.add(Box::new(light()));
+        let mut lights = HittableList::new();
+        lights.add(Box::new(light()));
+        (world, lights)
+    }
+
+    #[test]
+    fn test_caustics() {
+        // Only the light through the glass is kept, none of the light reaching the floor
+        // directly.
+        let (world, lights) = glass_ball();
+        let settings = PhotonSettings {
+            count: 20_000,
+            nearest: 20,
+        };
+
+        let map = PhotonMap::caustics(&world, &lights, &settings, 5, 1, 0.0);
+        assert!(!map.is_empty() && map.len() < 5_000);
+        assert!(map
+            .photons
+            .iter()
+            .all(|photon| photon.position.y().abs() < 1e-9));
+        // The glass focuses most of the light under it.
+        let focused = map
+            .nearest(&Point3::default(), map.len())
+            .iter()
+            .filter(|(distance_squared, _)| *distance_squared < 1.0)
+            .count();
+        assert!(focused as f64 > 0.8 * map.len() as f64, "{focused}");
+    }
+
+    #[test]
+    fn test_path() {
+        let (world, lights) = glass_ball();
+        let camera = CameraSettings {
+            look_from: Point3::new(0.0, 0.4, 2.0),
+            look_at: Point3::new(0.0, 0.0, 0.0),
+            view_up: Vec3::new(0.0, 1.0, 0.0),
+            vertical_field_of_view: 30.0,
+            aperture: 0.0,
+            focus_distance: 1.0,
+            shutter_open_time: 0.0,
+            shutter_close_time: 1.0,
+        }
+        .build(1.0);
+        let background = Background::Solid(Color::default());
+        let mean = |integrator, photons| {
+            let options = RenderOptions {
+                photons,
+                ..test_options(integrator, 256, 6)
+            };
+            mean_luminance(&world, &lights, &camera, &background, &options)
+        };
+
+        // The caustics are blurred, but carry the light the paths find through the glass. Spread
+        // over the disk of the nearest photons, they come out about 4% brighter with the default
+        // photons, less with more of them.
+        let path = mean(IntegratorKind::Path, PhotonSettings::default());
+        let photon = mean(IntegratorKind::Photon, PhotonSettings::default());
+        assert!(
+            (photon / path - 1.0).abs() < 0.08,
+            "{photon} against {path}"
+        );
+
+        // They are a large part of the light of the image.
+        let without = PhotonSettings {
+            count: 0,
+            nearest: 1,
+        };
+        assert!(mean(IntegratorKind::Photon, without) < 0.7 * photon);
+    }
+}
@@ -0,0 +1,855 @@
+use std::io::{self, Write};
+
+// Minimal PNG encoder for 8-bit RGB images, compressed with a single fixed Huffman deflate block,
+// and decoder for the non-interlaced images of every color type, converted to 8-bit RGB.
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+const LENGTH_BASES: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA_BITS: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASES: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA_BITS: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+// Order in which the code lengths of the code length alphabet are stored in dynamic blocks.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+const WINDOW_SIZE: usize = 32768;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const MAX_CHAIN: usize = 64;
+const HASH_BITS: u32 = 15;
+
+pub fn write_png<W: Write>(writer: &mut W, width: u32, height: u32, rgb: &[u8]) -> io::Result<()> {
+    let row_size = 3 * width as usize;
+    if rgb.len() != row_size * height as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "pixel buffer does not match the image size",
+        ));
+    }
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // Bit depth 8, color type 2 (RGB), default compression, filtering and no interlacing.
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    writer.write_all(&SIGNATURE)?;
+    write_chunk(writer, b"IHDR", &header)?;
+    write_chunk(writer, b"IDAT", &zlib_compress(&filter_rows(rgb, row_size)))?;
+    write_chunk(writer, b"IEND", &[])
+}
+
+fn write_chunk<W: Write>(writer: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+
+    let mut crc = Crc32::new();
+    crc.update(kind);
+    crc.update(data);
+    writer.write_all(&crc.finish().to_be_bytes())
+}
+
+// Prefix each row with the filter type giving the smallest sum of absolute residuals.
+fn filter_rows(rgb: &[u8], row_size: usize) -> Vec<u8> {
+    let bytes_per_pixel = 3;
+    let mut result = Vec::with_capacity(rgb.len() + rgb.len() / row_size.max(1));
+    let zero_row = vec![0u8; row_size];
+    let mut candidates = vec![vec![0u8; row_size]; 5];
+
+    for (index, row) in rgb.chunks(row_size).enumerate() {
+        let previous = if index == 0 {
+            &zero_row[..]
+        } else {
+            &rgb[(index - 1) * row_size..index * row_size]
+        };
+
+        for i in 0..row_size {
+            let left = if i >= bytes_per_pixel {
+                row[i - bytes_per_pixel]
+            } else {
+                0
+            };
+            let up = previous[i];
+            let up_left = if i >= bytes_per_pixel {
+                previous[i - bytes_per_pixel]
+            } else {
+                0
+            };
+
+            candidates[0][i] = row[i];
+            candidates[1][i] = row[i].wrapping_sub(left);
+            candidates[2][i] = row[i].wrapping_sub(up);
+            candidates[3][i] = row[i].wrapping_sub(((left as u16 + up as u16) / 2) as u8);
+            candidates[4][i] = row[i].wrapping_sub(paeth(left, up, up_left));
+        }
+
+        let (filter, filtered) = candidates
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, candidate)| {
+                candidate
+                    .iter()
+                    .map(|&byte| (byte as i8).unsigned_abs() as u64)
+                    .sum::<u64>()
+            })
+            .unwrap();
+        result.push(filter as u8);
+        result.extend_from_slice(filtered);
+    }
+
+    result
+}
+
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = left as i16 + up as i16 - up_left as i16;
+    let distance_left = (estimate - left as i16).abs();
+    let distance_up = (estimate - up as i16).abs();
+    let distance_up_left = (estimate - up_left as i16).abs();
+
+    if distance_left <= distance_up && distance_left <= distance_up_left {
+        left
+    } else if distance_up <= distance_up_left {
+        up
+    } else {
+        up_left
+    }
+}
+
+fn zlib_compress(data: &[u8]) -> Vec<u8> {
+    // CMF: deflate with a 32K window, FLG: no dictionary, check bits making the header a multiple of 31.
+    let mut result = vec![0x78, 0x01];
+    result.extend(deflate(data));
+    result.extend_from_slice(&adler32(data).to_be_bytes());
+    result
+}
+
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut output = BitWriter::default();
+    // BFINAL = 1, BTYPE = 01 (fixed Huffman codes)
+    output.write_bits(1, 1);
+    output.write_bits(1, 2);
+
+    let mut matcher = Matcher::new(data);
+    let mut position = 0;
+    while position < data.len() {
+        let (length, distance) = matcher.longest_match(position);
+
+        if length >= MIN_MATCH {
+            write_length(&mut output, length);
+            write_distance(&mut output, distance);
+            for offset in 0..length {
+                matcher.insert(position + offset);
+            }
+            position += length;
+        } else {
+            write_literal(&mut output, data[position] as u16);
+            matcher.insert(position);
+            position += 1;
+        }
+    }
+
+    write_literal(&mut output, 256);
+    output.finish()
+}
+
+// Hash chains of the positions starting with the same three bytes, used to find LZ77 matches.
+struct Matcher<'a> {
+    data: &'a [u8],
+    head: Vec<usize>,
+    previous: Vec<usize>,
+}
+
+impl<'a> Matcher<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Matcher {
+            data,
+            head: vec![usize::MAX; 1 << HASH_BITS],
+            previous: vec![usize::MAX; WINDOW_SIZE],
+        }
+    }
+
+    fn hash(&self, position: usize) -> usize {
+        let value = (self.data[position] as u32) << 16
+            | (self.data[position + 1] as u32) << 8
+            | self.data[position + 2] as u32;
+        (value.wrapping_mul(2654435761) >> (32 - HASH_BITS)) as usize
+    }
+
+    fn insert(&mut self, position: usize) {
+        if position + MIN_MATCH <= self.data.len() {
+            let key = self.hash(position);
+            self.previous[position % WINDOW_SIZE] = self.head[key];
+            self.head[key] = position;
+        }
+    }
+
+    fn longest_match(&self, position: usize) -> (usize, usize) {
+        if position + MIN_MATCH > self.data.len() {
+            return (0, 0);
+        }
+
+        let max_length = MAX_MATCH.min(self.data.len() - position);
+        let mut best = (0, 0);
+        let mut candidate = self.head[self.hash(position)];
+        let mut chain = 0;
+
+        while candidate != usize::MAX && chain < MAX_CHAIN {
+            let distance = position - candidate;
+            if distance >= WINDOW_SIZE {
+                break;
+            }
+
+            let length = self.data[candidate..]
+                .iter()
+                .zip(&self.data[position..position + max_length])
+                .take_while(|(lhs, rhs)| lhs == rhs)
+                .count();
+            if length > best.0 {
+                best = (length, distance);
+                if length == max_length {
+                    break;
+                }
+            }
+
+            let next = self.previous[candidate % WINDOW_SIZE];
+            // Entries older than the window have been overwritten by newer positions.
+            if next == usize::MAX || next >= candidate {
+                break;
+            }
+            candidate = next;
+            chain += 1;
+        }
+
+        best
+    }
+}
+
+fn write_literal(output: &mut BitWriter, symbol: u16) {
+    let (code, length) = match symbol {
+        0..=143 => (0x30 + symbol, 8),
+        144..=255 => (0x190 + symbol - 144, 9),
+        256..=279 => (symbol - 256, 7),
+        _ => (0xc0 + symbol - 280, 8),
+    };
+    output.write_huffman(code as u32, length);
+}
+
+fn write_length(output: &mut BitWriter, length: usize) {
+    let index = LENGTH_BASES
+        .iter()
+        .rposition(|&base| base as usize <= length)
+        .unwrap();
+    write_literal(output, 257 + index as u16);
+    output.write_bits(
+        (length - LENGTH_BASES[index] as usize) as u32,
+        LENGTH_EXTRA_BITS[index] as u32,
+    );
+}
+
+fn write_distance(output: &mut BitWriter, distance: usize) {
+    let index = DISTANCE_BASES
+        .iter()
+        .rposition(|&base| base as usize <= distance)
+        .unwrap();
+    output.write_huffman(index as u32, 5);
+    output.write_bits(
+        (distance - DISTANCE_BASES[index] as usize) as u32,
+        DISTANCE_EXTRA_BITS[index] as u32,
+    );
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u64,
+    count: u32,
+}
+
+impl BitWriter {
+    // Deflate packs values starting from the least significant bit.
+    fn write_bits(&mut self, value: u32, count: u32) {
+        self.buffer |= (value as u64) << self.count;
+        self.count += count;
+        while self.count >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    // Huffman codes are stored starting from their most significant bit.
+    fn write_huffman(&mut self, code: u32, length: u32) {
+        let reversed = code.reverse_bits() >> (32 - length);
+        self.write_bits(reversed, length);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+// Decode a PNG file into its width, height and 8-bit RGB pixels, the alpha channel is dropped.
+pub fn read_png(data: &[u8]) -> Result<(u32, u32, Vec<u8>), String> {
+    if !data.starts_with(&SIGNATURE) {
+        return Err("not a PNG file".to_string());
+    }
+
+    let mut header: Option<PngHeader> = None;
+    let mut palette: &[u8] = &[];
+    let mut compressed = Vec::new();
+    let mut position = SIGNATURE.len();
+    loop {
+        if position + 12 > data.len() {
+            return Err("truncated PNG file".to_string());
+        }
+        let length = u32::from_be_bytes(data[position..position + 4].try_into().unwrap()) as usize;
+        let kind = &data[position + 4..position + 8];
+        let end = position + 8 + length;
+        if end + 4 > data.len() {
+            return Err("truncated PNG file".to_string());
+        }
+        let chunk = &data[position + 8..end];
+
+        let mut crc = Crc32::new();
+        crc.update(kind);
+        crc.update(chunk);
+        if crc.finish().to_be_bytes() != data[end..end + 4] {
+            return Err(format!("corrupted {} chunk", String::from_utf8_lossy(kind)));
+        }
+        position = end + 4;
+
+        match kind {
+            b"IHDR" => header = Some(PngHeader::parse(chunk)?),
+            b"PLTE" => palette = chunk,
+            b"IDAT" => compressed.extend_from_slice(chunk),
+            b"IEND" => break,
+            _ => {}
+        }
+    }
+
+    let header = header.ok_or_else(|| "missing IHDR chunk".to_string())?;
+    let filtered = zlib_decompress(&compressed)?;
+    let raw = unfilter_rows(&filtered, &header)?;
+    let rgb = header.to_rgb(&raw, palette)?;
+
+    Ok((header.width, header.height, rgb))
+}
+
+struct PngHeader {
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    color_type: u8,
+}
+
+impl PngHeader {
+    fn parse(chunk: &[u8]) -> Result<Self, String> {
+        if chunk.len() != 13 {
+            return Err("invalid IHDR chunk".to_string());
+        }
+        let header = PngHeader {
+            width: u32::from_be_bytes(chunk[0..4].try_into().unwrap()),
+            height: u32::from_be_bytes(chunk[4..8].try_into().unwrap()),
+            bit_depth: chunk[8],
+            color_type: chunk[9],
+        };
+
+        let valid_depths: &[u8] = match header.color_type {
+            0 => &[1, 2, 4, 8, 16],
+            3 => &[1, 2, 4, 8],
+            2 | 4 | 6 => &[8, 16],
+            _ => return Err(format!("invalid color type {}", header.color_type)),
+        };
+        if !valid_depths.contains(&header.bit_depth) {
+            return Err(format!(
+                "invalid bit depth {} for color type {}",
+                header.bit_depth, header.color_type
+            ));
+        }
+        if chunk[10] != 0 || chunk[11] != 0 {
+            return Err("unknown compression or filter method".to_string());
+        }
+        if chunk[12] != 0 {
+            return Err("interlaced images are not supported".to_string());
+        }
+
+        Ok(header)
+    }
+
+    fn channels(&self) -> usize {
+        match self.color_type {
+            2 => 3,
+            4 => 2,
+            6 => 4,
+            _ => 1,
+        }
+    }
+
+    fn bits_per_pixel(&self) -> usize {
+        self.channels() * self.bit_depth as usize
+    }
+
+    fn row_size(&self) -> usize {
+        (self.width as usize * self.bits_per_pixel()).div_ceil(8)
+    }
+
+    // Read every sample of the unfiltered rows, scaled to 8 bits, and expand them to RGB.
+    fn to_rgb(&self, raw: &[u8], palette: &[u8]) -> Result<Vec<u8>, String> {
+        let width = self.width as usize;
+        let channels = self.channels();
+        let depth = self.bit_depth as usize;
+        let max_value = ((1u32 << depth) - 1) as usize;
+        let mut rgb = Vec::with_capacity(3 * width * self.height as usize);
+
+        for row in raw.chunks(self.row_size()) {
+            for x in 0..width {
+                let sample = |channel: usize| {
+                    let bit = (x * channels + channel) * depth;
+                    match depth {
+                        // The most significant byte of 16-bit samples is first.
+                        8 | 16 => row[bit / 8] as usize,
+                        _ => (row[bit / 8] as usize >> (8 - depth - bit % 8)) & max_value,
+                    }
+                };
+
+                match self.color_type {
+                    0 | 4 => {
+                        let gray = if depth < 8 {
+                            (sample(0) * 255 / max_value) as u8
+                        } else {
+                            sample(0) as u8
+                        };
+                        rgb.extend_from_slice(&[gray, gray, gray]);
+                    }
+                    3 => {
+                        let index = 3 * sample(0);
+                        let color = palette
+                            .get(index..index + 3)
+                            .ok_or_else(|| "palette index out of range".to_string())?;
+                        rgb.extend_from_slice(color);
+                    }
+                    _ => {
+                        rgb.extend_from_slice(&[sample(0) as u8, sample(1) as u8, sample(2) as u8])
+                    }
+                }
+            }
+        }
+
+        Ok(rgb)
+    }
+}
+
+fn unfilter_rows(filtered: &[u8], header: &PngHeader) -> Result<Vec<u8>, String> {
+    let row_size = header.row_size();
+    let height = header.height as usize;
+    if filtered.len() < (row_size + 1) * height {
+        return Err("not enough image data".to_string());
+    }
+    // Filters work on bytes, with at least one byte per pixel.
+    let bytes_per_pixel = header.bits_per_pixel().div_ceil(8);
+
+    let mut raw = vec![0u8; row_size * height];
+    for y in 0..height {
+        let filter = filtered[y * (row_size + 1)];
+        let source = &filtered[y * (row_size + 1) + 1..(y + 1) * (row_size + 1)];
+        let (previous_rows, rows) = raw.split_at_mut(y * row_size);
+        let previous = previous_rows
+            .get((y.max(1) - 1) * row_size..)
+            .filter(|_| y > 0);
+        let row = &mut rows[..row_size];
+
+        for i in 0..row_size {
+            let left = if i >= bytes_per_pixel {
+                row[i - bytes_per_pixel]
+            } else {
+                0
+            };
+            let up = previous.map_or(0, |previous| previous[i]);
+            let up_left = match previous {
+                Some(previous) if i >= bytes_per_pixel => previous[i - bytes_per_pixel],
+                _ => 0,
+            };
+
+            let predicted = match filter {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                4 => paeth(left, up, up_left),
+                _ => return Err(format!("invalid filter type {filter}")),
+            };
+            row[i] = source[i].wrapping_add(predicted);
+        }
+    }
+
+    Ok(raw)
+}
+
+fn zlib_decompress(data: &[u8]) -> Result<Vec<u8>, String> {
+    if data.len() < 6 {
+        return Err("truncated zlib stream".to_string());
+    }
+    if data[0] & 0x0f != 8 || !(data[0] as u16 * 256 + data[1] as u16).is_multiple_of(31) {
+        return Err("invalid zlib header".to_string());
+    }
+    if data[1] & 0x20 != 0 {
+        return Err("zlib preset dictionaries are not supported".to_string());
+    }
+
+    let (result, consumed) = inflate(&data[2..])?;
+    let checksum = data
+        .get(2 + consumed..6 + consumed)
+        .ok_or_else(|| "missing zlib checksum".to_string())?;
+    if adler32(&result).to_be_bytes() != checksum {
+        return Err("zlib checksum mismatch".to_string());
+    }
+
+    Ok(result)
+}
+
+// Decompress a deflate stream, returning the data and the number of bytes read.
+fn inflate(data: &[u8]) -> Result<(Vec<u8>, usize), String> {
+    let mut input = BitReader::new(data);
+    let mut output = Vec::new();
+
+    loop {
+        let last = input.read_bits(1)? == 1;
+        match input.read_bits(2)? {
+            0 => {
+                input.align();
+                let length = input.read_bits(16)?;
+                let complement = input.read_bits(16)?;
+                if length != !complement & 0xffff {
+                    return Err("invalid stored block length".to_string());
+                }
+                for _ in 0..length {
+                    output.push(input.read_bits(8)? as u8);
+                }
+            }
+            1 => {
+                let (literals, distances) = fixed_codes();
+                inflate_block(&mut input, &mut output, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_codes(&mut input)?;
+                inflate_block(&mut input, &mut output, &literals, &distances)?;
+            }
+            _ => return Err("invalid deflate block type".to_string()),
+        }
+
+        if last {
+            break;
+        }
+    }
+
+    input.align();
+    Ok((output, input.position / 8))
+}
+
+fn inflate_block(
+    input: &mut BitReader,
+    output: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<(), String> {
+    loop {
+        let symbol = literals.decode(input)? as usize;
+        match symbol {
+            0..=255 => output.push(symbol as u8),
+            256 => return Ok(()),
+            257..=285 => {
+                let index = symbol - 257;
+                let length = LENGTH_BASES[index] as usize
+                    + input.read_bits(LENGTH_EXTRA_BITS[index] as u32)? as usize;
+
+                let index = distances.decode(input)? as usize;
+                if index >= DISTANCE_BASES.len() {
+                    return Err("invalid distance code".to_string());
+                }
+                let distance = DISTANCE_BASES[index] as usize
+                    + input.read_bits(DISTANCE_EXTRA_BITS[index] as u32)? as usize;
+                if distance > output.len() {
+                    return Err("distance before the start of the data".to_string());
+                }
+
+                // Copied byte by byte, a match may overlap the bytes it produces.
+                let start = output.len() - distance;
+                for offset in 0..length {
+                    output.push(output[start + offset]);
+                }
+            }
+            _ => return Err("invalid literal/length code".to_string()),
+        }
+    }
+}
+
+fn fixed_codes() -> (Huffman, Huffman) {
+    let mut lengths = [0u8; 288];
+    lengths[0..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..288].fill(8);
+
+    (Huffman::new(&lengths), Huffman::new(&[5; 30]))
+}
+
+fn dynamic_codes(input: &mut BitReader) -> Result<(Huffman, Huffman), String> {
+    let literal_count = input.read_bits(5)? as usize + 257;
+    let distance_count = input.read_bits(5)? as usize + 1;
+    let code_length_count = input.read_bits(4)? as usize + 4;
+
+    let mut code_length_lengths = [0u8; 19];
+    for &symbol in CODE_LENGTH_ORDER.iter().take(code_length_count) {
+        code_length_lengths[symbol] = input.read_bits(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_length_lengths);
+
+    // The literal and distance code lengths form a single sequence, repeats may cross over.
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let symbol = code_lengths.decode(input)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *lengths
+                    .last()
+                    .ok_or_else(|| "repeated code length without a previous one".to_string())?;
+                (previous, 3 + input.read_bits(2)?)
+            }
+            17 => (0, 3 + input.read_bits(3)?),
+            _ => (0, 11 + input.read_bits(7)?),
+        };
+        for _ in 0..repeat {
+            lengths.push(value);
+        }
+    }
+    if lengths.len() > literal_count + distance_count {
+        return Err("too many code lengths".to_string());
+    }
+    if lengths[256] == 0 {
+        return Err("missing end of block code".to_string());
+    }
+
+    Ok((
+        Huffman::new(&lengths[..literal_count]),
+        Huffman::new(&lengths[literal_count..]),
+    ))
+}
+
+// Canonical Huffman code, decoded one bit at a time from the number of codes of each length.
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0u16; 16];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+
+        let mut symbols: Vec<u16> = (0..lengths.len() as u16)
+            .filter(|&symbol| lengths[symbol as usize] != 0)
+            .collect();
+        symbols.sort_by_key(|&symbol| lengths[symbol as usize]);
+
+        Huffman { counts, symbols }
+    }
+
+    fn decode(&self, input: &mut BitReader) -> Result<u16, String> {
+        // `code` is the code read so far, `first` the first code of the current length and
+        // `index` the index of that first code in `symbols`.
+        let mut code = 0i32;
+        let mut first = 0i32;
+        let mut index = 0i32;
+        for length in 1..16 {
+            code |= input.read_bits(1)? as i32;
+            let count = self.counts[length] as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        Err("invalid Huffman code".to_string())
+    }
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    // Position in bits.
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        BitReader { data, position: 0 }
+    }
+
+    fn read_bits(&mut self, count: u32) -> Result<u32, String> {
+        let mut value = 0;
+        for bit in 0..count {
+            let byte = self
+                .data
+                .get(self.position / 8)
+                .ok_or_else(|| "truncated deflate stream".to_string())?;
+            value |= ((*byte as u32 >> (self.position % 8)) & 1) << bit;
+            self.position += 1;
+        }
+        Ok(value)
+    }
+
+    fn align(&mut self) {
+        self.position = self.position.div_ceil(8) * 8;
+    }
+}
+
+pub struct Crc32 {
+    value: u32,
+}
+
+impl Crc32 {
+    pub fn new() -> Self {
+        Crc32 { value: 0xffff_ffff }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.value ^= byte as u32;
+            for _ in 0..8 {
+                let mask = (self.value & 1).wrapping_neg();
+                self.value = (self.value >> 1) ^ (0xedb8_8320 & mask);
+            }
+        }
+    }
+
+    pub fn finish(&self) -> u32 {
+        !self.value
+    }
+}
+
+pub fn adler32(data: &[u8]) -> u32 {
+    let mut a: u32 = 1;
+    let mut b: u32 = 0;
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc32() {
+        let mut crc = Crc32::new();
+        crc.update(b"123456789");
+        assert_eq!(0xcbf4_3926, crc.finish());
+    }
+
+    #[test]
+    fn test_adler32() {
+        assert_eq!(0x11e6_0398, adler32(b"Wikipedia"));
+        assert_eq!(1, adler32(&[]));
+    }
+
+    #[test]
+    fn test_header() {
+        let mut output = Vec::new();
+        write_png(&mut output, 2, 1, &[255, 0, 0, 0, 255, 0]).unwrap();
+
+        assert_eq!(SIGNATURE, output[0..8]);
+        assert_eq!(b"IHDR", &output[12..16]);
+        assert_eq!([0, 0, 0, 2, 0, 0, 0, 1, 8, 2, 0, 0, 0], output[16..29]);
+        assert_eq!(b"IEND", &output[output.len() - 8..output.len() - 4]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let (width, height) = (37, 11);
+        let rgb: Vec<u8> = (0..3 * width * height)
+            .map(|i| ((i * 7) % 256 / 16 * 16) as u8)
+            .collect();
+        let mut output = Vec::new();
+        write_png(&mut output, width as u32, height as u32, &rgb).unwrap();
+
+        assert_eq!(Ok((width as u32, height as u32, rgb)), read_png(&output));
+    }
+
+    #[test]
+    fn test_inflate() {
+        // Streams made by zlib: fixed codes with an overlapping match, dynamic codes and a stored
+        // block.
+        let fixed = [
+            0x78, 0xda, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x90, 0x00, 0x3a, 0x2e,
+            0x06, 0x7d,
+        ];
+        let dynamic = [
+            0x78, 0x01, 0x05, 0xc1, 0x01, 0x01, 0x00, 0x30, 0x0c, 0xc3, 0x20, 0xad, 0xa4, 0xbb,
+            0x7f, 0x0b, 0x07, 0x00, 0x80, 0xaa, 0xaa, 0x6d, 0xbb, 0x7b, 0x1f, 0xbc, 0xaa, 0x0b,
+            0xda,
+        ];
+        let stored = [
+            0x78, 0x01, 0x01, 0x05, 0x00, 0xfa, 0xff, b'h', b'e', b'l', b'l', b'o', 0x06, 0x2c,
+            0x02, 0x15,
+        ];
+
+        assert_eq!(Ok(b"hello hello hello".to_vec()), zlib_decompress(&fixed));
+        assert_eq!(
+            Ok(b"aaaaaaaaaaaaaaaabbbbbbbbccccdde".to_vec()),
+            zlib_decompress(&dynamic)
+        );
+        assert_eq!(Ok(b"hello".to_vec()), zlib_decompress(&stored));
+        assert!(zlib_decompress(&dynamic[..10]).is_err());
+    }
+
+    #[test]
+    fn test_read_palette() {
+        // 2x1 image with 1-bit palette indices, built by hand.
+        let mut data = SIGNATURE.to_vec();
+        write_chunk(&mut data, b"IHDR", &[0, 0, 0, 2, 0, 0, 0, 1, 1, 3, 0, 0, 0]).unwrap();
+        write_chunk(&mut data, b"PLTE", &[10, 20, 30, 200, 100, 0]).unwrap();
+        write_chunk(&mut data, b"IDAT", &zlib_compress(&[0, 0b0100_0000])).unwrap();
+        write_chunk(&mut data, b"IEND", &[]).unwrap();
+
+        assert_eq!(Ok((2, 1, vec![10, 20, 30, 200, 100, 0])), read_png(&data));
+
+        let corrupted_at = data.len() - 20;
+        data[corrupted_at] ^= 1;
+        assert!(read_png(&data).is_err());
+    }
+
+    #[test]
+    fn test_invalid_size() {
+        let mut output = Vec::new();
+        assert!(write_png(&mut output, 2, 2, &[0; 6]).is_err());
+    }
+}
@@ -2,7 +2,7 @@ use crate::vec3::Vec3;
 
 use Vec3 as Point3;
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone)]
 pub struct Ray {
     origin: Point3,
     direction: Vec3,
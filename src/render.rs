@@ -0,0 +1,972 @@
+use std::sync::atomic::{AtomicI64, AtomicUsize, Ordering};
+
+use rayon::prelude::*;
+
+use crate::background::Background;
+use crate::camera::Camera;
+use crate::filter::{Filter, PixelFilter};
+use crate::framebuffer::FrameBuffer;
+use crate::hittable::Hittable;
+use crate::hittable_list::HittableList;
+use crate::image::Image;
+use crate::integrator::IntegratorKind;
+use crate::photon_map::{PhotonMap, PhotonSettings};
+use crate::sampler::SamplerKind;
+use crate::utils::clamp;
+use crate::Color;
+
+// Everything deciding the pixels of a render besides the scene and the camera. Start from
+// `RenderOptions::default()`, the defaults of the command line, and change the fields needed.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct RenderOptions {
+    pub image_width: u32,
+    pub image_height: u32,
+    pub samples_per_pixel: u32,
+    pub depth: PathDepth,
+    pub seed: u64,
+    pub integrator: IntegratorKind,
+    // Photons traced for the caustics when `integrator` is `photon`.
+    pub photons: PhotonSettings,
+    pub sampler: SamplerKind,
+    pub filter: PixelFilter,
+    // Stop sampling the pixels that converged before `samples_per_pixel`.
+    pub adaptive: Option<AdaptiveSampling>,
+    // Print the number of tiles left to stderr while rendering.
+    pub show_progress: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            image_width: 400,
+            image_height: 225,
+            samples_per_pixel: 100,
+            depth: PathDepth::new(50),
+            seed: 0,
+            integrator: IntegratorKind::default(),
+            photons: PhotonSettings::default(),
+            sampler: SamplerKind::default(),
+            filter: PixelFilter::default(),
+            adaptive: None,
+            show_progress: false,
+        }
+    }
+}
+
+// Bounces a path may take, in total and of each kind: `diffuse` off matte surfaces, `specular` off
+// mirrors and glass, and `transmission` through glass. Russian roulette randomly ends the paths
+// carrying little light once they made `roulette` bounces.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PathDepth {
+    pub max: u32,
+    pub diffuse: u32,
+    pub specular: u32,
+    pub transmission: u32,
+    pub roulette: u32,
+}
+
+impl PathDepth {
+    // Only `max` limits the bounces, roulette starting after 3.
+    pub fn new(max: u32) -> Self {
+        PathDepth {
+            max,
+            diffuse: max,
+            specular: max,
+            transmission: max,
+            roulette: 3,
+        }
+    }
+}
+
+// Luminance below which pixels are compared to this floor instead, so black pixels converge too.
+const MIN_LUMINANCE: f64 = 0.01;
+
+// Stop sampling a pixel once its luminance is known within `threshold`, relative to its value,
+// with a 95% confidence.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AdaptiveSampling {
+    pub min_samples: u32,
+    pub threshold: f64,
+}
+
+impl AdaptiveSampling {
+    pub fn is_converged(&self, pixel: &PixelSamples) -> bool {
+        if pixel.count < self.min_samples.max(2) {
+            return false;
+        }
+
+        let count = pixel.count as f64;
+        let sum = pixel.sum.luminance();
+        let mean = sum / count;
+        let variance = ((pixel.sum_squares - sum * mean) / (count - 1.0)).max(0.0);
+        1.96 * (variance / count).sqrt() <= self.threshold * mean.max(MIN_LUMINANCE)
+    }
+}
+
+// Side of the square tiles the image is split in, small enough to spread the work over the threads
+// and to lose little when a render is interrupted.
+pub const TILE_SIZE: u32 = 32;
+
+// Running sums of the samples of a pixel, enough for its mean and the variance of its luminance.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct PixelSamples {
+    pub sum: Color,
+    pub sum_squares: f64,
+    pub count: u32,
+}
+
+impl PixelSamples {
+    pub fn add(&mut self, color: Color) {
+        self.sum += color;
+        self.sum_squares += color.luminance().powi(2);
+        self.count += 1;
+    }
+
+    // Black before the first sample.
+    pub fn mean(&self) -> Color {
+        self.sum / self.count.max(1) as f64
+    }
+}
+
+// Samples of the tiles splatted on a pixel, weighted by the reconstruction filter.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct FilmPixel {
+    pub weighted_sum: Color,
+    pub weight_sum: f64,
+}
+
+// Light carried to the camera by the paths started from the lights, which can reach any pixel.
+// The sums are kept in fixed point so they do not depend on the order the threads add to them in.
+pub struct Splats {
+    width: u32,
+    height: u32,
+    sums: Vec<[AtomicI64; 3]>,
+}
+
+// Fixed point scale of the splat sums, 2^32.
+const SPLAT_SCALE: f64 = 4294967296.0;
+
+impl Splats {
+    pub fn new(width: u32, height: u32) -> Self {
+        let sums = (0..width as usize * height as usize)
+            .map(|_| Default::default())
+            .collect();
+        Splats {
+            width,
+            height,
+            sums,
+        }
+    }
+
+    // Sums saved by `sums`, or an empty list when nothing was splatted.
+    pub fn with_sums(width: u32, height: u32, sums: Vec<[i64; 3]>) -> Result<Self, String> {
+        let splats = Splats::new(width, height);
+        if sums.is_empty() {
+            return Ok(splats);
+        }
+        if sums.len() != splats.sums.len() {
+            return Err(format!(
+                "{} splatted pixels for a {width}x{height} image",
+                sums.len()
+            ));
+        }
+        for (result, sum) in splats.sums.iter().zip(sums) {
+            for (channel, value) in result.iter().zip(sum) {
+                channel.store(value, Ordering::Relaxed);
+            }
+        }
+        Ok(splats)
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    // Add light to the pixel at `column` and `row`, counting rows from the top. Infinite and NaN
+    // values are dropped. The sums saturate at about 2^31 rather than wrapping around.
+    pub fn add(&self, column: u32, row: u32, color: Color) {
+        let sums = &self.sums[(row * self.width + column) as usize];
+        for (channel, value) in sums.iter().zip([color.x(), color.y(), color.z()]) {
+            if value.is_finite() {
+                // Values past the range of i64 saturate when converted.
+                let value = (value * SPLAT_SCALE).round() as i64;
+                let _ = channel.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |sum| {
+                    Some(sum.saturating_add(value))
+                });
+            }
+        }
+    }
+
+    // Sum of the light added to a pixel, rows from the top.
+    pub fn color(&self, index: usize) -> Color {
+        let [r, g, b] = self.sums[index]
+            .each_ref()
+            .map(|channel| channel.load(Ordering::Relaxed) as f64 / SPLAT_SCALE);
+        Color::new(r, g, b)
+    }
+
+    // Fixed point sums to save, empty when nothing was splatted as with every integrator but
+    // `bdpt`.
+    pub fn sums(&self) -> Vec<[i64; 3]> {
+        let sums: Vec<[i64; 3]> = self
+            .sums
+            .iter()
+            .map(|sum| {
+                sum.each_ref()
+                    .map(|channel| channel.load(Ordering::Relaxed))
+            })
+            .collect();
+        if sums.iter().all(|sum| *sum == [0; 3]) {
+            return Vec::new();
+        }
+        sums
+    }
+}
+
+// Rectangle of pixels rendered together, `y` counting from the top row of the image. `samples`
+// is the number of samples the pixels were asked for, converged pixels may have less. The film
+// extends `margin` pixels around the tile since the filter spreads samples over the neighbours.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tile {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub margin: u32,
+    pub samples: u32,
+    pub pixels: Vec<PixelSamples>,
+    pub film: Vec<FilmPixel>,
+}
+
+impl Tile {
+    pub fn new(x: u32, y: u32, width: u32, height: u32, margin: u32) -> Self {
+        let film_size = (width + 2 * margin) as usize * (height + 2 * margin) as usize;
+        Tile {
+            x,
+            y,
+            width,
+            height,
+            margin,
+            samples: 0,
+            pixels: vec![PixelSamples::default(); width as usize * height as usize],
+            film: vec![FilmPixel::default(); film_size],
+        }
+    }
+
+    pub fn film_width(&self) -> u32 {
+        self.width + 2 * self.margin
+    }
+}
+
+// Tiles covering an image, row by row from the top left corner.
+pub fn split_tiles(image_width: u32, image_height: u32, margin: u32) -> Vec<Tile> {
+    let mut tiles = Vec::new();
+    for y in (0..image_height).step_by(TILE_SIZE as usize) {
+        for x in (0..image_width).step_by(TILE_SIZE as usize) {
+            tiles.push(Tile::new(
+                x,
+                y,
+                TILE_SIZE.min(image_width - x),
+                TILE_SIZE.min(image_height - y),
+                margin,
+            ));
+        }
+    }
+    tiles
+}
+
+// Image refined by passes of samples, or tile by tile, the sums of the samples being kept so the
+// current estimate can be looked at and saved at any time. Samples are numbered per pixel, so the
+// image only depends on the number of samples and not on how the work was split.
+pub struct ProgressiveRender<'a> {
+    world: &'a dyn Hittable,
+    lights: &'a HittableList,
+    camera: &'a Camera,
+    background: &'a Background,
+    options: RenderOptions,
+    tiles: Vec<Tile>,
+    splats: Splats,
+    photons: Option<PhotonMap>,
+}
+
+impl<'a> ProgressiveRender<'a> {
+    pub fn new(
+        world: &'a dyn Hittable,
+        lights: &'a HittableList,
+        camera: &'a Camera,
+        background: &'a Background,
+        options: &RenderOptions,
+    ) -> Self {
+        ProgressiveRender {
+            world,
+            lights,
+            camera,
+            background,
+            options: *options,
+            tiles: split_tiles(
+                options.image_width,
+                options.image_height,
+                options.filter.margin(),
+            ),
+            splats: Splats::new(options.image_width, options.image_height),
+            photons: photon_map(world, lights, camera, options),
+        }
+    }
+
+    // Continue from the tiles and splats saved by an earlier render of the same image.
+    pub fn with_tiles(
+        world: &'a dyn Hittable,
+        lights: &'a HittableList,
+        camera: &'a Camera,
+        background: &'a Background,
+        options: &RenderOptions,
+        tiles: Vec<Tile>,
+        splats: Vec<[i64; 3]>,
+    ) -> Result<Self, String> {
+        let margin = options.filter.margin();
+        let expected = split_tiles(options.image_width, options.image_height, margin);
+        let matches = expected.len() == tiles.len()
+            && expected.iter().zip(tiles.iter()).all(|(lhs, rhs)| {
+                (lhs.x, lhs.y, lhs.width, lhs.height) == (rhs.x, rhs.y, rhs.width, rhs.height)
+                    && lhs.pixels.len() == rhs.pixels.len()
+            });
+        if !matches {
+            return Err(format!(
+                "the tiles do not cover a {}x{} image",
+                options.image_width, options.image_height
+            ));
+        }
+        if expected
+            .iter()
+            .zip(tiles.iter())
+            .any(|(lhs, rhs)| lhs.margin != rhs.margin || lhs.film.len() != rhs.film.len())
+        {
+            return Err(format!(
+                "the tiles were not rendered with a {} filter of radius {}",
+                options.filter.kind.name(),
+                options.filter.radius
+            ));
+        }
+
+        let splats = Splats::with_sums(options.image_width, options.image_height, splats)?;
+
+        Ok(ProgressiveRender {
+            world,
+            lights,
+            camera,
+            background,
+            options: *options,
+            tiles,
+            splats,
+            photons: photon_map(world, lights, camera, options),
+        })
+    }
+
+    pub fn tiles(&self) -> &[Tile] {
+        &self.tiles
+    }
+
+    pub fn splats(&self) -> &Splats {
+        &self.splats
+    }
+
+    // Samples per pixel rendered in every tile so far.
+    pub fn samples(&self) -> u32 {
+        self.tiles
+            .iter()
+            .map(|tile| tile.samples)
+            .min()
+            .unwrap_or(0)
+    }
+
+    pub fn unfinished_tiles(&self) -> usize {
+        let target = self.options.samples_per_pixel;
+        self.tiles
+            .iter()
+            .filter(|tile| tile.samples < target)
+            .count()
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.unfinished_tiles() == 0
+    }
+
+    // Bring every pixel up to `samples` more samples than the least sampled tile, without going
+    // over `samples_per_pixel`.
+    pub fn add_samples(&mut self, samples: u32) {
+        let target = (self.samples() + samples).min(self.options.samples_per_pixel);
+        let (renderer, tiles) = self.renderer();
+        tiles
+            .par_iter_mut()
+            .for_each(|tile| renderer.render_tile(tile, target));
+    }
+
+    // Render up to `count` of the tiles not done yet with all their samples.
+    pub fn finish_tiles(&mut self, count: usize) {
+        let target = self.options.samples_per_pixel;
+        let remaining = AtomicUsize::new(self.unfinished_tiles());
+        let (renderer, tiles) = self.renderer();
+        tiles
+            .iter_mut()
+            .filter(|tile| tile.samples < target)
+            .take(count)
+            .par_bridge()
+            .for_each(|tile| {
+                renderer.render_tile(tile, target);
+                let remaining = remaining.fetch_sub(1, Ordering::Relaxed) - 1;
+                if renderer.options.show_progress {
+                    eprintln!("Tiles remaining: {remaining}");
+                }
+            });
+    }
+
+    // Filtered average of the samples so far, black where nothing was rendered yet, plus the
+    // splats averaged over every sample of the image. Negative lobes of the filter are clamped to
+    // zero.
+    pub fn framebuffer(&self) -> FrameBuffer {
+        let image_width = self.options.image_width as i64;
+        let image_height = self.options.image_height as i64;
+        let mut film = vec![FilmPixel::default(); (image_width * image_height) as usize];
+        for tile in self.tiles.iter() {
+            let margin = tile.margin as i64;
+            for (row, pixels) in tile.film.chunks(tile.film_width() as usize).enumerate() {
+                let y = tile.y as i64 + row as i64 - margin;
+                if y < 0 || y >= image_height {
+                    continue;
+                }
+                for (column, pixel) in pixels.iter().enumerate() {
+                    let x = tile.x as i64 + column as i64 - margin;
+                    if x < 0 || x >= image_width {
+                        continue;
+                    }
+                    let result = &mut film[(y * image_width + x) as usize];
+                    result.weighted_sum += pixel.weighted_sum;
+                    result.weight_sum += pixel.weight_sum;
+                }
+            }
+        }
+
+        // Each sample started one path from the lights, that may have reached any pixel.
+        let samples: u64 = self
+            .tiles
+            .iter()
+            .flat_map(|tile| tile.pixels.iter())
+            .map(|pixel| pixel.count as u64)
+            .sum();
+        let splat_scale = film.len() as f64 / samples.max(1) as f64;
+
+        let pixels = film
+            .iter()
+            .enumerate()
+            .map(|(index, pixel)| {
+                let mut color = splat_scale * self.splats.color(index);
+                if pixel.weight_sum > 0.0 {
+                    color += pixel.weighted_sum / pixel.weight_sum;
+                }
+                Color::new(color.x().max(0.0), color.y().max(0.0), color.z().max(0.0))
+            })
+            .collect();
+        FrameBuffer::new(self.options.image_width, self.options.image_height, pixels)
+    }
+
+    // Samples used by each pixel, rows from top to bottom.
+    pub fn sample_counts(&self) -> Vec<u32> {
+        self.pixel_values(|pixel| pixel.count)
+    }
+
+    // Samples used by each pixel, from black for none to white for `samples_per_pixel`.
+    pub fn heatmap(&self) -> Image {
+        let max_samples = self.options.samples_per_pixel as f64;
+        let pixels = self
+            .sample_counts()
+            .into_iter()
+            .flat_map(|count| {
+                // Black to red, yellow then white.
+                let t = 3.0 * count as f64 / max_samples;
+                [t, t - 1.0, t - 2.0].map(|value| (255.0 * clamp(value, 0.0, 1.0)) as u8)
+            })
+            .collect();
+        Image::new(self.options.image_width, self.options.image_height, pixels)
+    }
+
+    fn pixel_values<T: Copy + Default>(&self, value: impl Fn(&PixelSamples) -> T) -> Vec<T> {
+        let image_width = self.options.image_width as usize;
+        let mut values = vec![T::default(); image_width * self.options.image_height as usize];
+        for tile in self.tiles.iter() {
+            for (row, pixels) in tile.pixels.chunks(tile.width as usize).enumerate() {
+                let start = (tile.y as usize + row) * image_width + tile.x as usize;
+                for (result, pixel) in values[start..start + pixels.len()].iter_mut().zip(pixels) {
+                    *result = value(pixel);
+                }
+            }
+        }
+        values
+    }
+
+    // What the threads share, and apart from it the tiles they render.
+    fn renderer(&mut self) -> (TileRenderer<'_>, &mut Vec<Tile>) {
+        let renderer = TileRenderer {
+            world: self.world,
+            lights: self.lights,
+            camera: self.camera,
+            background: self.background,
+            splats: &self.splats,
+            photons: self.photons.as_ref(),
+            options: self.options,
+        };
+        (renderer, &mut self.tiles)
+    }
+}
+
+// What the threads share to render tiles, apart from the tiles themselves.
+#[derive(Clone, Copy)]
+struct TileRenderer<'a> {
+    world: &'a dyn Hittable,
+    lights: &'a HittableList,
+    camera: &'a Camera,
+    background: &'a Background,
+    splats: &'a Splats,
+    photons: Option<&'a PhotonMap>,
+    options: RenderOptions,
+}
+
+impl TileRenderer<'_> {
+    // Add samples to every pixel of the tile until it has `target` of them or converged. The
+    // samples are taken one per pixel at a time rather than pixel after pixel, so they add up on
+    // the film in the same order however the render is split in passes.
+    fn render_tile(&self, tile: &mut Tile, target: u32) {
+        let image_width = self.options.image_width;
+        let image_height = self.options.image_height;
+        let filter = self.options.filter.filter();
+        let integrator = self.options.integrator.integrator(
+            &self.options.depth,
+            self.world,
+            self.camera,
+            self.splats,
+            self.photons,
+        );
+        let mut sampler = self
+            .options
+            .sampler
+            .sampler(self.options.seed, self.options.samples_per_pixel);
+
+        let first = tile
+            .pixels
+            .iter()
+            .map(|pixel| pixel.count)
+            .min()
+            .unwrap_or(0);
+        for sample in first..target {
+            for row in 0..tile.height {
+                // The pixel numbers used for the random numbers count rows from the bottom.
+                let index_height = image_height - 1 - (tile.y + row);
+                for column in 0..tile.width {
+                    let samples = &mut tile.pixels[(row * tile.width + column) as usize];
+                    if samples.count != sample
+                        || self
+                            .options
+                            .adaptive
+                            .is_some_and(|adaptive| adaptive.is_converged(samples))
+                    {
+                        continue;
+                    }
+
+                    let index_width = tile.x + column;
+                    let pixel = (index_height * image_width + index_width) as u64;
+                    sampler.start_sample(pixel, sample);
+                    let (x, y) = sampler.next_2d();
+                    let u = (index_width as f64 + x) / ((image_width - 1) as f64);
+                    let v = (index_height as f64 + y) / ((image_height - 1) as f64);
+                    let ray = self.camera.get_ray(u, v, sampler.as_mut());
+                    let color = integrator.radiance(
+                        &ray,
+                        self.world,
+                        self.lights,
+                        self.background,
+                        sampler.as_mut(),
+                    );
+                    samples.add(color);
+                    splat(
+                        tile,
+                        filter.as_ref(),
+                        column,
+                        row,
+                        (x - 0.5, y - 0.5),
+                        color,
+                    );
+                }
+            }
+        }
+
+        tile.samples = tile.samples.max(target);
+    }
+}
+
+// Add a sample taken at `offset` from the center of a pixel of the tile, `y` going up, to the film
+// pixels around it.
+fn splat(
+    tile: &mut Tile,
+    filter: &dyn Filter,
+    column: u32,
+    row: u32,
+    offset: (f64, f64),
+    color: Color,
+) {
+    let margin = tile.margin as i64;
+    let film_width = tile.film_width() as i64;
+    for down in -margin..=margin {
+        for right in -margin..=margin {
+            let weight = filter.evaluate(offset.0 - right as f64, offset.1 + down as f64);
+            if weight == 0.0 {
+                continue;
+            }
+            let film_row = row as i64 + margin + down;
+            let film_column = column as i64 + margin + right;
+            let pixel = &mut tile.film[(film_row * film_width + film_column) as usize];
+            pixel.weighted_sum += weight * color;
+            pixel.weight_sum += weight;
+        }
+    }
+}
+
+// Photons of the caustics when rendering with the `photon` integrator, traced from the lights at
+// the time the shutter opens. They depend on the seed alone, so a resumed render traces the same
+// ones.
+fn photon_map(
+    world: &dyn Hittable,
+    lights: &HittableList,
+    camera: &Camera,
+    options: &RenderOptions,
+) -> Option<PhotonMap> {
+    (options.integrator == IntegratorKind::Photon).then(|| {
+        PhotonMap::caustics(
+            world,
+            lights,
+            &options.photons,
+            options.depth.max,
+            options.seed,
+            camera.shutter_open_time,
+        )
+    })
+}
+
+pub fn render(
+    world: &dyn Hittable,
+    lights: &HittableList,
+    camera: &Camera,
+    background: &Background,
+    options: &RenderOptions,
+) -> FrameBuffer {
+    let mut progressive = ProgressiveRender::new(world, lights, camera, background, options);
+    progressive.finish_tiles(usize::MAX);
+    progressive.framebuffer()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::camera::CameraSettings;
+    use crate::filter::FilterKind;
+    use crate::sphere::Sphere;
+    use crate::{Dielectric, Lambertian, Point3, Vec3};
+
+    // Small render with `samples_per_pixel` samples of paths of at most `max_depth` bounces, for
+    // the tests comparing integrators.
+    pub(crate) fn test_options(
+        integrator: IntegratorKind,
+        samples_per_pixel: u32,
+        max_depth: u32,
+    ) -> RenderOptions {
+        RenderOptions {
+            image_width: 8,
+            image_height: 8,
+            samples_per_pixel,
+            depth: PathDepth::new(max_depth),
+            seed: 2,
+            integrator,
+            ..Default::default()
+        }
+    }
+
+    // Mean luminance of the pixels of a render.
+    pub(crate) fn mean_luminance(
+        world: &dyn Hittable,
+        lights: &HittableList,
+        camera: &Camera,
+        background: &Background,
+        options: &RenderOptions,
+    ) -> f64 {
+        let framebuffer = render(world, lights, camera, background, options);
+        let pixels = framebuffer.pixels();
+        pixels.iter().map(|pixel| pixel.luminance()).sum::<f64>() / pixels.len() as f64
+    }
+
+    fn options(samples_per_pixel: u32) -> RenderOptions {
+        RenderOptions {
+            image_width: 6,
+            image_height: 4,
+            seed: 9,
+            ..test_options(IntegratorKind::Path, samples_per_pixel, 5)
+        }
+    }
+
+    #[test]
+    fn test_splats() {
+        let splats = Splats::new(2, 1);
+        splats.add(1, 0, Color::new(0.25, 1.0, 0.0));
+        splats.add(1, 0, Color::new(0.25, f64::NAN, 0.0));
+        assert_eq!(Color::new(0.5, 1.0, 0.0), splats.color(1));
+
+        // Light too bright for the fixed point sums saturates instead of wrapping around.
+        splats.add(0, 0, Color::new(1e30, 1e30, 1e30));
+        splats.add(0, 0, Color::new(1e30, 1e30, 1e30));
+        let most = i64::MAX as f64 / SPLAT_SCALE;
+        assert_eq!(Color::new(most, most, most), splats.color(0));
+    }
+
+    fn camera() -> Camera {
+        CameraSettings {
+            look_from: Point3::new(0.0, 0.0, 1.0),
+            look_at: Point3::new(0.0, 0.0, -1.0),
+            view_up: Vec3::new(0.0, 1.0, 0.0),
+            vertical_field_of_view: 90.0,
+            aperture: 0.0,
+            focus_distance: 1.0,
+            shutter_open_time: 0.0,
+            shutter_close_time: 1.0,
+        }
+        .build(1.5)
+    }
+
+    #[test]
+    fn test_passes() {
+        let sphere = Sphere::new(
+            Point3::new(0.0, 0.0, -1.0),
+            0.5,
+            Lambertian::new(&Color::new(0.5, 0.5, 0.5)),
+        );
+        let camera = camera();
+        let lights = HittableList::new();
+        let background = Background::Sky;
+
+        let mut progressive =
+            ProgressiveRender::new(&sphere, &lights, &camera, &background, &options(5));
+        assert_eq!(Color::default(), progressive.framebuffer().pixels()[0]);
+        progressive.add_samples(2);
+        assert_eq!(2, progressive.samples());
+        progressive.add_samples(2);
+        assert!(!progressive.is_done());
+        progressive.add_samples(2);
+        assert_eq!(5, progressive.samples());
+        assert!(progressive.is_done());
+
+        // Splitting the samples in passes gives the same image as rendering them at once.
+        let full = render(&sphere, &lights, &camera, &background, &options(5));
+        assert_eq!(full, progressive.framebuffer());
+    }
+
+    #[test]
+    fn test_resume_tiles() {
+        let sphere = Sphere::new(
+            Point3::new(0.0, 0.0, -1.0),
+            0.5,
+            Lambertian::new(&Color::new(0.5, 0.5, 0.5)),
+        );
+        let camera = camera();
+        let lights = HittableList::new();
+        let background = Background::Sky;
+        let options = RenderOptions {
+            image_width: 70,
+            image_height: 40,
+            ..options(2)
+        };
+
+        let mut interrupted =
+            ProgressiveRender::new(&sphere, &lights, &camera, &background, &options);
+        assert_eq!(6, interrupted.unfinished_tiles());
+        interrupted.finish_tiles(2);
+        assert_eq!(4, interrupted.unfinished_tiles());
+        assert_eq!(0, interrupted.samples());
+
+        // Resuming with more samples finishes the tiles left, then adds to every one.
+        let more = RenderOptions {
+            samples_per_pixel: 3,
+            ..options
+        };
+        let tiles = interrupted.tiles().to_vec();
+        let mut resumed = ProgressiveRender::with_tiles(
+            &sphere,
+            &lights,
+            &camera,
+            &background,
+            &more,
+            tiles,
+            Vec::new(),
+        )
+        .unwrap();
+        resumed.finish_tiles(usize::MAX);
+        assert!(resumed.is_done());
+        assert_eq!(
+            render(&sphere, &lights, &camera, &background, &more),
+            resumed.framebuffer()
+        );
+
+        let smaller = RenderOptions {
+            image_width: 60,
+            ..options
+        };
+        let tiles = interrupted.tiles().to_vec();
+        assert!(ProgressiveRender::with_tiles(
+            &sphere,
+            &lights,
+            &camera,
+            &background,
+            &smaller,
+            tiles,
+            Vec::new()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_converged() {
+        let adaptive = AdaptiveSampling {
+            min_samples: 4,
+            threshold: 0.05,
+        };
+        let mut flat = PixelSamples::default();
+        let mut noisy = PixelSamples::default();
+        for i in 0..4 {
+            assert!(!adaptive.is_converged(&flat));
+            flat.add(Color::new(0.5, 0.7, 1.0));
+            noisy.add(Color::new(1.0, 1.0, 1.0) * (i % 2) as f64);
+        }
+
+        assert!(adaptive.is_converged(&flat));
+        assert!(!adaptive.is_converged(&noisy));
+        assert_eq!(Color::new(0.5, 0.5, 0.5), noisy.mean());
+    }
+
+    #[test]
+    fn test_adaptive() {
+        let sphere = Sphere::new(
+            Point3::new(0.0, 0.0, -1.0),
+            0.5,
+            Lambertian::new(&Color::new(0.5, 0.5, 0.5)),
+        );
+        let camera = camera();
+        let lights = HittableList::new();
+        let background = Background::Sky;
+        let options = RenderOptions {
+            adaptive: Some(AdaptiveSampling {
+                min_samples: 4,
+                threshold: 0.02,
+            }),
+            ..options(64)
+        };
+
+        // The sky in the corners converges at once, the lit sphere in the middle needs more.
+        let mut progressive =
+            ProgressiveRender::new(&sphere, &lights, &camera, &background, &options);
+        progressive.finish_tiles(usize::MAX);
+        let counts = progressive.sample_counts();
+        assert_eq!(4, counts[0]);
+        assert!(counts[6 + 3] > 4);
+        assert_eq!([47, 0, 0], progressive.heatmap().pixels()[..3]); // 4 of 64 samples
+
+        let mut passes = ProgressiveRender::new(&sphere, &lights, &camera, &background, &options);
+        while !passes.is_done() {
+            passes.add_samples(5);
+        }
+        assert_eq!(progressive.framebuffer(), passes.framebuffer());
+    }
+
+    #[test]
+    fn test_filter() {
+        let sphere = Sphere::new(
+            Point3::new(0.0, 0.0, -1.0),
+            0.5,
+            Lambertian::new(&Color::new(0.5, 0.5, 0.5)),
+        );
+        let camera = camera();
+        let lights = HittableList::new();
+        let options = RenderOptions {
+            image_width: 70,
+            image_height: 40,
+            filter: PixelFilter::new(FilterKind::Mitchell),
+            ..options(3)
+        };
+
+        // Samples splatted across the tile borders add up the same in passes.
+        let background = Background::Sky;
+        let mut passes = ProgressiveRender::new(&sphere, &lights, &camera, &background, &options);
+        for _ in 0..3 {
+            passes.add_samples(1);
+        }
+        let full = render(&sphere, &lights, &camera, &background, &options);
+        assert_eq!(full, passes.framebuffer());
+        let boxed = RenderOptions {
+            filter: PixelFilter::default(),
+            ..options
+        };
+        assert_ne!(full, render(&sphere, &lights, &camera, &background, &boxed));
+
+        // The weights are normalized, even on the borders of the image and the tiles.
+        let color = Color::new(0.2, 0.4, 0.8);
+        let empty = HittableList::new();
+        let uniform = render(
+            &empty,
+            &lights,
+            &camera,
+            &Background::Solid(color),
+            &options,
+        );
+        for pixel in uniform.pixels() {
+            assert!((*pixel - color).length() < 1e-9, "{pixel}");
+        }
+    }
+
+    #[test]
+    fn test_depth() {
+        let camera = camera();
+        let lights = HittableList::new();
+        let background = Background::Solid(Color::new(1.0, 1.0, 1.0));
+        let luminance = |world: &dyn Hittable, depth: PathDepth| {
+            let options = RenderOptions {
+                depth,
+                ..options(64)
+            };
+            mean_luminance(world, &lights, &camera, &background, &options)
+        };
+
+        // Without refractions, the glass sphere only shows its reflections.
+        let glass = Sphere::new(Point3::new(0.0, 0.0, -1.0), 1.5, Dielectric::new(1.5));
+        let no_transmission = PathDepth {
+            transmission: 0,
+            ..PathDepth::new(5)
+        };
+        assert!(luminance(&glass, no_transmission) < 0.8 * luminance(&glass, PathDepth::new(5)));
+
+        // Russian roulette ends paths early without changing the mean.
+        let matte = Sphere::new(
+            Point3::new(0.0, 0.0, -1.0),
+            0.5,
+            Lambertian::new(&Color::new(0.9, 0.9, 0.9)),
+        );
+        let no_roulette = PathDepth {
+            roulette: 5,
+            ..PathDepth::new(5)
+        };
+        let roulette = PathDepth {
+            roulette: 0,
+            ..PathDepth::new(5)
+        };
+        let expected = luminance(&matte, no_roulette);
+        assert!((luminance(&matte, roulette) - expected).abs() < 0.02 * expected);
+    }
+}
@@ -0,0 +1,548 @@
+use std::f64::consts::PI;
+use std::str::FromStr;
+
+use rand::rngs::StdRng;
+
+use crate::utils::{mix, random_f64, sample_rng};
+use crate::Vec3;
+
+// Source of the numbers in [0, 1) a sample is made of. Each sample of a pixel draws its dimensions
+// in the same order: the position in the pixel, the lens, the time, then the bounces, so that
+// samplers spreading the samples of a pixel evenly do so for each of these decisions.
+pub trait Sampler {
+    // Restart at the first dimension of sample `index` of `pixel`.
+    fn start_sample(&mut self, pixel: u64, index: u32);
+
+    fn next_1d(&mut self) -> f64;
+
+    fn next_2d(&mut self) -> (f64, f64);
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum SamplerKind {
+    Independent,
+    Stratified,
+    Halton,
+    #[default]
+    Sobol,
+}
+
+impl SamplerKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SamplerKind::Independent => "independent",
+            SamplerKind::Stratified => "stratified",
+            SamplerKind::Halton => "halton",
+            SamplerKind::Sobol => "sobol",
+        }
+    }
+
+    pub fn sampler(&self, seed: u64, samples_per_pixel: u32) -> Box<dyn Sampler> {
+        match self {
+            SamplerKind::Independent => Box::new(IndependentSampler::new(seed)),
+            SamplerKind::Stratified => Box::new(StratifiedSampler::new(seed, samples_per_pixel)),
+            SamplerKind::Halton => Box::new(HaltonSampler::new(seed)),
+            SamplerKind::Sobol => Box::new(SobolSampler::new(seed)),
+        }
+    }
+}
+
+impl FromStr for SamplerKind {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "independent" => Ok(SamplerKind::Independent),
+            "stratified" => Ok(SamplerKind::Stratified),
+            "halton" => Ok(SamplerKind::Halton),
+            "sobol" => Ok(SamplerKind::Sobol),
+            _ => Err(format!(
+                "unknown sampler '{name}', expected independent, stratified, halton or sobol"
+            )),
+        }
+    }
+}
+
+// Uniform random numbers, each sample with its own generator.
+pub struct IndependentSampler {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl IndependentSampler {
+    pub fn new(seed: u64) -> Self {
+        IndependentSampler {
+            seed,
+            rng: sample_rng(seed, 0, 0),
+        }
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn start_sample(&mut self, pixel: u64, index: u32) {
+        self.rng = sample_rng(self.seed, pixel, index as u64);
+    }
+
+    fn next_1d(&mut self) -> f64 {
+        random_f64(&mut self.rng)
+    }
+
+    fn next_2d(&mut self) -> (f64, f64) {
+        (self.next_1d(), self.next_1d())
+    }
+}
+
+// One sample at a random position in each of `samples_per_pixel` strata, the strata being
+// shuffled differently for every dimension. The strata depend on `samples_per_pixel`, so adding
+// samples to a finished render does not give the image rendered with all of them at once.
+pub struct StratifiedSampler {
+    seed: u64,
+    samples_per_pixel: u32,
+    pixel: u64,
+    index: u32,
+    dimension: u64,
+    rng: StdRng,
+}
+
+impl StratifiedSampler {
+    pub fn new(seed: u64, samples_per_pixel: u32) -> Self {
+        StratifiedSampler {
+            seed,
+            samples_per_pixel: samples_per_pixel.max(1),
+            pixel: 0,
+            index: 0,
+            dimension: 0,
+            rng: sample_rng(seed, 0, 0),
+        }
+    }
+
+    // Stratum of the current sample in the next dimension.
+    fn next_stratum(&mut self) -> u32 {
+        let hash = dimension_hash(self.seed, self.pixel, self.dimension);
+        self.dimension += 1;
+        permutation_element(
+            self.index % self.samples_per_pixel,
+            self.samples_per_pixel,
+            hash as u32,
+        )
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_sample(&mut self, pixel: u64, index: u32) {
+        self.pixel = pixel;
+        self.index = index;
+        self.dimension = 0;
+        self.rng = sample_rng(self.seed, pixel, index as u64);
+    }
+
+    fn next_1d(&mut self) -> f64 {
+        let stratum = self.next_stratum();
+        (stratum as f64 + random_f64(&mut self.rng)) / self.samples_per_pixel as f64
+    }
+
+    // Strata on a grid as square as possible, some of them left empty when the samples do not
+    // fill it.
+    fn next_2d(&mut self) -> (f64, f64) {
+        let columns = (self.samples_per_pixel as f64).sqrt().ceil() as u32;
+        let rows = self.samples_per_pixel.div_ceil(columns);
+        let stratum = self.next_stratum();
+        (
+            ((stratum % columns) as f64 + random_f64(&mut self.rng)) / columns as f64,
+            ((stratum / columns) as f64 + random_f64(&mut self.rng)) / rows as f64,
+        )
+    }
+}
+
+// Bases of the Halton dimensions, past them the numbers are random.
+const PRIMES: [u32; 256] = primes();
+
+const fn primes<const N: usize>() -> [u32; N] {
+    let mut primes = [0; N];
+    let mut count = 0;
+    let mut candidate = 2;
+    while count < N {
+        let mut index = 0;
+        while index < count && candidate % primes[index] != 0 {
+            index += 1;
+        }
+        if index == count {
+            primes[count] = candidate;
+            count += 1;
+        }
+        candidate += 1;
+    }
+    primes
+}
+
+// Radical inverses of the sample index in a different prime base for every dimension, with the
+// digits Owen scrambled per pixel so neighbouring pixels do not share the same pattern.
+pub struct HaltonSampler {
+    seed: u64,
+    pixel: u64,
+    index: u32,
+    dimension: u64,
+    rng: StdRng,
+}
+
+impl HaltonSampler {
+    pub fn new(seed: u64) -> Self {
+        HaltonSampler {
+            seed,
+            pixel: 0,
+            index: 0,
+            dimension: 0,
+            rng: sample_rng(seed, 0, 0),
+        }
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn start_sample(&mut self, pixel: u64, index: u32) {
+        self.pixel = pixel;
+        self.index = index;
+        self.dimension = 0;
+        self.rng = sample_rng(self.seed, pixel, index as u64);
+    }
+
+    fn next_1d(&mut self) -> f64 {
+        let dimension = self.dimension;
+        self.dimension += 1;
+        match PRIMES.get(dimension as usize) {
+            Some(&base) => {
+                let hash = dimension_hash(self.seed, self.pixel, dimension);
+                scrambled_radical_inverse(self.index, base, hash as u32)
+            }
+            None => random_f64(&mut self.rng),
+        }
+    }
+
+    fn next_2d(&mut self) -> (f64, f64) {
+        (self.next_1d(), self.next_1d())
+    }
+}
+
+// Pairs of the first two Sobol dimensions for every pair of dimensions, with the sample order
+// shuffled and the points Owen scrambled independently for each pair (Burley, "Practical
+// Hash-based Owen Scrambling", 2020). Any power of two of samples stays well stratified, and
+// there is no limit on the number of dimensions.
+pub struct SobolSampler {
+    seed: u64,
+    pixel: u64,
+    index: u32,
+    dimension: u64,
+}
+
+impl SobolSampler {
+    pub fn new(seed: u64) -> Self {
+        SobolSampler {
+            seed,
+            pixel: 0,
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    // Sobol point for the next dimensions, before the scrambling of the coordinates.
+    fn next_point(&mut self) -> (u64, u32) {
+        let hash = dimension_hash(self.seed, self.pixel, self.dimension);
+        self.dimension += 1;
+        let index = owen_scramble(self.index, hash as u32);
+        (hash, index)
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn start_sample(&mut self, pixel: u64, index: u32) {
+        self.pixel = pixel;
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn next_1d(&mut self) -> f64 {
+        let (hash, index) = self.next_point();
+        to_unit(owen_scramble(index.reverse_bits(), (hash >> 32) as u32))
+    }
+
+    fn next_2d(&mut self) -> (f64, f64) {
+        let (hash, index) = self.next_point();
+        let second_hash = mix(hash) as u32;
+        (
+            to_unit(owen_scramble(index.reverse_bits(), (hash >> 32) as u32)),
+            to_unit(owen_scramble(sobol_second(index), second_hash)),
+        )
+    }
+}
+
+fn dimension_hash(seed: u64, pixel: u64, dimension: u64) -> u64 {
+    mix(mix(mix(seed) ^ pixel) ^ dimension)
+}
+
+fn to_unit(value: u32) -> f64 {
+    value as f64 / (1u64 << 32) as f64
+}
+
+// Second dimension of the Sobol sequence, its direction numbers following Pascal's triangle
+// modulo 2.
+fn sobol_second(index: u32) -> u32 {
+    let mut result = 0;
+    let mut direction = 1 << 31;
+    let mut index = index;
+    while index != 0 {
+        if index & 1 != 0 {
+            result ^= direction;
+        }
+        direction ^= direction >> 1;
+        index >>= 1;
+    }
+    result
+}
+
+// Owen scrambling of the bits of `value` from the most significant one, each bit being flipped
+// depending on the bits above it.
+fn owen_scramble(value: u32, seed: u32) -> u32 {
+    let mut x = value.reverse_bits();
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50_b47c);
+    x ^= x.wrapping_mul(0xb82f_1e52);
+    x ^= x.wrapping_mul(0xc7af_e638);
+    x ^= x.wrapping_mul(0x8d22_f6e6);
+    x.reverse_bits()
+}
+
+// Radical inverse of `index` in `base`, each digit permuted depending on the digits before it.
+fn scrambled_radical_inverse(index: u32, base: u32, seed: u32) -> f64 {
+    let inverse_base = 1.0 / base as f64;
+    let mut index = index;
+    let mut digits: u64 = 0;
+    let mut scale = 1.0;
+    // Digits past the precision of a double do not change the result.
+    while 1.0 - (base - 1) as f64 * scale * inverse_base < 1.0 {
+        let digit = index % base;
+        index /= base;
+        let hash = mix(seed as u64 ^ digits) as u32;
+        digits = digits.wrapping_mul(base as u64) + permutation_element(digit, base, hash) as u64;
+        scale *= inverse_base;
+    }
+    (digits as f64 * scale).min(1.0 - f64::EPSILON / 2.0)
+}
+
+// Element `index` of a random permutation of 0..length chosen by `seed` (Kensler, "Correlated
+// Multi-Jittered Sampling", 2013).
+fn permutation_element(index: u32, length: u32, seed: u32) -> u32 {
+    let mut mask = length - 1;
+    mask |= mask >> 1;
+    mask |= mask >> 2;
+    mask |= mask >> 4;
+    mask |= mask >> 8;
+    mask |= mask >> 16;
+
+    let mut i = index;
+    loop {
+        i ^= seed;
+        i = i.wrapping_mul(0xe170_893d);
+        i ^= seed >> 16;
+        i ^= (i & mask) >> 4;
+        i ^= seed >> 8;
+        i = i.wrapping_mul(0x0929_eb3f);
+        i ^= seed >> 23;
+        i ^= (i & mask) >> 1;
+        i = i.wrapping_mul(1 | seed >> 27);
+        i = i.wrapping_mul(0x6935_fa69);
+        i ^= (i & mask) >> 11;
+        i = i.wrapping_mul(0x74dc_b303);
+        i ^= (i & mask) >> 2;
+        i = i.wrapping_mul(0x9e50_1cc3);
+        i ^= (i & mask) >> 2;
+        i = i.wrapping_mul(0xc860_a3df);
+        i &= mask;
+        i ^= i >> 5;
+        if i < length {
+            return (i.wrapping_add(seed)) % length;
+        }
+    }
+}
+
+// Point of the disk of radius 1 in the z = 0 plane, keeping the stratification of `u` (Shirley and
+// Chiu's concentric mapping).
+pub fn sample_unit_disk(u: (f64, f64)) -> Vec3 {
+    let (a, b) = (2.0 * u.0 - 1.0, 2.0 * u.1 - 1.0);
+    if a == 0.0 && b == 0.0 {
+        return Vec3::default();
+    }
+
+    let (radius, angle) = if a.abs() > b.abs() {
+        (a, PI / 4.0 * (b / a))
+    } else {
+        (b, PI / 2.0 - PI / 4.0 * (a / b))
+    };
+    Vec3::new(radius * angle.cos(), radius * angle.sin(), 0.0)
+}
+
+// Point of the sphere of radius 1.
+pub fn sample_unit_vector(u: (f64, f64)) -> Vec3 {
+    let z = 1.0 - 2.0 * u.0;
+    let radius = (1.0 - z * z).max(0.0).sqrt();
+    let angle = 2.0 * PI * u.1;
+    Vec3::new(radius * angle.cos(), radius * angle.sin(), z)
+}
+
+// Direction of the hemisphere around +Z, with a density following the cosine to +Z (Malley's
+// method, lifting the disk onto the hemisphere).
+pub fn sample_cosine_direction(u: (f64, f64)) -> Vec3 {
+    let disk = sample_unit_disk(u);
+    let z = (1.0 - disk.length_squared()).max(0.0).sqrt();
+    Vec3::new(disk.x(), disk.y(), z)
+}
+
+// Point inside the sphere of radius 1, `w` choosing the distance to the center.
+pub fn sample_unit_ball(u: (f64, f64), w: f64) -> Vec3 {
+    w.cbrt() * sample_unit_vector(u)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samplers() -> Vec<Box<dyn Sampler>> {
+        [
+            SamplerKind::Independent,
+            SamplerKind::Stratified,
+            SamplerKind::Halton,
+            SamplerKind::Sobol,
+        ]
+        .iter()
+        .map(|kind| kind.sampler(3, 16))
+        .collect()
+    }
+
+    // The values of every sample of a pixel for one dimension after `skip` others.
+    fn dimension(sampler: &mut dyn Sampler, pixel: u64, skip: usize, count: u32) -> Vec<f64> {
+        (0..count)
+            .map(|index| {
+                sampler.start_sample(pixel, index);
+                for _ in 0..skip {
+                    sampler.next_1d();
+                }
+                sampler.next_1d()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_names() {
+        for sampler in ["independent", "stratified", "halton", "sobol"] {
+            assert_eq!(sampler, sampler.parse::<SamplerKind>().unwrap().name());
+        }
+        assert!("random".parse::<SamplerKind>().is_err());
+    }
+
+    #[test]
+    fn test_range() {
+        for mut sampler in samplers() {
+            for index in 0..64 {
+                sampler.start_sample(5, index);
+                for _ in 0..300 {
+                    let value = sampler.next_1d();
+                    assert!((0.0..1.0).contains(&value), "{value}");
+                    let (x, y) = sampler.next_2d();
+                    assert!((0.0..1.0).contains(&x) && (0.0..1.0).contains(&y));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_deterministic() {
+        for mut sampler in samplers() {
+            let values = dimension(sampler.as_mut(), 2, 3, 16);
+            assert_eq!(values, dimension(sampler.as_mut(), 2, 3, 16));
+            assert_ne!(values, dimension(sampler.as_mut(), 7, 3, 16));
+            assert_ne!(values, dimension(sampler.as_mut(), 2, 4, 16));
+        }
+    }
+
+    #[test]
+    fn test_stratified() {
+        // Each of 16 intervals gets one of 16 samples, in every dimension but for Halton whose
+        // other bases than 2 do not divide 16.
+        let kinds = [
+            (SamplerKind::Stratified, vec![0, 1, 7, 100]),
+            (SamplerKind::Halton, vec![0]),
+            (SamplerKind::Sobol, vec![0, 1, 7, 100]),
+        ];
+        for (kind, skips) in kinds {
+            let mut sampler = kind.sampler(3, 16);
+            for skip in skips {
+                let mut strata: Vec<_> = dimension(sampler.as_mut(), 11, skip, 16)
+                    .iter()
+                    .map(|value| (value * 16.0) as u32)
+                    .collect();
+                strata.sort();
+                assert_eq!((0..16).collect::<Vec<_>>(), strata);
+            }
+        }
+    }
+
+    #[test]
+    fn test_sobol_2d() {
+        // The 16 first points fall in different cells of the 4x4, 2x8 and 8x2 grids.
+        let mut sampler = SobolSampler::new(1);
+        let points: Vec<_> = (0..16)
+            .map(|index| {
+                sampler.start_sample(4, index);
+                sampler.next_2d();
+                sampler.next_2d()
+            })
+            .collect();
+        for (columns, rows) in [(4.0, 4.0), (2.0, 8.0), (8.0, 2.0), (16.0, 1.0), (1.0, 16.0)] {
+            let mut cells: Vec<_> = points
+                .iter()
+                .map(|(x, y)| ((x * columns) as u32, (y * rows) as u32))
+                .collect();
+            cells.sort();
+            cells.dedup();
+            assert_eq!(16, cells.len());
+        }
+    }
+
+    #[test]
+    fn test_less_noise() {
+        // Integrating x * y over the unit square with 16 samples per pixel.
+        let squared_error = |kind: SamplerKind| -> f64 {
+            let mut sampler = kind.sampler(1, 16);
+            (0..256)
+                .map(|pixel| {
+                    let mut sum = 0.0;
+                    for index in 0..16 {
+                        sampler.start_sample(pixel, index);
+                        let (x, y) = sampler.next_2d();
+                        sum += x * y;
+                    }
+                    (sum / 16.0 - 0.25).powi(2)
+                })
+                .sum()
+        };
+
+        let independent = squared_error(SamplerKind::Independent);
+        for kind in [
+            SamplerKind::Stratified,
+            SamplerKind::Halton,
+            SamplerKind::Sobol,
+        ] {
+            assert!(squared_error(kind) < independent / 4.0, "{kind:?}");
+        }
+    }
+
+    #[test]
+    fn test_warps() {
+        for u in [(0.0, 0.0), (0.1, 0.9), (0.5, 0.5), (0.99, 0.3)] {
+            assert!(sample_unit_disk(u).length() <= 1.0 + 1e-12);
+            assert!((sample_unit_vector(u).length() - 1.0).abs() < 1e-12);
+            assert!(sample_unit_ball(u, 0.5).length() < 1.0);
+            let cosine = sample_cosine_direction(u);
+            assert!((cosine.length() - 1.0).abs() < 1e-12 && cosine.z() >= 0.0);
+        }
+        assert!((sample_unit_disk((1.0, 0.5)) - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-12);
+    }
+}
@@ -0,0 +1,968 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+use crate::aarect::{XYRect, XZRect, YZRect};
+use crate::background::Background;
+use crate::box_shape::BoxShape;
+use crate::camera::CameraSettings;
+use crate::constant_medium::ConstantMedium;
+use crate::dielectric::Dielectric;
+use crate::diffuse_light::DiffuseLight;
+use crate::flip_face::FlipFace;
+use crate::hittable::{HitRecord, Hittable};
+use crate::hittable_list::HittableList;
+use crate::isotropic::Isotropic;
+use crate::lambertian::Lambertian;
+use crate::material::{Material, ScatterRecord};
+use crate::mesh::Mesh;
+use crate::metal::Metal;
+use crate::moving_sphere::MovingSphere;
+use crate::obj::load_obj;
+use crate::perlin::{NoiseKind, NoiseTexture};
+use crate::sampler::Sampler;
+use crate::sphere::Sphere;
+use crate::texture::{CheckerTexture, ImageTexture, SolidColor, Texture};
+use crate::triangle::Triangle;
+use crate::{Color, Point3, Ray, Vec3};
+
+// Scene description format, one directive per line, '#' starts a comment:
+//
+//   camera look_from=13,2,3 look_at=0,0,0 vfov=20 [view_up=0,1,0] [aperture=0]
+//          [focus_distance=<distance to look_at>] [time0=0] [time1=1]
+//   texture <name> solid color=<r,g,b>
+//   texture <name> checker even=<r,g,b> odd=<r,g,b> [size=1]
+//   texture <name> image file=<image.ppm|image.png>
+//   texture <name> noise [kind=noise|turbulence|marble] [scale=1] [seed=0]
+//   material <name> lambertian albedo=<r,g,b>|texture=<name>
+//   material <name> metal albedo=<r,g,b>|texture=<name> [fuzz=0]
+//   material <name> dielectric index=<index of refraction>
+//   material <name> diffuse_light emit=<r,g,b>|texture=<name>
+//   material <name> isotropic albedo=<r,g,b>|texture=<name>
+//   background sky|black|<r,g,b>
+//   sphere center=<x,y,z> radius=<r> material=<name> [density=<d>]
+//   moving_sphere center0=<x,y,z> center1=<x,y,z> time0=<t> time1=<t> radius=<r> material=<name>
+//   xy_rect x0=<x> x1=<x> y0=<y> y1=<y> k=<z> material=<name> [flip=false]
+//   xz_rect x0=<x> x1=<x> z0=<z> z1=<z> k=<y> material=<name> [flip=false]
+//   yz_rect y0=<y> y1=<y> z0=<z> z1=<z> k=<x> material=<name> [flip=false]
+//   box min=<x,y,z> max=<x,y,z> material=<name> [density=<d>]
+//   triangle v0=<x,y,z> v1=<x,y,z> v2=<x,y,z> [n0=<x,y,z> n1=<x,y,z> n2=<x,y,z>] material=<name>
+//   mesh file=<model.obj> [material=<name>] [scale=1] [translate=<x,y,z>]
+//
+// A mesh without material uses the materials named by its 'usemtl' statements, looked up in its
+// '.mtl' libraries then in the scene materials. Paths are relative to the scene file.
+//
+// A sphere or box with a density is filled with smoke instead, scattering light with its material,
+// usually isotropic.
+//
+// Textures must be declared before the materials using them, and materials before the objects.
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(std::io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl Display for SceneError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SceneError::Io(error) => write!(f, "unable to read scene: {error}"),
+            SceneError::Parse { line, message } => write!(f, "line {line}: {message}"),
+        }
+    }
+}
+
+impl From<std::io::Error> for SceneError {
+    fn from(error: std::io::Error) -> Self {
+        SceneError::Io(error)
+    }
+}
+
+// Textures referenced by name in a scene file.
+#[derive(Debug, Clone)]
+pub enum SceneTexture {
+    Solid(SolidColor),
+    Checker(CheckerTexture),
+    Image(ImageTexture),
+    Noise(NoiseTexture),
+}
+
+impl Texture for SceneTexture {
+    fn value(&self, u: f64, v: f64, point: &Point3) -> Color {
+        match self {
+            SceneTexture::Solid(texture) => texture.value(u, v, point),
+            SceneTexture::Checker(texture) => texture.value(u, v, point),
+            SceneTexture::Image(texture) => texture.value(u, v, point),
+            SceneTexture::Noise(texture) => texture.value(u, v, point),
+        }
+    }
+}
+
+impl From<Color> for SceneTexture {
+    fn from(color: Color) -> Self {
+        SceneTexture::Solid(SolidColor::new(color))
+    }
+}
+
+// Materials referenced by name in a scene file, resolved when the file is loaded.
+#[derive(Debug, Clone)]
+pub enum SceneMaterial {
+    Lambertian(Lambertian<SceneTexture>),
+    Metal(Metal<SceneTexture>),
+    Dielectric(Dielectric),
+    DiffuseLight(DiffuseLight<SceneTexture>),
+    Isotropic(Isotropic<SceneTexture>),
+}
+
+impl SceneMaterial {
+    fn is_light(&self) -> bool {
+        matches!(self, SceneMaterial::DiffuseLight(_))
+    }
+}
+
+impl Material for SceneMaterial {
+    fn scatter(
+        &self,
+        ray: &Ray,
+        hit_record: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        match self {
+            SceneMaterial::Lambertian(material) => material.scatter(ray, hit_record, sampler),
+            SceneMaterial::Metal(material) => material.scatter(ray, hit_record, sampler),
+            SceneMaterial::Dielectric(material) => material.scatter(ray, hit_record, sampler),
+            SceneMaterial::DiffuseLight(material) => material.scatter(ray, hit_record, sampler),
+            SceneMaterial::Isotropic(material) => material.scatter(ray, hit_record, sampler),
+        }
+    }
+
+    fn scattering_pdf(&self, ray: &Ray, hit_record: &HitRecord, scattered: &Ray) -> f64 {
+        match self {
+            SceneMaterial::Lambertian(material) => {
+                material.scattering_pdf(ray, hit_record, scattered)
+            }
+            SceneMaterial::Metal(material) => material.scattering_pdf(ray, hit_record, scattered),
+            SceneMaterial::Dielectric(material) => {
+                material.scattering_pdf(ray, hit_record, scattered)
+            }
+            SceneMaterial::DiffuseLight(material) => {
+                material.scattering_pdf(ray, hit_record, scattered)
+            }
+            SceneMaterial::Isotropic(material) => {
+                material.scattering_pdf(ray, hit_record, scattered)
+            }
+        }
+    }
+
+    fn emitted(&self, u: f64, v: f64, point: &Point3) -> Color {
+        match self {
+            SceneMaterial::Lambertian(material) => material.emitted(u, v, point),
+            SceneMaterial::Metal(material) => material.emitted(u, v, point),
+            SceneMaterial::Dielectric(material) => material.emitted(u, v, point),
+            SceneMaterial::DiffuseLight(material) => material.emitted(u, v, point),
+            SceneMaterial::Isotropic(material) => material.emitted(u, v, point),
+        }
+    }
+
+    fn is_medium(&self) -> bool {
+        match self {
+            SceneMaterial::Lambertian(material) => material.is_medium(),
+            SceneMaterial::Metal(material) => material.is_medium(),
+            SceneMaterial::Dielectric(material) => material.is_medium(),
+            SceneMaterial::DiffuseLight(material) => material.is_medium(),
+            SceneMaterial::Isotropic(material) => material.is_medium(),
+        }
+    }
+}
+
+pub struct Scene {
+    pub world: HittableList,
+    // Copies of the emitting spheres and rectangles of the world, sampled to light its surfaces.
+    pub lights: HittableList,
+    pub camera: CameraSettings,
+    pub background: Background,
+    // Files the scene file refers to, read while loading it: meshes, material libraries and
+    // images.
+    pub files: Vec<PathBuf>,
+}
+
+pub fn load_scene<P: AsRef<Path>>(path: P) -> Result<Scene, SceneError> {
+    let source = std::fs::read_to_string(path.as_ref())?;
+    let directory = path.as_ref().parent().unwrap_or_else(|| Path::new(""));
+    parse_scene(&source, directory)
+}
+
+// Paths in the scene, like the mesh files, are relative to `directory`.
+pub fn parse_scene(source: &str, directory: &Path) -> Result<Scene, SceneError> {
+    let mut textures: HashMap<String, SceneTexture> = HashMap::new();
+    let mut materials: HashMap<String, SceneMaterial> = HashMap::new();
+    let mut world = HittableList::new();
+    let mut lights = HittableList::new();
+    let mut camera = None;
+    let mut background = None;
+    let mut files = Vec::new();
+
+    for (index, line) in source.lines().enumerate() {
+        let error = |message: String| SceneError::Parse {
+            line: index + 1,
+            message,
+        };
+
+        let content = line.split('#').next().unwrap_or_default();
+        let mut tokens = content.split_whitespace();
+        let directive = match tokens.next() {
+            Some(directive) => directive,
+            None => continue,
+        };
+
+        match directive {
+            "camera" => {
+                if camera.is_some() {
+                    return Err(error("the camera is already defined".to_string()));
+                }
+                let fields = Fields::parse(tokens).map_err(error)?;
+                camera = Some(parse_camera(fields).map_err(error)?);
+            }
+            "background" => {
+                if background.is_some() {
+                    return Err(error("the background is already defined".to_string()));
+                }
+                let value = tokens
+                    .next()
+                    .ok_or_else(|| error("missing background color".to_string()))?;
+                if let Some(extra) = tokens.next() {
+                    return Err(error(format!("unexpected '{extra}' after the background")));
+                }
+                background = Some(value.parse().map_err(error)?);
+            }
+            "texture" => {
+                let name = tokens
+                    .next()
+                    .ok_or_else(|| error("missing texture name".to_string()))?;
+                let kind = tokens
+                    .next()
+                    .ok_or_else(|| error(format!("missing type of texture '{name}'")))?;
+                if textures.contains_key(name) {
+                    return Err(error(format!("texture '{name}' is already defined")));
+                }
+                let fields = Fields::parse(tokens).map_err(error)?;
+                let texture = parse_texture(kind, fields, directory, &mut files).map_err(error)?;
+                textures.insert(name.to_string(), texture);
+            }
+            "material" => {
+                let name = tokens
+                    .next()
+                    .ok_or_else(|| error("missing material name".to_string()))?;
+                let kind = tokens
+                    .next()
+                    .ok_or_else(|| error(format!("missing type of material '{name}'")))?;
+                if materials.contains_key(name) {
+                    return Err(error(format!("material '{name}' is already defined")));
+                }
+                let fields = Fields::parse(tokens).map_err(error)?;
+                let material = parse_material(kind, fields, &textures).map_err(error)?;
+                materials.insert(name.to_string(), material);
+            }
+            "sphere" => {
+                let mut fields = Fields::parse(tokens).map_err(error)?;
+                let center = fields.vec3("center").map_err(error)?;
+                let radius = fields.f64("radius").map_err(error)?;
+                let material = fields.material(&materials).map_err(error)?;
+                let density = fields.density().map_err(error)?;
+                fields.finish().map_err(error)?;
+                let sphere = Sphere::new(center, radius, material.clone());
+                match density {
+                    Some(density) => {
+                        world.add(Box::new(ConstantMedium::new(sphere, density, material)))
+                    }
+                    None => {
+                        if material.is_light() {
+                            lights.add(Box::new(Sphere::new(center, radius, material.clone())));
+                        }
+                        world.add(Box::new(sphere));
+                    }
+                }
+            }
+            "moving_sphere" => {
+                let mut fields = Fields::parse(tokens).map_err(error)?;
+                let sphere = MovingSphere::new(
+                    fields.vec3("center0").map_err(error)?,
+                    fields.vec3("center1").map_err(error)?,
+                    fields.f64("time0").map_err(error)?,
+                    fields.f64("time1").map_err(error)?,
+                    fields.f64("radius").map_err(error)?,
+                    fields.material(&materials).map_err(error)?,
+                );
+                fields.finish().map_err(error)?;
+                world.add(Box::new(sphere));
+            }
+            "xy_rect" | "xz_rect" | "yz_rect" => {
+                let fields = Fields::parse(tokens).map_err(error)?;
+                let rect = parse_rect(directive, fields, &materials).map_err(error)?;
+                if rect.material.is_light() {
+                    lights.add(rect.build());
+                }
+                world.add(rect.build());
+            }
+            "box" => {
+                let mut fields = Fields::parse(tokens).map_err(error)?;
+                let minimum = fields.vec3("min").map_err(error)?;
+                let maximum = fields.vec3("max").map_err(error)?;
+                let material = fields.material(&materials).map_err(error)?;
+                let density = fields.density().map_err(error)?;
+                fields.finish().map_err(error)?;
+                if minimum.x() > maximum.x()
+                    || minimum.y() > maximum.y()
+                    || minimum.z() > maximum.z()
+                {
+                    return Err(error(
+                        "field 'min' must be smaller than 'max' on every axis".to_string(),
+                    ));
+                }
+                let shape = BoxShape::new(minimum, maximum, material.clone());
+                match density {
+                    Some(density) => {
+                        world.add(Box::new(ConstantMedium::new(shape, density, material)))
+                    }
+                    None => world.add(Box::new(shape)),
+                }
+            }
+            "triangle" => {
+                let mut fields = Fields::parse(tokens).map_err(error)?;
+                let v0 = fields.vec3("v0").map_err(error)?;
+                let v1 = fields.vec3("v1").map_err(error)?;
+                let v2 = fields.vec3("v2").map_err(error)?;
+                let normals = [
+                    fields.optional_vec3("n0").map_err(error)?,
+                    fields.optional_vec3("n1").map_err(error)?,
+                    fields.optional_vec3("n2").map_err(error)?,
+                ];
+                let material = fields.material(&materials).map_err(error)?;
+                fields.finish().map_err(error)?;
+                match normals {
+                    [None, None, None] => world.add(Box::new(Triangle::new(v0, v1, v2, material))),
+                    [Some(n0), Some(n1), Some(n2)] => world.add(Box::new(Triangle::with_normals(
+                        v0,
+                        v1,
+                        v2,
+                        [n0, n1, n2],
+                        material,
+                    ))),
+                    _ => {
+                        return Err(error(
+                            "fields 'n0', 'n1' and 'n2' must be given together".to_string(),
+                        ))
+                    }
+                }
+            }
+            "mesh" => {
+                let fields = Fields::parse(tokens).map_err(error)?;
+                let mesh = parse_mesh(fields, directory, &materials, &mut files).map_err(error)?;
+                world.add(Box::new(mesh));
+            }
+            _ => return Err(error(format!("unknown directive '{directive}'"))),
+        }
+    }
+
+    let camera = camera.ok_or_else(|| SceneError::Parse {
+        line: source.lines().count().max(1),
+        message: "missing camera directive".to_string(),
+    })?;
+
+    Ok(Scene {
+        world,
+        lights,
+        camera,
+        background: background.unwrap_or(Background::Sky),
+        files,
+    })
+}
+
+fn parse_camera(mut fields: Fields) -> Result<CameraSettings, String> {
+    let look_from = fields.vec3("look_from")?;
+    let look_at = fields.vec3("look_at")?;
+    let camera = CameraSettings {
+        look_from,
+        look_at,
+        view_up: fields
+            .optional_vec3("view_up")?
+            .unwrap_or_else(|| Vec3::new(0.0, 1.0, 0.0)),
+        vertical_field_of_view: fields.f64("vfov")?,
+        aperture: fields.optional_f64("aperture")?.unwrap_or(0.0),
+        focus_distance: fields
+            .optional_f64("focus_distance")?
+            .unwrap_or_else(|| (look_from - look_at).length()),
+        shutter_open_time: fields.optional_f64("time0")?.unwrap_or(0.0),
+        shutter_close_time: fields.optional_f64("time1")?.unwrap_or(1.0),
+    };
+    fields.finish()?;
+
+    if (look_at - look_from).near_zero() {
+        return Err("fields 'look_from' and 'look_at' must be different points".to_string());
+    }
+
+    Ok(camera)
+}
+
+// Rectangle of the scene, built twice when it is also a light.
+struct SceneRect {
+    kind: String,
+    bounds: (f64, f64, f64, f64, f64),
+    material: SceneMaterial,
+    flip: bool,
+}
+
+impl SceneRect {
+    fn build(&self) -> Box<dyn Hittable> {
+        let (a0, a1, b0, b1, k) = self.bounds;
+        let material = self.material.clone();
+        match (self.kind.as_str(), self.flip) {
+            ("xy_rect", false) => Box::new(XYRect::new(a0, a1, b0, b1, k, material)),
+            ("xy_rect", true) => Box::new(FlipFace::new(XYRect::new(a0, a1, b0, b1, k, material))),
+            ("xz_rect", false) => Box::new(XZRect::new(a0, a1, b0, b1, k, material)),
+            ("xz_rect", true) => Box::new(FlipFace::new(XZRect::new(a0, a1, b0, b1, k, material))),
+            (_, false) => Box::new(YZRect::new(a0, a1, b0, b1, k, material)),
+            (_, true) => Box::new(FlipFace::new(YZRect::new(a0, a1, b0, b1, k, material))),
+        }
+    }
+}
+
+fn parse_rect(
+    kind: &str,
+    mut fields: Fields,
+    materials: &HashMap<String, SceneMaterial>,
+) -> Result<SceneRect, String> {
+    let (a, b) = match kind {
+        "xy_rect" => ("x", "y"),
+        "xz_rect" => ("x", "z"),
+        _ => ("y", "z"),
+    };
+    let a0 = fields.f64(&format!("{a}0"))?;
+    let a1 = fields.f64(&format!("{a}1"))?;
+    let b0 = fields.f64(&format!("{b}0"))?;
+    let b1 = fields.f64(&format!("{b}1"))?;
+    let k = fields.f64("k")?;
+    let material = fields.material(materials)?;
+    let flip = fields.optional_bool("flip")?.unwrap_or(false);
+    fields.finish()?;
+
+    Ok(SceneRect {
+        kind: kind.to_string(),
+        bounds: (a0, a1, b0, b1, k),
+        material,
+        flip,
+    })
+}
+
+fn parse_mesh(
+    mut fields: Fields,
+    directory: &Path,
+    materials: &HashMap<String, SceneMaterial>,
+    files: &mut Vec<PathBuf>,
+) -> Result<Mesh<SceneMaterial>, String> {
+    let file = fields.string("file")?;
+    let material = match fields.optional_string("material") {
+        None => None,
+        Some(name) => Some(
+            materials
+                .get(name)
+                .cloned()
+                .ok_or_else(|| format!("unknown material '{name}'"))?,
+        ),
+    };
+    let scale = fields.optional_f64("scale")?.unwrap_or(1.0);
+    let translate = fields.optional_vec3("translate")?.unwrap_or_default();
+    fields.finish()?;
+
+    if scale <= 0.0 {
+        return Err("field 'scale' must be positive".to_string());
+    }
+
+    let mut model = load_obj(&directory.join(file), materials, material)?;
+    files.append(&mut model.files);
+    for position in model.positions.iter_mut() {
+        *position = scale * *position + translate;
+    }
+
+    Ok(model.into_mesh())
+}
+
+fn parse_texture(
+    kind: &str,
+    mut fields: Fields,
+    directory: &Path,
+    files: &mut Vec<PathBuf>,
+) -> Result<SceneTexture, String> {
+    let texture = match kind {
+        "solid" => SceneTexture::from(fields.vec3("color")?),
+        "checker" => {
+            let even = SolidColor::new(fields.vec3("even")?);
+            let odd = SolidColor::new(fields.vec3("odd")?);
+            let size = fields.optional_f64("size")?.unwrap_or(1.0);
+            if size <= 0.0 {
+                return Err("field 'size' must be positive".to_string());
+            }
+            SceneTexture::Checker(CheckerTexture::new(even, odd, size))
+        }
+        "image" => {
+            let path = directory.join(fields.string("file")?);
+            let texture = ImageTexture::load(&path)?;
+            files.push(path);
+            SceneTexture::Image(texture)
+        }
+        "noise" => SceneTexture::Noise(NoiseTexture::new(
+            fields
+                .optional_string("kind")
+                .map_or(Ok(NoiseKind::Noise), str::parse)?,
+            fields.optional_f64("scale")?.unwrap_or(1.0),
+            fields.optional_u64("seed")?.unwrap_or(0),
+        )),
+        _ => {
+            return Err(format!(
+                "unknown texture type '{kind}', expected solid, checker, image or noise"
+            ))
+        }
+    };
+    fields.finish()?;
+
+    Ok(texture)
+}
+
+fn parse_material(
+    kind: &str,
+    mut fields: Fields,
+    textures: &HashMap<String, SceneTexture>,
+) -> Result<SceneMaterial, String> {
+    let material = match kind {
+        "lambertian" => SceneMaterial::Lambertian(Lambertian::with_texture(
+            fields.texture("albedo", textures)?,
+        )),
+        "metal" => SceneMaterial::Metal(Metal::with_texture(
+            fields.texture("albedo", textures)?,
+            fields.optional_f64("fuzz")?.unwrap_or(0.0),
+        )),
+        "dielectric" => SceneMaterial::Dielectric(Dielectric::new(fields.f64("index")?)),
+        "diffuse_light" => SceneMaterial::DiffuseLight(DiffuseLight::with_texture(
+            fields.texture("emit", textures)?,
+        )),
+        "isotropic" => SceneMaterial::Isotropic(Isotropic::with_texture(
+            fields.texture("albedo", textures)?,
+        )),
+        _ => {
+            return Err(format!(
+                "unknown material type '{kind}', expected lambertian, metal, dielectric, diffuse_light or isotropic"
+            ))
+        }
+    };
+    fields.finish()?;
+
+    Ok(material)
+}
+
+// The key=value pairs of a directive, consumed as they are read so leftovers can be reported.
+struct Fields<'a> {
+    values: HashMap<&'a str, &'a str>,
+}
+
+impl<'a> Fields<'a> {
+    fn parse<I: Iterator<Item = &'a str>>(tokens: I) -> Result<Self, String> {
+        let mut values = HashMap::new();
+        for token in tokens {
+            let (name, value) = token
+                .split_once('=')
+                .ok_or_else(|| format!("expected name=value, got '{token}'"))?;
+            if values.insert(name, value).is_some() {
+                return Err(format!("field '{name}' is given more than once"));
+            }
+        }
+
+        Ok(Fields { values })
+    }
+
+    fn optional_string(&mut self, name: &str) -> Option<&'a str> {
+        self.values.remove(name)
+    }
+
+    fn string(&mut self, name: &str) -> Result<&'a str, String> {
+        self.optional_string(name)
+            .ok_or_else(|| format!("missing field '{name}'"))
+    }
+
+    fn optional_f64(&mut self, name: &str) -> Result<Option<f64>, String> {
+        match self.values.remove(name) {
+            None => Ok(None),
+            Some(value) => value.parse().map(Some).map_err(|_| {
+                format!("invalid value '{value}' for field '{name}': expected a number")
+            }),
+        }
+    }
+
+    fn optional_u64(&mut self, name: &str) -> Result<Option<u64>, String> {
+        match self.values.remove(name) {
+            None => Ok(None),
+            Some(value) => value.parse().map(Some).map_err(|_| {
+                format!("invalid value '{value}' for field '{name}': expected a positive integer")
+            }),
+        }
+    }
+
+    fn optional_bool(&mut self, name: &str) -> Result<Option<bool>, String> {
+        match self.values.remove(name) {
+            None => Ok(None),
+            Some(value) => value.parse().map(Some).map_err(|_| {
+                format!("invalid value '{value}' for field '{name}': expected true or false")
+            }),
+        }
+    }
+
+    fn f64(&mut self, name: &str) -> Result<f64, String> {
+        self.optional_f64(name)?
+            .ok_or_else(|| format!("missing field '{name}'"))
+    }
+
+    // Density of the smoke filling a shape, if any.
+    fn density(&mut self) -> Result<Option<f64>, String> {
+        match self.optional_f64("density")? {
+            Some(density) if !(density > 0.0 && density.is_finite()) => Err(format!(
+                "invalid density '{density}', expected a positive number"
+            )),
+            density => Ok(density),
+        }
+    }
+
+    fn optional_vec3(&mut self, name: &str) -> Result<Option<Vec3>, String> {
+        let value = match self.values.remove(name) {
+            None => return Ok(None),
+            Some(value) => value,
+        };
+        let coordinates = value
+            .split(',')
+            .map(|coordinate| coordinate.parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>();
+
+        match coordinates.as_deref() {
+            Ok([x, y, z]) => Ok(Some(Vec3::new(*x, *y, *z))),
+            _ => Err(format!(
+                "invalid value '{value}' for field '{name}': expected three comma separated numbers"
+            )),
+        }
+    }
+
+    fn vec3(&mut self, name: &str) -> Result<Vec3, String> {
+        self.optional_vec3(name)?
+            .ok_or_else(|| format!("missing field '{name}'"))
+    }
+
+    fn material(
+        &mut self,
+        materials: &HashMap<String, SceneMaterial>,
+    ) -> Result<SceneMaterial, String> {
+        let name = self
+            .values
+            .remove("material")
+            .ok_or_else(|| "missing field 'material'".to_string())?;
+        materials
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("unknown material '{name}'"))
+    }
+
+    // A constant color given in `color_field`, or a texture referenced by the 'texture' field.
+    fn texture(
+        &mut self,
+        color_field: &str,
+        textures: &HashMap<String, SceneTexture>,
+    ) -> Result<SceneTexture, String> {
+        match (
+            self.optional_vec3(color_field)?,
+            self.values.remove("texture"),
+        ) {
+            (Some(color), None) => Ok(SceneTexture::from(color)),
+            (None, Some(name)) => textures
+                .get(name)
+                .cloned()
+                .ok_or_else(|| format!("unknown texture '{name}'")),
+            (Some(_), Some(_)) => Err(format!(
+                "fields '{color_field}' and 'texture' cannot be used together"
+            )),
+            (None, None) => Err(format!("missing field '{color_field}'")),
+        }
+    }
+
+    fn finish(self) -> Result<(), String> {
+        let mut unknown: Vec<&str> = self.values.into_keys().collect();
+        unknown.sort_unstable();
+        match unknown.first() {
+            None => Ok(()),
+            Some(name) => Err(format!("unknown field '{name}'")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sampler::IndependentSampler;
+
+    fn parse_error(source: &str) -> String {
+        match parse_scene(source, Path::new("scenes")) {
+            Ok(_) => panic!("expected the scene to be rejected"),
+            Err(error) => error.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let scene = parse_scene(
+            "# A red ball on a large ground sphere
+            camera look_from=0,0,5 look_at=0,0,0 vfov=40 aperture=0.1
+
+            material ground lambertian albedo=0.5,0.5,0.5
+            material red lambertian albedo=1,0,0 # comment after a directive
+            sphere center=0,-100.5,0 radius=100 material=ground
+            moving_sphere center0=0,0,0 center1=0,1,0 time0=0 time1=1 radius=0.5 material=red",
+            Path::new("scenes"),
+        )
+        .unwrap();
+
+        assert_eq!(Point3::new(0.0, 0.0, 5.0), scene.camera.look_from);
+        assert_eq!(Vec3::new(0.0, 1.0, 0.0), scene.camera.view_up);
+        assert_eq!(40.0, scene.camera.vertical_field_of_view);
+        assert_eq!(0.1, scene.camera.aperture);
+        assert_eq!(5.0, scene.camera.focus_distance);
+        assert_eq!(Background::Sky, scene.background);
+
+        let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let hit = scene.world.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(4.5, hit.t);
+    }
+
+    #[test]
+    fn test_example_scene() {
+        for source in [
+            include_str!("../scenes/simple.scene"),
+            include_str!("../scenes/lamp.scene"),
+            include_str!("../scenes/cornell.scene"),
+            include_str!("../scenes/pyramid.scene"),
+            include_str!("../scenes/textures.scene"),
+        ] {
+            let scene = parse_scene(source, Path::new("scenes")).unwrap();
+            assert!(scene.world.bounding_box(0.0, 1.0).is_some());
+        }
+    }
+
+    #[test]
+    fn test_light() {
+        let scene = parse_scene(
+            "camera look_from=0,0,5 look_at=0,0,0 vfov=40
+            background black
+            material lamp diffuse_light emit=4,4,4
+            sphere center=0,0,0 radius=1 material=lamp",
+            Path::new("scenes"),
+        )
+        .unwrap();
+
+        assert_eq!(Background::Solid(Color::default()), scene.background);
+        let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let hit = scene.world.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(
+            Color::new(4.0, 4.0, 4.0),
+            hit.material.emitted(0.0, 0.0, &hit.point)
+        );
+    }
+
+    #[test]
+    fn test_shapes() {
+        let scene = parse_scene(
+            "camera look_from=0,0,5 look_at=0,0,0 vfov=40
+            material white lambertian albedo=1,1,1
+            xy_rect x0=-1 x1=1 y0=-1 y1=1 k=-3 material=white
+            xz_rect x0=-1 x1=1 z0=-1 z1=1 k=-3 material=white flip=true
+            yz_rect y0=-1 y1=1 z0=-1 z1=1 k=-3 material=white
+            box min=-0.5,-0.5,-0.5 max=0.5,0.5,0.5 material=white
+            triangle v0=-1,-1,1 v1=1,-1,1 v2=0,1,1 material=white
+            triangle v0=5,0,0 v1=6,0,0 v2=5,1,0 n0=0,0,1 n1=0,0,1 n2=0,0,1 material=white",
+            Path::new("scenes"),
+        )
+        .unwrap();
+
+        let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let hit = scene.world.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(4.0, hit.t); // triangle in front of the box
+
+        let expected = crate::aabb::Aabb::new(
+            Point3::new(-3.0001, -3.0001, -3.0001),
+            Point3::new(6.0001, 1.0001, 1.0001),
+        );
+        assert_eq!(Some(expected), scene.world.bounding_box(0.0, 1.0));
+    }
+
+    #[test]
+    fn test_media() {
+        let scene = parse_scene(
+            "camera look_from=0,0,5 look_at=0,0,0 vfov=40
+            material smoke isotropic albedo=0.5,0.5,0.5
+            sphere center=0,0,0 radius=1 material=smoke density=1000
+            box min=-1,-1,-10 max=1,1,-8 material=smoke density=1000",
+            Path::new("scenes"),
+        )
+        .unwrap();
+
+        // Dense smoke stops the rays right after they enter it.
+        for (x, t) in [(0.0, 4.0), (0.5, 5.0 - 0.75f64.sqrt())] {
+            let ray = Ray::new(Point3::new(x, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+            let hit = scene.world.hit(&ray, 0.001, f64::INFINITY).unwrap();
+            assert!(hit.is_in_medium());
+            assert!((hit.t - t).abs() < 0.05, "{}", hit.t);
+        }
+        let ray = Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let hit = scene.world.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert!(hit.is_in_medium() && (hit.t - 3.0).abs() < 0.05);
+
+        assert_eq!(
+            "line 2: invalid density '0', expected a positive number",
+            parse_error(
+                "material smoke isotropic albedo=1,1,1\n\
+                 sphere center=0,0,0 radius=1 material=smoke density=0"
+            )
+        );
+        assert_eq!(
+            "line 2: invalid density 'NaN', expected a positive number",
+            parse_error(
+                "material smoke isotropic albedo=1,1,1\n\
+                 sphere center=0,0,0 radius=1 material=smoke density=nan"
+            )
+        );
+    }
+
+    #[test]
+    fn test_textures() {
+        let scene = parse_scene(
+            "camera look_from=0,0,5 look_at=0,0,0 vfov=40
+            texture tiles checker even=1,1,1 odd=0,0,0 size=2
+            texture bricks image file=bricks.png
+            material floor lambertian texture=tiles
+            material wall metal texture=bricks fuzz=0.5
+            sphere center=0,0,0 radius=1 material=floor
+            sphere center=0,0,-10 radius=1 material=wall",
+            Path::new("scenes"),
+        )
+        .unwrap();
+        assert_eq!(vec![Path::new("scenes").join("bricks.png")], scene.files);
+
+        let ray = Ray::new(Point3::new(0.5, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let hit = scene.world.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        let attenuation = hit
+            .material
+            .scatter(&ray, &hit, &mut IndependentSampler::new(0))
+            .unwrap()
+            .attenuation;
+        assert_eq!(Color::new(1.0, 1.0, 1.0), attenuation);
+    }
+
+    #[test]
+    fn test_mesh() {
+        let scene = parse_scene(
+            "camera look_from=0,0,5 look_at=0,0,0 vfov=40
+            material white lambertian albedo=1,1,1
+            mesh file=pyramid.obj material=white scale=2 translate=0,-1,0",
+            Path::new("scenes"),
+        )
+        .unwrap();
+
+        assert_eq!(vec![Path::new("scenes").join("pyramid.obj")], scene.files);
+        let scenes = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenes");
+        let pyramids = load_scene(scenes.join("pyramid.scene")).unwrap();
+        assert_eq!(
+            vec![
+                scenes.join("pyramid.obj"),
+                scenes.join("pyramid.mtl"),
+                scenes.join("pyramid.obj")
+            ],
+            pyramids.files
+        );
+
+        // The pyramid's base spans [-1, 1] on x and z, scaled and moved down.
+        let expected_min = Point3::new(-2.0, -1.0, -2.0);
+        let expected_max = Point3::new(2.0, 1.0, 2.0);
+        let bounding_box = scene.world.bounding_box(0.0, 1.0).unwrap();
+        assert!((bounding_box.min() - expected_min).length() < 0.001);
+        assert!((bounding_box.max() - expected_max).length() < 0.001);
+
+        let ray = Ray::new(Point3::new(0.0, -5.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 0.0);
+        let hit = scene.world.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(4.0, hit.t);
+        assert_eq!(Vec3::new(0.0, -1.0, 0.0), hit.normal);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            "line 3: unknown material 'gold'",
+            parse_error(
+                "camera look_from=0,0,5 look_at=0,0,0 vfov=40\n\
+                 material red lambertian albedo=1,0,0\n\
+                 sphere center=0,0,0 radius=1 material=gold"
+            )
+        );
+        assert_eq!(
+            "line 1: invalid value 'far' for field 'radius': expected a number",
+            parse_error("sphere center=0,0,0 radius=far material=red")
+        );
+        assert_eq!(
+            "line 1: missing field 'vfov'",
+            parse_error("camera look_from=0,0,5 look_at=0,0,0")
+        );
+        assert_eq!(
+            "line 1: unknown field 'colour'",
+            parse_error("material red lambertian albedo=1,0,0 colour=red")
+        );
+        assert_eq!(
+            "line 1: unknown material type 'glass', expected lambertian, metal, dielectric, diffuse_light or isotropic",
+            parse_error("material red glass")
+        );
+        assert_eq!("line 2: unknown directive 'cube'", parse_error("\ncube"));
+        assert_eq!(
+            "line 1: invalid background 'blue', expected sky, black or R,G,B",
+            parse_error("background blue")
+        );
+        assert_eq!(
+            "line 2: fields 'n0', 'n1' and 'n2' must be given together",
+            parse_error(
+                "material w lambertian albedo=1,1,1\n\
+                 triangle v0=0,0,0 v1=1,0,0 v2=0,1,0 n0=0,0,1 material=w"
+            )
+        );
+        assert_eq!(
+            "line 1: unknown texture 'wood'",
+            parse_error("material floor lambertian texture=wood")
+        );
+        assert_eq!(
+            "line 2: fields 'albedo' and 'texture' cannot be used together",
+            parse_error(
+                "texture grey solid color=0.5,0.5,0.5\n\
+                 material floor lambertian albedo=1,1,1 texture=grey"
+            )
+        );
+        assert_eq!(
+            "line 1: unknown noise 'wood', expected noise, turbulence or marble",
+            parse_error("texture grain noise kind=wood")
+        );
+        assert_eq!(
+            "line 1: unknown texture type 'wood', expected solid, checker, image or noise",
+            parse_error("texture grain wood")
+        );
+        assert!(parse_error("texture photo image file=missing.png")
+            .starts_with("line 1: unable to load 'scenes/missing.png'"));
+        assert!(parse_error("mesh file=missing.obj")
+            .starts_with("line 1: unable to read 'scenes/missing.obj'"));
+        assert_eq!(
+            "line 1: field 'scale' must be positive",
+            parse_error("mesh file=pyramid.obj scale=0")
+        );
+        assert_eq!(
+            "line 1: missing camera directive",
+            parse_error("material red lambertian albedo=1,0,0")
+        );
+    }
+}
@@ -0,0 +1,259 @@
+use std::str::FromStr;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::aarect::{XYRect, XZRect, YZRect};
+use crate::background::Background;
+use crate::box_shape::BoxShape;
+use crate::camera::CameraSettings;
+use crate::dielectric::Dielectric;
+use crate::diffuse_light::DiffuseLight;
+use crate::flip_face::FlipFace;
+use crate::hittable_list::HittableList;
+use crate::lambertian::Lambertian;
+use crate::metal::Metal;
+use crate::moving_sphere::MovingSphere;
+use crate::scene_file::Scene;
+use crate::sphere::Sphere;
+use crate::utils::{random_f64, random_f64_range};
+use crate::{Color, Point3, Vec3};
+
+// Scenes built into the renderer, the ones of the book.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SceneName {
+    Random,
+    Simple,
+    Cornell,
+}
+
+impl FromStr for SceneName {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "random" => Ok(SceneName::Random),
+            "simple" => Ok(SceneName::Simple),
+            "cornell" => Ok(SceneName::Cornell),
+            _ => Err(format!(
+                "unknown scene '{name}', expected random, simple or cornell"
+            )),
+        }
+    }
+}
+
+// Objects, camera and background of a built-in scene, `seed` placing the random spheres.
+pub fn builtin_scene(name: SceneName, seed: u64) -> Scene {
+    let lights = match name {
+        SceneName::Cornell => cornell_lights(),
+        _ => HittableList::new(),
+    };
+    let (world, background) = match name {
+        SceneName::Random => (
+            random_scene(&mut StdRng::seed_from_u64(seed)),
+            Background::Sky,
+        ),
+        SceneName::Simple => (simple_scene(), Background::Sky),
+        SceneName::Cornell => (cornell_box(), Background::Solid(Color::default())),
+    };
+
+    Scene {
+        world,
+        lights,
+        camera: scene_camera(name),
+        background,
+        files: Vec::new(),
+    }
+}
+
+pub fn simple_scene() -> HittableList {
+    let mut world_mut = HittableList::new();
+
+    let ground = Lambertian::new(&Color::new(0.8, 0.8, 0.0));
+    let center = Lambertian::new(&Color::new(0.1, 0.2, 0.5));
+    let left = Dielectric::new(1.5);
+    let right = Metal::new(&Color::new(0.8, 0.6, 0.2), 0.0);
+
+    world_mut.add(Box::new(Sphere::new(
+        Point3::new(0.0, -100.5, -1.0),
+        100.0,
+        ground,
+    )));
+    world_mut.add(Box::new(Sphere::new(
+        Point3::new(0.0, 0.0, -1.0),
+        0.5,
+        center,
+    )));
+    world_mut.add(Box::new(Sphere::new(
+        Point3::new(-1.0, 0.0, -1.0),
+        0.5,
+        left,
+    )));
+    world_mut.add(Box::new(Sphere::new(
+        Point3::new(-1.0, 0.0, -1.0),
+        -0.45,
+        left,
+    )));
+    world_mut.add(Box::new(Sphere::new(
+        Point3::new(1.0, 0.0, -1.0),
+        0.5,
+        right,
+    )));
+
+    world_mut
+}
+
+pub fn random_scene(rng: &mut StdRng) -> HittableList {
+    let mut world = HittableList::new();
+
+    let ground_material = Lambertian::new(&Color::new(0.5, 0.5, 0.5));
+    world.add(Box::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        ground_material,
+    )));
+
+    for a in -11..11 {
+        for b in -11..11 {
+            let center = Point3::new(
+                a as f64 + 0.9 * random_f64(rng),
+                0.2,
+                b as f64 + 0.9 * random_f64(rng),
+            );
+
+            let random = random_f64(rng);
+            if random < 0.8 {
+                // diffuse
+                let albedo = Color::random(rng) * Color::random(rng);
+                let center2 = center + Vec3::new(0.0, random_f64_range(rng, 0.0, 0.5), 0.0);
+                world.add(Box::new(MovingSphere::new(
+                    center,
+                    center2,
+                    0.0,
+                    1.0,
+                    0.2,
+                    Lambertian::new(&albedo),
+                )));
+            } else if random < 0.95 {
+                // metal
+                world.add(Box::new(Sphere::new(
+                    center,
+                    0.2,
+                    Metal::new(&Color::random(rng), random_f64_range(rng, 0.0, 0.5)),
+                )));
+            } else {
+                // glass
+                world.add(Box::new(Sphere::new(center, 0.2, Dielectric::new(1.5))));
+            }
+        }
+    }
+
+    world.add(Box::new(Sphere::new(
+        Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        Dielectric::new(1.5),
+    )));
+
+    world.add(Box::new(Sphere::new(
+        Point3::new(-4.0, 1.0, 0.0),
+        1.0,
+        Lambertian::new(&Color::new(0.4, 0.2, 0.1)),
+    )));
+
+    world.add(Box::new(Sphere::new(
+        Point3::new(4.0, 1.0, 0.0),
+        1.0,
+        Metal::new(&Color::new(0.7, 0.6, 0.5), 0.0),
+    )));
+
+    world
+}
+
+pub fn cornell_box() -> HittableList {
+    let mut world = HittableList::new();
+
+    let red = Lambertian::new(&Color::new(0.65, 0.05, 0.05));
+    let white = Lambertian::new(&Color::new(0.73, 0.73, 0.73));
+    let green = Lambertian::new(&Color::new(0.12, 0.45, 0.15));
+    let light = DiffuseLight::new(&Color::new(15.0, 15.0, 15.0));
+
+    world.add(Box::new(YZRect::new(0.0, 555.0, 0.0, 555.0, 555.0, green)));
+    world.add(Box::new(YZRect::new(0.0, 555.0, 0.0, 555.0, 0.0, red)));
+    world.add(Box::new(FlipFace::new(XZRect::new(
+        213.0, 343.0, 227.0, 332.0, 554.0, light,
+    ))));
+    world.add(Box::new(XZRect::new(0.0, 555.0, 0.0, 555.0, 0.0, white)));
+    world.add(Box::new(XZRect::new(0.0, 555.0, 0.0, 555.0, 555.0, white)));
+    world.add(Box::new(XYRect::new(0.0, 555.0, 0.0, 555.0, 555.0, white)));
+
+    world.add(Box::new(BoxShape::new(
+        Point3::new(130.0, 0.0, 65.0),
+        Point3::new(295.0, 165.0, 230.0),
+        white,
+    )));
+    world.add(Box::new(BoxShape::new(
+        Point3::new(265.0, 0.0, 295.0),
+        Point3::new(430.0, 330.0, 460.0),
+        white,
+    )));
+
+    world
+}
+
+// The light of the Cornell box, for the renderer to sample.
+pub fn cornell_lights() -> HittableList {
+    let mut lights = HittableList::new();
+    let light = DiffuseLight::new(&Color::new(15.0, 15.0, 15.0));
+    lights.add(Box::new(XZRect::new(
+        213.0, 343.0, 227.0, 332.0, 554.0, light,
+    )));
+    lights
+}
+
+fn scene_camera(scene: SceneName) -> CameraSettings {
+    match scene {
+        SceneName::Random => CameraSettings {
+            look_from: Point3::new(13.0, 2.0, 3.0),
+            look_at: Point3::new(0.0, 0.0, 0.0),
+            view_up: Vec3::new(0.0, 1.0, 0.0),
+            vertical_field_of_view: 20.0,
+            aperture: 0.1,
+            focus_distance: 10.0,
+            shutter_open_time: 0.0,
+            shutter_close_time: 1.0,
+        },
+        SceneName::Simple => CameraSettings {
+            look_from: Point3::new(-2.0, 2.0, 1.0),
+            look_at: Point3::new(0.0, 0.0, -1.0),
+            view_up: Vec3::new(0.0, 1.0, 0.0),
+            vertical_field_of_view: 20.0,
+            aperture: 0.0,
+            focus_distance: 3.0_f64.sqrt(),
+            shutter_open_time: 0.0,
+            shutter_close_time: 1.0,
+        },
+        SceneName::Cornell => CameraSettings {
+            look_from: Point3::new(278.0, 278.0, -800.0),
+            look_at: Point3::new(278.0, 278.0, 0.0),
+            view_up: Vec3::new(0.0, 1.0, 0.0),
+            vertical_field_of_view: 40.0,
+            aperture: 0.0,
+            focus_distance: 10.0,
+            shutter_open_time: 0.0,
+            shutter_close_time: 1.0,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_scenes() {
+        for name in [SceneName::Random, SceneName::Simple, SceneName::Cornell] {
+            assert!(!builtin_scene(name, 1).world.is_empty());
+        }
+        assert_eq!(Ok(SceneName::Cornell), "cornell".parse());
+    }
+}
@@ -1,5 +1,8 @@
+use crate::aabb::Aabb;
 use crate::hittable::{HitRecord, Hittable};
 use crate::material::Material;
+use crate::onb::Onb;
+use crate::sampler::{sample_unit_vector, Sampler};
 use crate::{dot, Point3, Ray, Vec3};
 
 pub struct Sphere<Mat: Material> {
@@ -16,10 +19,26 @@ impl<Mat: Material> Sphere<Mat> {
             material,
         }
     }
+
+    fn area(&self) -> f64 {
+        4.0 * std::f64::consts::PI * self.radius * self.radius
+    }
+}
+
+// Coordinates of a point of the unit sphere, u being the angle around the Y axis starting from -X
+// and v the angle from -Y to +Y, both scaled to [0, 1].
+pub fn sphere_uv(point: &Point3) -> (f64, f64) {
+    let theta = (-point.y()).clamp(-1.0, 1.0).acos();
+    let phi = (-point.z()).atan2(point.x()) + std::f64::consts::PI;
+
+    (
+        phi / (2.0 * std::f64::consts::PI),
+        theta / std::f64::consts::PI,
+    )
 }
 
 impl<Mat: Material> Hittable for Sphere<Mat> {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
         let oc = ray.origin() - self.center;
         let a = ray.direction().length_squared();
         let half_b = dot(&oc, &ray.direction());
@@ -39,19 +58,85 @@ impl<Mat: Material> Hittable for Sphere<Mat> {
             }
         }
 
+        let point = ray.at(root);
+        let outward_normal = (point - self.center) / self.radius;
+        let (u, v) = sphere_uv(&outward_normal);
         let mut hit = HitRecord {
-            point: ray.at(root),
+            point,
             normal: Vec3::default(),
             t: root,
+            u,
+            v,
             front_face: false,
             material: &self.material,
         };
-
-        let outward_normal = (hit.point - self.center) / self.radius;
         hit.set_face_normal(ray, &outward_normal);
 
         Some(hit)
     }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<Aabb> {
+        // A negative radius is used for hollow spheres, the box must still enclose them.
+        let radius = self.radius.abs();
+        let extent = Vec3::new(radius, radius, radius);
+        Some(Aabb::new(self.center - extent, self.center + extent))
+    }
+
+    // Directions towards the sphere are chosen uniformly in the cone it fills, or over every
+    // direction from inside it.
+    fn pdf_value(&self, origin: &Point3, direction: &Vec3) -> f64 {
+        let ray = Ray::new(*origin, *direction, 0.0);
+        if self.hit(&ray, 0.001, f64::INFINITY).is_none() {
+            return 0.0;
+        }
+
+        let distance_squared = (self.center - *origin).length_squared();
+        let radius_squared = self.radius * self.radius;
+        if distance_squared <= radius_squared {
+            return 1.0 / (4.0 * std::f64::consts::PI);
+        }
+        let cos_theta_max = (1.0 - radius_squared / distance_squared).sqrt();
+        1.0 / (2.0 * std::f64::consts::PI * (1.0 - cos_theta_max))
+    }
+
+    fn random(&self, origin: &Point3, sampler: &mut dyn Sampler) -> Vec3 {
+        let direction = self.center - *origin;
+        let distance_squared = direction.length_squared();
+        let radius_squared = self.radius * self.radius;
+        if distance_squared <= radius_squared {
+            return sample_unit_vector(sampler.next_2d());
+        }
+
+        let (a, b) = sampler.next_2d();
+        let cos_theta_max = (1.0 - radius_squared / distance_squared).sqrt();
+        let z = 1.0 + a * (cos_theta_max - 1.0);
+        let sin_theta = (1.0 - z * z).max(0.0).sqrt();
+        let phi = 2.0 * std::f64::consts::PI * b;
+        Onb::from_w(&direction).local(&Vec3::new(phi.cos() * sin_theta, phi.sin() * sin_theta, z))
+    }
+
+    fn sample_surface(&self, sampler: &mut dyn Sampler) -> Option<(HitRecord<'_>, f64)> {
+        let direction = sample_unit_vector(sampler.next_2d());
+        let (u, v) = sphere_uv(&direction);
+        let hit = HitRecord {
+            point: self.center + self.radius * direction,
+            normal: direction * self.radius.signum(),
+            t: 0.0,
+            u,
+            v,
+            front_face: true,
+            material: &self.material,
+        };
+        Some((hit, 1.0 / self.area()))
+    }
+
+    fn surface_pdf(&self, point: &Point3) -> f64 {
+        let radius = self.radius.abs();
+        if ((*point - self.center).length() - radius).abs() > 1e-6 * (1.0 + radius) {
+            return 0.0;
+        }
+        1.0 / self.area()
+    }
 }
 
 impl<Mat: Material> PartialEq<Self> for Sphere<Mat> {
@@ -65,8 +150,43 @@ impl<Mat: Material> Eq for Sphere<Mat> {}
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::hittable::pdf_integral;
+    use crate::sampler::IndependentSampler;
     use crate::{Color, Lambertian};
 
+    #[test]
+    fn test_pdf() {
+        let sphere = Sphere::new(
+            Point3::new(0.0, 0.0, -3.0),
+            1.0,
+            Lambertian::new(&Color::new(1.0, 1.0, 1.0)),
+        );
+        let origin = Point3::default();
+
+        assert!((pdf_integral(&sphere, &origin) - 1.0).abs() < 0.02);
+        assert!((pdf_integral(&sphere, &Point3::new(0.0, 0.5, -3.0)) - 1.0).abs() < 1e-9);
+        assert_eq!(0.0, sphere.pdf_value(&origin, &Vec3::new(0.0, 0.0, 1.0)));
+
+        let mut sampler = IndependentSampler::new(2);
+        for index in 0..100 {
+            sampler.start_sample(0, index);
+            let direction = sphere.random(&origin, &mut sampler);
+            assert!(sphere.pdf_value(&origin, &direction) > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_uv() {
+        let close = |(u, v): (f64, f64), expected: (f64, f64)| {
+            (u - expected.0).abs() < 1e-12 && (v - expected.1).abs() < 1e-12
+        };
+
+        assert!(close(sphere_uv(&Point3::new(1.0, 0.0, 0.0)), (0.5, 0.5)));
+        assert!(close(sphere_uv(&Point3::new(0.0, 1.0, 0.0)), (0.5, 1.0)));
+        assert!(close(sphere_uv(&Point3::new(0.0, 0.0, 1.0)), (0.25, 0.5)));
+        assert!(close(sphere_uv(&Point3::new(0.0, -1.0, 0.0)), (0.5, 0.0)));
+    }
+
     #[test]
     fn new_test() {
         let lambertian_white = Lambertian::new(&Color::new(1.0, 1.0, 1.0));
@@ -83,7 +203,7 @@ mod tests {
         let sphere = Sphere::new(Point3::default(), 1.0, lambertian_white);
 
         let hit_record = sphere.hit(
-            &Ray::new_old(Point3::new(0.0, 0.0, 2.0), Vec3::new(1.0, 0.0, 0.0)),
+            &Ray::new(Point3::new(0.0, 0.0, 2.0), Vec3::new(1.0, 0.0, 0.0), 0.0),
             0.1,
             f64::INFINITY,
         );
@@ -97,17 +217,26 @@ mod tests {
         let sphere = Sphere::new(Point3::default(), 1.0, lambertian_white);
 
         match sphere.hit(
-            &Ray::new_old(Point3::new(0.0, 0.0, 2.0), Vec3::new(0.0, 0.0, -1.0)),
+            &Ray::new(Point3::new(0.0, 0.0, 2.0), Vec3::new(0.0, 0.0, -1.0), 0.0),
             0.1,
             f64::INFINITY,
         ) {
-            None => assert!(false),
+            None => panic!("the sphere should be hit"),
             Some(hit_record) => {
                 assert_eq!(1.0, hit_record.t); // Sphere is hit in one "step"
                 assert_eq!(Vec3::new(0.0, 0.0, 1.0), hit_record.normal); // -direction of the ray
                 assert_eq!(Vec3::new(0.0, 0.0, 1.0), hit_record.point); // position of the hit
-                assert_eq!(true, hit_record.front_face); // hit from the exterior
+                assert!(hit_record.front_face); // hit from the exterior
             }
         }
     }
+
+    #[test]
+    fn test_bounding_box() {
+        let lambertian_white = Lambertian::new(&Color::new(1.0, 1.0, 1.0));
+        let sphere = Sphere::new(Point3::new(1.0, 2.0, 3.0), -0.5, lambertian_white);
+
+        let expected = Aabb::new(Point3::new(0.5, 1.5, 2.5), Point3::new(1.5, 2.5, 3.5));
+        assert_eq!(Some(expected), sphere.bounding_box(0.0, 1.0));
+    }
 }
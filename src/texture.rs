@@ -0,0 +1,147 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::image::Image;
+use crate::tone_mapping::TransferFunction;
+use crate::utils::clamp;
+use crate::{Color, Point3};
+
+// Color varying over a surface, looked up with the surface coordinates or the hit point.
+pub trait Texture: Sync {
+    fn value(&self, u: f64, v: f64, point: &Point3) -> Color;
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct SolidColor {
+    color: Color,
+}
+
+impl SolidColor {
+    pub fn new(color: Color) -> Self {
+        SolidColor { color }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f64, _v: f64, _point: &Point3) -> Color {
+        self.color
+    }
+}
+
+// Alternate between two textures in a 3D grid of cubes, which does not depend on the surface
+// coordinates so it works on every shape.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CheckerTexture<T: Texture = SolidColor> {
+    even: T,
+    odd: T,
+    size: f64,
+}
+
+impl<T: Texture> CheckerTexture<T> {
+    pub fn new(even: T, odd: T, size: f64) -> Self {
+        CheckerTexture { even, odd, size }
+    }
+}
+
+impl<T: Texture> Texture for CheckerTexture<T> {
+    fn value(&self, u: f64, v: f64, point: &Point3) -> Color {
+        let cell = (point.x() / self.size).floor()
+            + (point.y() / self.size).floor()
+            + (point.z() / self.size).floor();
+
+        if cell.rem_euclid(2.0) == 0.0 {
+            self.even.value(u, v, point)
+        } else {
+            self.odd.value(u, v, point)
+        }
+    }
+}
+
+// Texture read from an 8-bit sRGB image, stretched over the [0, 1] surface coordinates with v
+// going up. Pixels are shared, so copies are cheap.
+#[derive(Debug, Clone)]
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    pixels: Arc<[Color]>,
+}
+
+impl ImageTexture {
+    pub fn new(image: &Image) -> Self {
+        let decode = |value: u8| TransferFunction::Srgb.decode(value as f64 / 255.0);
+        let pixels = image
+            .pixels()
+            .chunks(3)
+            .map(|rgb| Color::new(decode(rgb[0]), decode(rgb[1]), decode(rgb[2])))
+            .collect();
+
+        ImageTexture {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            pixels,
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let image = Image::load(path.as_ref())
+            .map_err(|error| format!("unable to load '{}': {error}", path.as_ref().display()))?;
+        Ok(ImageTexture::new(&image))
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _point: &Point3) -> Color {
+        if self.pixels.is_empty() {
+            return Color::default();
+        }
+
+        let u = clamp(u, 0.0, 1.0);
+        let v = 1.0 - clamp(v, 0.0, 1.0);
+        let i = ((u * self.width as f64) as usize).min(self.width - 1);
+        let j = ((v * self.height as f64) as usize).min(self.height - 1);
+
+        self.pixels[j * self.width + i]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checker() {
+        let white = SolidColor::new(Color::new(1.0, 1.0, 1.0));
+        let black = SolidColor::new(Color::default());
+        let checker = CheckerTexture::new(white, black, 2.0);
+
+        assert_eq!(
+            Color::new(1.0, 1.0, 1.0),
+            checker.value(0.0, 0.0, &Point3::new(0.5, 0.5, 0.5))
+        );
+        assert_eq!(
+            Color::default(),
+            checker.value(0.0, 0.0, &Point3::new(2.5, 0.5, 0.5))
+        );
+        assert_eq!(
+            Color::new(1.0, 1.0, 1.0),
+            checker.value(0.0, 0.0, &Point3::new(-0.5, -0.5, 0.5))
+        );
+    }
+
+    #[test]
+    fn test_image() {
+        // Red and green on the top row, blue and white on the bottom one.
+        let image = Image::new(2, 2, vec![255, 0, 0, 0, 255, 0, 0, 0, 255, 255, 255, 255]);
+        let texture = ImageTexture::new(&image);
+        let origin = Point3::default();
+
+        assert_eq!(Color::new(1.0, 0.0, 0.0), texture.value(0.1, 0.9, &origin));
+        assert_eq!(Color::new(0.0, 1.0, 0.0), texture.value(0.9, 1.0, &origin));
+        assert_eq!(Color::new(0.0, 0.0, 1.0), texture.value(0.0, 0.0, &origin));
+        assert_eq!(Color::new(1.0, 1.0, 1.0), texture.value(2.0, -1.0, &origin));
+
+        // Mid grey in sRGB is about 0.22 in linear.
+        let grey = ImageTexture::new(&Image::new(1, 1, vec![128, 128, 128]));
+        assert!((grey.value(0.5, 0.5, &origin).x() - 0.2158).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,233 @@
+use std::str::FromStr;
+
+use crate::utils::clamp;
+use crate::Color;
+
+// Compress linear radiance into the [0, 1] display range.
+pub trait ToneMapper: Sync {
+    fn map(&self, color: Color) -> Color;
+}
+
+// Keep the radiance as is, values above 1.0 are clamped when quantized.
+pub struct LinearClamp;
+
+impl ToneMapper for LinearClamp {
+    fn map(&self, color: Color) -> Color {
+        color
+    }
+}
+
+pub struct Reinhard;
+
+impl ToneMapper for Reinhard {
+    fn map(&self, color: Color) -> Color {
+        let map_channel = |value: f64| value / (1.0 + value);
+        Color::new(
+            map_channel(color.x()),
+            map_channel(color.y()),
+            map_channel(color.z()),
+        )
+    }
+}
+
+// Krzysztof Narkowicz's fit of the ACES filmic curve.
+pub struct AcesFilmic;
+
+impl ToneMapper for AcesFilmic {
+    fn map(&self, color: Color) -> Color {
+        let map_channel = |value: f64| {
+            let result = (value * (2.51 * value + 0.03)) / (value * (2.43 * value + 0.59) + 0.14);
+            clamp(result, 0.0, 1.0)
+        };
+        Color::new(
+            map_channel(color.x()),
+            map_channel(color.y()),
+            map_channel(color.z()),
+        )
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ToneMapperKind {
+    Linear,
+    Reinhard,
+    Aces,
+}
+
+impl ToneMapperKind {
+    pub fn tone_mapper(&self) -> Box<dyn ToneMapper> {
+        match self {
+            ToneMapperKind::Linear => Box::new(LinearClamp),
+            ToneMapperKind::Reinhard => Box::new(Reinhard),
+            ToneMapperKind::Aces => Box::new(AcesFilmic),
+        }
+    }
+}
+
+impl FromStr for ToneMapperKind {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "linear" => Ok(ToneMapperKind::Linear),
+            "reinhard" => Ok(ToneMapperKind::Reinhard),
+            "aces" => Ok(ToneMapperKind::Aces),
+            _ => Err(format!(
+                "unknown tone mapper '{name}', expected linear, reinhard or aces"
+            )),
+        }
+    }
+}
+
+// Encoding from linear values to the non-linear values stored in 8-bit images.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TransferFunction {
+    Srgb,
+    Gamma(f64),
+}
+
+impl TransferFunction {
+    pub fn encode(&self, value: f64) -> f64 {
+        match self {
+            TransferFunction::Srgb => {
+                if value <= 0.0031308 {
+                    12.92 * value
+                } else {
+                    1.055 * value.powf(1.0 / 2.4) - 0.055
+                }
+            }
+            TransferFunction::Gamma(gamma) => value.powf(1.0 / gamma),
+        }
+    }
+
+    // Inverse of `encode`, turning stored values back into linear ones.
+    pub fn decode(&self, value: f64) -> f64 {
+        match self {
+            TransferFunction::Srgb => {
+                if value <= 0.04045 {
+                    value / 12.92
+                } else {
+                    ((value + 0.055) / 1.055).powf(2.4)
+                }
+            }
+            TransferFunction::Gamma(gamma) => value.powf(*gamma),
+        }
+    }
+}
+
+impl FromStr for TransferFunction {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if value == "srgb" {
+            return Ok(TransferFunction::Srgb);
+        }
+
+        match value.parse::<f64>() {
+            Ok(gamma) if gamma > 0.0 => Ok(TransferFunction::Gamma(gamma)),
+            _ => Err(format!(
+                "invalid gamma '{value}', expected srgb or a positive number"
+            )),
+        }
+    }
+}
+
+// Everything turning the radiance of a pixel into the bytes of a displayable image.
+pub struct DisplayTransform {
+    // Exposure in stops, each one doubling the radiance.
+    pub exposure: f64,
+    pub tone_mapper: Box<dyn ToneMapper>,
+    pub transfer_function: TransferFunction,
+}
+
+impl DisplayTransform {
+    pub fn new(
+        exposure: f64,
+        tone_mapper: Box<dyn ToneMapper>,
+        transfer_function: TransferFunction,
+    ) -> Self {
+        DisplayTransform {
+            exposure,
+            tone_mapper,
+            transfer_function,
+        }
+    }
+
+    pub fn to_rgb(&self, color: Color) -> [u8; 3] {
+        let mapped = self.tone_mapper.map(2.0_f64.powf(self.exposure) * color);
+        let to_byte = |value: f64| {
+            let encoded = self.transfer_function.encode(clamp(value, 0.0, 1.0));
+            (256.0 * clamp(encoded, 0.0, 0.999)) as u8
+        };
+
+        [
+            to_byte(mapped.x()),
+            to_byte(mapped.y()),
+            to_byte(mapped.z()),
+        ]
+    }
+}
+
+impl Default for DisplayTransform {
+    fn default() -> Self {
+        DisplayTransform::new(0.0, Box::new(LinearClamp), TransferFunction::Srgb)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gamma() {
+        let display =
+            DisplayTransform::new(0.0, Box::new(LinearClamp), TransferFunction::Gamma(2.0));
+
+        assert_eq!([255, 0, 181], display.to_rgb(Color::new(1.0, 0.0, 0.5))); // 0.5^1/2 ~= 0.71, 0.71 * 256 ~= 181
+        assert_eq!(
+            [255, 0, 0],
+            display.to_rgb(Color::new(10.0, -1.0, f64::NAN))
+        );
+    }
+
+    #[test]
+    fn test_srgb() {
+        let srgb = TransferFunction::Srgb;
+
+        assert_eq!(0.0, srgb.encode(0.0));
+        assert_eq!(12.92 * 0.001, srgb.encode(0.001));
+        assert!((srgb.encode(1.0) - 1.0).abs() < 1e-12);
+        assert!((srgb.encode(0.5) - 0.7354).abs() < 1e-4);
+        for value in [0.0, 0.002, 0.2, 0.5, 1.0] {
+            assert!((srgb.decode(srgb.encode(value)) - value).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_exposure() {
+        let display =
+            DisplayTransform::new(1.0, Box::new(LinearClamp), TransferFunction::Gamma(1.0));
+
+        assert_eq!([128, 255, 0], display.to_rgb(Color::new(0.25, 0.5, 0.0)));
+    }
+
+    #[test]
+    fn test_tone_mappers() {
+        let bright = Color::new(1.0, 3.0, 1000.0);
+
+        assert_eq!(Color::new(0.5, 0.75, 1000.0 / 1001.0), Reinhard.map(bright));
+        let aces = AcesFilmic.map(bright);
+        assert!(aces.x() > 0.7 && aces.x() < aces.y() && aces.z() == 1.0);
+        assert_eq!(Color::default(), AcesFilmic.map(Color::default()));
+        assert_eq!(bright, LinearClamp.map(bright));
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Ok(ToneMapperKind::Aces), "aces".parse());
+        assert!("filmic".parse::<ToneMapperKind>().is_err());
+        assert_eq!(Ok(TransferFunction::Srgb), "srgb".parse());
+        assert_eq!(Ok(TransferFunction::Gamma(2.2)), "2.2".parse());
+        assert!("0".parse::<TransferFunction>().is_err());
+    }
+}
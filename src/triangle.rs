@@ -0,0 +1,244 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::vec3::cross;
+use crate::{dot, Point3, Ray, Vec3};
+
+// Triangles parallel to an axis have a flat box, it is padded like the rectangles' ones.
+const PADDING: f64 = 0.0001;
+
+pub struct Triangle<Mat: Material> {
+    vertices: [Point3; 3],
+    // Per vertex normals, interpolated over the surface for smooth shading.
+    normals: Option<[Vec3; 3]>,
+    material: Mat,
+}
+
+impl<Mat: Material> Triangle<Mat> {
+    pub fn new(v0: Point3, v1: Point3, v2: Point3, material: Mat) -> Self {
+        Triangle {
+            vertices: [v0, v1, v2],
+            normals: None,
+            material,
+        }
+    }
+
+    pub fn with_normals(
+        v0: Point3,
+        v1: Point3,
+        v2: Point3,
+        normals: [Vec3; 3],
+        material: Mat,
+    ) -> Self {
+        Triangle {
+            vertices: [v0, v1, v2],
+            normals: Some(normals),
+            material,
+        }
+    }
+}
+
+// Möller–Trumbore intersection, returning the distance and the barycentric coordinates (u, v) of
+// the hit point, relative to the second and third vertex.
+pub fn intersect_triangle(
+    ray: &Ray,
+    vertices: &[Point3; 3],
+    t_min: f64,
+    t_max: f64,
+) -> Option<(f64, f64, f64)> {
+    let epsilon = 1e-12;
+    let edge1 = vertices[1] - vertices[0];
+    let edge2 = vertices[2] - vertices[0];
+
+    let p = cross(&ray.direction(), &edge2);
+    let determinant = dot(&edge1, &p);
+    // The ray is parallel to the triangle, or the triangle is degenerate.
+    if determinant.abs() < epsilon * edge1.length() * edge2.length() * ray.direction().length() {
+        return None;
+    }
+    let inverse_determinant = 1.0 / determinant;
+
+    let s = ray.origin() - vertices[0];
+    let u = dot(&s, &p) * inverse_determinant;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let q = cross(&s, &edge1);
+    let v = dot(&ray.direction(), &q) * inverse_determinant;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let t = dot(&edge2, &q) * inverse_determinant;
+    if t < t_min || t > t_max {
+        return None;
+    }
+
+    Some((t, u, v))
+}
+
+// Build the hit record of an intersection found by `intersect_triangle`. Without texture
+// coordinates, the barycentric coordinates are used as surface coordinates.
+pub fn triangle_hit<'a>(
+    ray: &Ray,
+    vertices: &[Point3; 3],
+    normals: Option<[Vec3; 3]>,
+    texture_coordinates: Option<[(f64, f64); 3]>,
+    (t, u, v): (f64, f64, f64),
+    material: &'a dyn Material,
+) -> HitRecord<'a> {
+    let [v0, v1, v2] = *vertices;
+    let geometric_normal = cross(&(v1 - v0), &(v2 - v0)).unit_vector();
+    let (surface_u, surface_v) = match texture_coordinates {
+        None => (u, v),
+        Some([t0, t1, t2]) => (
+            (1.0 - u - v) * t0.0 + u * t1.0 + v * t2.0,
+            (1.0 - u - v) * t0.1 + u * t1.1 + v * t2.1,
+        ),
+    };
+
+    let mut hit = HitRecord {
+        point: ray.at(t),
+        normal: Vec3::default(),
+        t,
+        u: surface_u,
+        v: surface_v,
+        front_face: false,
+        material,
+    };
+    hit.set_face_normal(ray, &geometric_normal);
+
+    // The side is decided by the real surface, the interpolated normal only changes the shading.
+    if let Some([n0, n1, n2]) = normals {
+        let shading_normal = ((1.0 - u - v) * n0 + u * n1 + v * n2).unit_vector();
+        hit.normal = if hit.front_face {
+            shading_normal
+        } else {
+            -shading_normal
+        };
+    }
+
+    hit
+}
+
+impl<Mat: Material> Hittable for Triangle<Mat> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let intersection = intersect_triangle(ray, &self.vertices, t_min, t_max)?;
+        Some(triangle_hit(
+            ray,
+            &self.vertices,
+            self.normals,
+            None,
+            intersection,
+            &self.material,
+        ))
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<Aabb> {
+        Some(triangle_box(&self.vertices))
+    }
+}
+
+pub fn triangle_box(vertices: &[Point3; 3]) -> Aabb {
+    let [v0, v1, v2] = vertices;
+    let padding = Vec3::new(PADDING, PADDING, PADDING);
+    let small = Point3::new(
+        v0.x().min(v1.x()).min(v2.x()),
+        v0.y().min(v1.y()).min(v2.y()),
+        v0.z().min(v1.z()).min(v2.z()),
+    );
+    let big = Point3::new(
+        v0.x().max(v1.x()).max(v2.x()),
+        v0.y().max(v1.y()).max(v2.y()),
+        v0.z().max(v1.z()).max(v2.z()),
+    );
+
+    Aabb::new(small - padding, big + padding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Color, Lambertian};
+
+    fn triangle() -> Triangle<Lambertian> {
+        Triangle::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+            Lambertian::new(&Color::new(1.0, 1.0, 1.0)),
+        )
+    }
+
+    #[test]
+    fn test_hit() {
+        let triangle = triangle();
+        let ray = Ray::new(Point3::new(0.25, 0.25, 2.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+
+        let hit = triangle.hit(&ray, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(2.0, hit.t);
+        assert_eq!(Point3::new(0.25, 0.25, 0.0), hit.point);
+        assert_eq!(Vec3::new(0.0, 0.0, 1.0), hit.normal);
+        assert!(hit.front_face);
+
+        let back = Ray::new(Point3::new(0.25, 0.25, -2.0), Vec3::new(0.0, 0.0, 1.0), 0.0);
+        let hit = triangle.hit(&back, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(Vec3::new(0.0, 0.0, -1.0), hit.normal);
+        assert!(!hit.front_face);
+    }
+
+    #[test]
+    fn test_miss() {
+        let outside = Ray::new(Point3::new(0.75, 0.75, 2.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let parallel = Ray::new(Point3::new(0.25, 0.25, 0.0), Vec3::new(1.0, 0.0, 0.0), 0.0);
+        let behind = Ray::new(Point3::new(0.25, 0.25, 2.0), Vec3::new(0.0, 0.0, 1.0), 0.0);
+
+        assert!(triangle().hit(&outside, 0.001, f64::INFINITY).is_none());
+        assert!(triangle().hit(&parallel, 0.001, f64::INFINITY).is_none());
+        assert!(triangle().hit(&behind, 0.001, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_degenerate() {
+        let line = Triangle::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(2.0, 0.0, 0.0),
+            Lambertian::new(&Color::new(1.0, 1.0, 1.0)),
+        );
+        let ray = Ray::new(Point3::new(0.5, 0.0, 2.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+
+        assert!(line.hit(&ray, 0.001, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_interpolated_normal() {
+        let up = Vec3::new(0.0, 0.0, 1.0);
+        let tilted = Vec3::new(1.0, 0.0, 1.0).unit_vector();
+        let smooth = Triangle::with_normals(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+            [up, tilted, up],
+            Lambertian::new(&Color::new(1.0, 1.0, 1.0)),
+        );
+
+        let at_vertex = Ray::new(Point3::new(1.0, 0.0, 2.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let hit = smooth.hit(&at_vertex, 0.001, f64::INFINITY).unwrap();
+        assert!((hit.normal - tilted).length() < 1e-12);
+
+        let back = Ray::new(Point3::new(0.0, 0.0, -2.0), Vec3::new(0.0, 0.0, 1.0), 0.0);
+        let hit = smooth.hit(&back, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(-up, hit.normal);
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        let expected = Aabb::new(
+            Point3::new(-PADDING, -PADDING, -PADDING),
+            Point3::new(1.0 + PADDING, 1.0 + PADDING, PADDING),
+        );
+        assert_eq!(Some(expected), triangle().bounding_box(0.0, 1.0));
+    }
+}
@@ -1,8 +1,5 @@
-use rand::Rng;
-
-use Vec3 as Color;
-
-use crate::vec3::Vec3;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
 pub fn clamp(x: f64, min: f64, max: f64) -> f64 {
     if x < min {
@@ -16,29 +13,26 @@ pub fn clamp(x: f64, min: f64, max: f64) -> f64 {
     x
 }
 
-pub fn channel_to_rgb(pixel: f64, scale: f64) -> u8 {
-    // Gamma correction is color ^ 1 / gamma value. Here we gamma correct of 2.
-    let gamma_corrected = (pixel * scale).sqrt();
-    (256.0 * clamp(gamma_corrected, 0.0, 0.999)) as u8
+pub fn random_f64(rng: &mut StdRng) -> f64 {
+    rng.gen()
 }
 
-pub fn color_to_rbg(pixel: Color, samples_per_pixel: u32) -> Vec<u8> {
-    let scale = 1.0 / (samples_per_pixel as f64);
-    let result = vec![
-        channel_to_rgb(pixel.x(), scale),
-        channel_to_rgb(pixel.y(), scale),
-        channel_to_rgb(pixel.z(), scale),
-    ];
-
-    result
+pub fn random_f64_range(rng: &mut StdRng, min: f64, max: f64) -> f64 {
+    min + (max - min) * random_f64(rng)
 }
 
-pub fn random_f64() -> f64 {
-    rand::thread_rng().gen()
+// Generator of one sample of one pixel, independent of the others so the image only depends on
+// the seed and not on which thread renders what.
+pub fn sample_rng(seed: u64, pixel: u64, sample: u64) -> StdRng {
+    StdRng::seed_from_u64(mix(mix(mix(seed) ^ pixel) ^ sample))
 }
 
-pub fn random_f64_range(min: f64, max: f64) -> f64 {
-    rand::thread_rng().gen_range(min..max)
+// SplitMix64 finalizer, spreading close inputs to unrelated seeds.
+pub fn mix(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
 }
 
 #[cfg(test)]
@@ -54,8 +48,9 @@ mod tests {
 
     #[test]
     fn random_test() {
+        let mut rng = StdRng::seed_from_u64(1);
         for _ in 0..100 {
-            let random = random_f64();
+            let random = random_f64(&mut rng);
             assert!(0.0 <= random);
             assert!(1.0 >= random);
         }
@@ -63,17 +58,22 @@ mod tests {
 
     #[test]
     fn random_range_test() {
+        let mut rng = StdRng::seed_from_u64(1);
         for _ in 0..100 {
-            let random = random_f64_range(-1.0, 10.0);
+            let random = random_f64_range(&mut rng, -1.0, 10.0);
             assert!(-1.0 <= random);
             assert!(10.0 >= random);
         }
     }
 
     #[test]
-    fn channel_to_rgb_test() {
-        assert_eq!(255, channel_to_rgb(1.0, 1.0));
-        assert_eq!(0, channel_to_rgb(0.0, 1.0));
-        assert_eq!(181, channel_to_rgb(0.5, 1.0)); // With gamma correction of 2: 0.5^1/2 ~= 0.71, 0.71 * 256 ~= 181
+    fn sample_rng_test() {
+        let numbers =
+            |mut rng: StdRng| -> Vec<f64> { (0..10).map(|_| random_f64(&mut rng)).collect() };
+
+        assert_eq!(numbers(sample_rng(7, 3, 1)), numbers(sample_rng(7, 3, 1)));
+        assert_ne!(numbers(sample_rng(7, 3, 1)), numbers(sample_rng(8, 3, 1)));
+        assert_ne!(numbers(sample_rng(7, 3, 1)), numbers(sample_rng(7, 4, 1)));
+        assert_ne!(numbers(sample_rng(7, 3, 1)), numbers(sample_rng(7, 3, 2)));
     }
 }
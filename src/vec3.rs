@@ -2,6 +2,8 @@ use std::fmt::{Display, Formatter};
 use std::iter::Sum;
 use std::ops::{Add, AddAssign, Div, DivAssign, Index, Mul, MulAssign, Neg, Sub};
 
+use rand::rngs::StdRng;
+
 use crate::utils::{random_f64, random_f64_range};
 
 #[derive(Copy, Clone, Default, Debug)]
@@ -36,29 +38,34 @@ impl Vec3 {
         self.length_squared().sqrt()
     }
 
+    // Relative luminance, for a color with linear Rec. 709 primaries.
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.x + 0.7152 * self.y + 0.0722 * self.z
+    }
+
     pub fn unit_vector(&self) -> Vec3 {
         *self / self.length()
     }
 
-    pub fn random() -> Vec3 {
+    pub fn random(rng: &mut StdRng) -> Vec3 {
         Vec3 {
-            x: random_f64(),
-            y: random_f64(),
-            z: random_f64(),
+            x: random_f64(rng),
+            y: random_f64(rng),
+            z: random_f64(rng),
         }
     }
 
-    pub fn random_range(min: f64, max: f64) -> Vec3 {
+    pub fn random_range(rng: &mut StdRng, min: f64, max: f64) -> Vec3 {
         Vec3 {
-            x: random_f64_range(min, max),
-            y: random_f64_range(min, max),
-            z: random_f64_range(min, max),
+            x: random_f64_range(rng, min, max),
+            y: random_f64_range(rng, min, max),
+            z: random_f64_range(rng, min, max),
         }
     }
 
-    pub fn random_in_unit_sphere() -> Vec3 {
+    pub fn random_in_unit_sphere(rng: &mut StdRng) -> Vec3 {
         loop {
-            let result = Vec3::random_range(-1.0, 1.0);
+            let result = Vec3::random_range(rng, -1.0, 1.0);
             if result.length_squared() >= 1.0 {
                 continue;
             }
@@ -66,8 +73,8 @@ impl Vec3 {
         }
     }
 
-    pub fn random_unit_vector() -> Vec3 {
-        Vec3::random_in_unit_sphere().unit_vector()
+    pub fn random_unit_vector(rng: &mut StdRng) -> Vec3 {
+        Vec3::random_in_unit_sphere(rng).unit_vector()
     }
 
     // Return true if the vector is close to zero in all dimensions.
@@ -297,7 +304,7 @@ mod tests {
     fn vec3_array_access_oob() {
         let vec = Vec3::default();
 
-        vec[-1];
+        let _ = vec[-1];
     }
 
     #[test]
@@ -305,7 +312,7 @@ mod tests {
     fn vec3_array_access_oob2() {
         let vec = Vec3::default();
 
-        vec[3];
+        let _ = vec[3];
     }
 
     #[test]
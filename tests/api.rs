@@ -0,0 +1,53 @@
+use ray_tracing_in_one_weekend::sphere::Sphere;
+use ray_tracing_in_one_weekend::{
+    render, Background, BvhNode, CameraSettings, Color, DisplayTransform, HittableList, Lambertian,
+    PathDepth, Point3, RenderOptions, Vec3,
+};
+
+// Rendering a scene built from code, the way other tools embed the renderer.
+#[test]
+fn test_render_embedded_scene() {
+    let mut world = HittableList::new();
+    world.add(Box::new(Sphere::new(
+        Point3::new(0.0, 0.0, -1.0),
+        0.5,
+        Lambertian::new(&Color::new(0.5, 0.5, 0.5)),
+    )));
+    let world = BvhNode::new(world, 0.0, 1.0);
+    // Nothing emits light, the sky lights the scene.
+    let lights = HittableList::new();
+    let camera = CameraSettings {
+        look_from: Point3::new(0.0, 0.0, 1.0),
+        look_at: Point3::new(0.0, 0.0, -1.0),
+        view_up: Vec3::new(0.0, 1.0, 0.0),
+        vertical_field_of_view: 60.0,
+        aperture: 0.0,
+        focus_distance: 2.0,
+        shutter_open_time: 0.0,
+        shutter_close_time: 1.0,
+    }
+    .build(1.0);
+    let mut options = RenderOptions::default();
+    options.image_width = 9;
+    options.image_height = 9;
+    options.samples_per_pixel = 4;
+    options.depth = PathDepth::new(5);
+    options.seed = 3;
+
+    let image = render(&world, &lights, &camera, &Background::Sky, &options)
+        .to_image(&DisplayTransform::default());
+
+    // The sphere in the middle is darker than the sky in the top left corner.
+    let brightness = |index: usize| -> u32 {
+        image.pixels()[3 * index..][..3]
+            .iter()
+            .map(|&value| value as u32)
+            .sum()
+    };
+    assert!(brightness(4 * 9 + 4) < brightness(0));
+    assert_eq!(
+        image,
+        render(&world, &lights, &camera, &Background::Sky, &options)
+            .to_image(&DisplayTransform::default())
+    );
+}
@@ -0,0 +1,57 @@
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn run(directory: &Path, arguments: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_ray-tracing-in-one-weekend"))
+        .current_dir(directory)
+        .args([
+            "--scene-file",
+            "mesh.scene",
+            "--width",
+            "8",
+            "--height",
+            "8",
+        ])
+        .args(["--samples", "2", "--checkpoint", "mesh.ckpt"])
+        .args(arguments)
+        .output()
+        .unwrap()
+}
+
+// A checkpoint is not resumed once a mesh of its scene changed, even though the scene file did not.
+#[test]
+fn test_resume_after_editing_a_mesh() {
+    let directory = Path::new(env!("CARGO_TARGET_TMPDIR")).join("checkpoint_mesh");
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(
+        directory.join("mesh.scene"),
+        "camera look_from=0,0,4 look_at=0,0,0 vfov=40\n\
+         material white lambertian albedo=0.8,0.8,0.8\n\
+         mesh file=triangle.obj material=white\n",
+    )
+    .unwrap();
+    fs::write(
+        directory.join("triangle.obj"),
+        "v -1 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n",
+    )
+    .unwrap();
+
+    let rendered = run(&directory, &[]);
+    assert!(rendered.status.success(), "{rendered:?}");
+    let resumed = run(&directory, &["--samples", "4", "--resume"]);
+    assert!(resumed.status.success(), "{resumed:?}");
+
+    fs::write(
+        directory.join("triangle.obj"),
+        "v -1 0 0\nv 1 0 0\nv 0 2 0\nf 1 2 3\n",
+    )
+    .unwrap();
+    let refused = run(&directory, &["--samples", "8", "--resume"]);
+    assert!(!refused.status.success());
+    let error = String::from_utf8_lossy(&refused.stderr);
+    assert!(
+        error.contains("changed since the checkpoint was saved"),
+        "{error}"
+    );
+}